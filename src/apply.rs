@@ -10,7 +10,7 @@ use std::path::{Path, PathBuf};
 /// callers (mostly tests).
 ///
 /// Algorithm:
-/// 1. Validate the chunks (same path, sorted, non-overlapping, non-zero length).
+/// 1. Validate the chunks (same path, sorted, non-overlapping).
 /// 2. Stream the original file, copying unchanged lines through and substituting
 ///    chunk content for the lines each chunk replaces.
 /// 3. Detect out-of-bounds chunks when the stream reaches EOF.
@@ -40,8 +40,20 @@ pub enum ApplyError {
     #[error("Invalid line number: line numbers must be >= 1")]
     InvalidLineNumber,
 
-    #[error("Invalid number of lines: number of lines must be > 0")]
-    InvalidNumberOfLines,
+    #[error("Chunk at line {line} does not match the file within {tolerance} lines")]
+    LineNumberDrifted { line: usize, tolerance: usize },
+
+    #[error(
+        "{first_input} and {second_input} both edit {} (lines {}-{} and {}-{})",
+        path.display(), first.0, first.1, second.0, second.1
+    )]
+    ConflictingInputs {
+        path: PathBuf,
+        first_input: String,
+        first: (usize, usize),
+        second_input: String,
+        second: (usize, usize),
+    },
 
     #[error("Chunks are not sorted by line number")]
     UnsortedChunks,
@@ -75,13 +87,6 @@ fn chunks_are_all_for_same_path(chunks: &[Chunk]) -> Result<(), ApplyError> {
     Ok(())
 }
 
-fn chunks_are_have_non_zero_length(chunks: &[Chunk]) -> Result<(), ApplyError> {
-    if !chunks.iter().all(|c| c.num_lines > 0) {
-        return Err(ApplyError::InvalidNumberOfLines);
-    }
-    Ok(())
-}
-
 // TODO: use this
 #[allow(dead_code)]
 fn chunks_have_valid_line_numbers(chunks: &[Chunk]) -> Result<(), ApplyError> {
@@ -92,8 +97,9 @@ fn chunks_have_valid_line_numbers(chunks: &[Chunk]) -> Result<(), ApplyError> {
 }
 
 fn chunks_are_sorted_by_line_number(chunks: &[Chunk]) -> Result<(), ApplyError> {
+    // Chunks starting on the same line are sorted, but overlap, see below
     if !chunks.windows(2).all(|w| match w {
-        [c1, c2] => c1.start_line < c2.start_line,
+        [c1, c2] => c1.start_line <= c2.start_line,
         _ => false,
     }) {
         return Err(ApplyError::UnsortedChunks);
@@ -104,14 +110,19 @@ fn chunks_are_sorted_by_line_number(chunks: &[Chunk]) -> Result<(), ApplyError>
 fn chunks_are_not_overlapping(chunks: &[Chunk]) -> Result<(), ApplyError> {
     for window in chunks.windows(2) {
         if let [c1, c2] = window {
-            let c1_end = c1.start_line + c1.num_lines - 1;
-
-            if c1_end > c2.start_line {
+            // The first line after c1; an insertion (no lines) ends where it starts
+            let c1_next = c1.start_line + c1.num_lines;
+            // An insertion is reported as covering the line it goes before
+            let last = |c: &Chunk| c.start_line + c.num_lines.max(1) - 1;
+
+            // Two chunks at one line conflict even if one is an insertion, since
+            // it's ambiguous which goes first
+            if c1_next > c2.start_line || c1.start_line == c2.start_line {
                 return Err(ApplyError::OverlappingChunks(
                     c1.start_line,
-                    c1_end - 1,
+                    last(c1),
                     c2.start_line,
-                    c2.start_line + c2.num_lines - 1,
+                    last(c2),
                 ));
             }
         }
@@ -144,6 +155,10 @@ const STREAM_BUF_SIZE: usize = 64 * 1024;
 /// Stream a file's reconstruction: read the original from `reader`, interleave the
 /// chunk replacements, and write the result to `writer`.
 ///
+/// A chunk with `num_lines == 0` replaces nothing: its content is inserted before
+/// its `start_line`, which may be one past the last line to append to a file that
+/// ends in a newline.
+///
 /// This is the reconstruction core. It uses a single fixed-size read buffer and a
 /// byte-level state machine (copy original lines through, or skip the lines a chunk
 /// replaces), so its memory use is approximately constant per call — independent of
@@ -153,7 +168,7 @@ const STREAM_BUF_SIZE: usize = 64 * 1024;
 ///
 /// # Errors
 /// Returns the accumulated validation errors if the chunks are structurally invalid
-/// (mixed paths, unsorted, overlapping, zero-length), [`ApplyError::ChunkOutOfBounds`]
+/// (mixed paths, unsorted, overlapping), [`ApplyError::ChunkOutOfBounds`]
 /// for any chunk that references lines past EOF, or [`ApplyError::Io`] on a read/write
 /// failure.
 pub fn apply_format_streaming(
@@ -174,7 +189,6 @@ pub fn apply_format_streaming(
             chunks_are_sorted_by_line_number(chunks)
                 .and_then(|()| chunks_are_not_overlapping(chunks)),
         );
-        handle_error(chunks_are_have_non_zero_length(chunks));
     }
     if !errors.is_empty() {
         return Err(errors);
@@ -249,9 +263,11 @@ pub fn apply_format_streaming(
     } else {
         cur_line
     };
-    for chunk in chunks {
+    for (chunk_idx, chunk) in chunks.iter().enumerate() {
         let end_line = chunk.start_line + chunk.num_lines - 1;
-        if end_line > file_lines {
+        // A chunk never reached can only be an insertion after a last line
+        // without a newline, as anything else past EOF fails the first check
+        if end_line > file_lines || chunk_idx >= idx {
             errors.push(ApplyError::ChunkOutOfBounds {
                 line: chunk.start_line,
                 num_lines: chunk.num_lines,
@@ -275,8 +291,7 @@ pub fn apply_format_streaming(
 ///
 /// # Errors
 /// Returns an error if chunks have different paths, are unsorted, overlap, are
-/// zero-length, or reference lines outside the file.
-#[allow(dead_code)]
+/// or reference lines outside the file.
 pub fn apply_format(chunks: &[Chunk], content: &str) -> Result<String, Vec<ApplyError>> {
     if chunks.is_empty() {
         return Ok(content.to_string());
@@ -298,7 +313,6 @@ pub fn apply_format(chunks: &[Chunk], content: &str) -> Result<String, Vec<Apply
             chunks_are_sorted_by_line_number(chunks)
                 .and_then(|()| chunks_are_not_overlapping(chunks)),
         );
-        handle_error(chunks_are_have_non_zero_length(chunks));
     }
     if !errors.is_empty() {
         return Err(errors);
@@ -309,6 +323,132 @@ pub fn apply_format(chunks: &[Chunk], content: &str) -> Result<String, Vec<Apply
     Ok(String::from_utf8(out).expect("reconstruction of UTF-8 input stays UTF-8"))
 }
 
+/// Like [`apply_format`], but tolerates chunks whose line numbers are off by up to
+/// `tolerance` lines (e.g. line 101 written where line 100 was meant).
+///
+/// A chunk stays at its own position unless a region within `tolerance` lines
+/// shares more of the chunk's lines than the region it claims; then the nearest
+/// such region is used instead. A chunk whose position is past the end of the
+/// file must find a region sharing at least one line.
+///
+/// # Errors
+/// Returns [`ApplyError::LineNumberDrifted`] for every chunk with no matching region
+/// within `tolerance`, or the errors from [`apply_format`] for the adjusted chunks.
+#[allow(dead_code)]
+pub fn apply_format_fuzzy(
+    chunks: &[Chunk],
+    content: &str,
+    tolerance: usize,
+) -> Result<String, Vec<ApplyError>> {
+    let file_lines: Vec<&str> = content.split_inclusive('\n').map(trim_eol).collect();
+
+    let mut errors = Vec::new();
+    let mut adjusted = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        match find_drifted_start(chunk, &file_lines, tolerance) {
+            Some(start_line) => adjusted.push(Chunk {
+                start_line,
+                ..chunk.clone()
+            }),
+            None => errors.push(ApplyError::LineNumberDrifted {
+                line: chunk.start_line,
+                tolerance,
+            }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    apply_format(&adjusted, content)
+}
+
+fn trim_eol(line: &str) -> &str {
+    line.trim_end_matches(['\r', '\n'])
+}
+
+/// The start line for `chunk`: its own if no region within `tolerance` lines shares
+/// more of its lines, otherwise the nearest region that does (checking 1 above,
+/// 1 below, 2 above, ...)
+fn find_drifted_start(chunk: &Chunk, file_lines: &[&str], tolerance: usize) -> Option<usize> {
+    let chunk_lines: Vec<&str> = chunk.content.split_inclusive('\n').map(trim_eol).collect();
+
+    // How many of the chunk's lines appear in the original region starting at `start`
+    let score_at = |start: usize| {
+        let from = start.checked_sub(1)?;
+        let region = file_lines.get(from..from + chunk.num_lines)?;
+        Some(
+            chunk_lines
+                .iter()
+                .filter(|line| region.contains(line))
+                .count(),
+        )
+    };
+
+    let mut best = score_at(chunk.start_line).map(|score| (chunk.start_line, score));
+    for offset in 1..=tolerance {
+        let candidates = [
+            chunk.start_line.checked_sub(offset),
+            Some(chunk.start_line + offset),
+        ];
+        for start in candidates.into_iter().flatten() {
+            if let Some(score) = score_at(start)
+                && score > best.map_or(0, |(_, best)| best)
+            {
+                best = Some((start, score));
+            }
+        }
+    }
+    best.map(|(start, _)| start)
+}
+
+/// Combine formats read from several inputs into one, merged with
+/// [`Format::normalize`]
+///
+/// Each input is a `(name, format)` pair; the name is only used in errors.
+/// Chunks from different inputs may touch and are then merged into one, and the
+/// same chunk repeated in several inputs is kept once. Chunks from different
+/// inputs that overlap with different edits conflict, since there's no telling
+/// which edit should win.
+///
+/// # Errors
+/// Returns an [`ApplyError::ConflictingInputs`] for every conflicting pair.
+pub fn merge_inputs(inputs: Vec<(String, Format)>) -> Result<Format, Vec<ApplyError>> {
+    let mut tagged: Vec<(usize, Chunk)> = inputs
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, (_, format))| format.iter().map(move |chunk| (idx, chunk.clone())))
+        .collect();
+    tagged.sort_by(|(_, a), (_, b)| (&a.path, a.start_line).cmp(&(&b.path, b.start_line)));
+
+    let lines = |c: &Chunk| (c.start_line, c.start_line + c.num_lines.saturating_sub(1));
+    let mut errors = Vec::new();
+    for (i, (a_input, a)) in tagged.iter().enumerate() {
+        // Sorted by start, so only the chunks that start before `a` ends can overlap it
+        for (b_input, b) in tagged[i + 1..]
+            .iter()
+            .take_while(|(_, b)| b.path == a.path && a.overlaps(b.start_line, b.num_lines))
+        {
+            if a_input != b_input && a != b {
+                errors.push(ApplyError::ConflictingInputs {
+                    path: a.path.clone(),
+                    first_input: inputs[*a_input].0.clone(),
+                    first: lines(a),
+                    second_input: inputs[*b_input].0.clone(),
+                    second: lines(b),
+                });
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut format = Format(tagged.into_iter().map(|(_, chunk)| chunk).collect());
+    format.normalize();
+    Ok(format)
+}
+
 /// Verify that a parsed format can be applied cleanly, without writing anything.
 ///
 /// This is exactly phase 1 of an atomic apply (and the entire `--dry-run` path): it
@@ -359,11 +499,13 @@ pub fn apply_format_to_fs(format: &mut Format, fs: &dyn FileSystem) -> Result<()
     // Phase 2: stage every file into a tracked temp file, then commit.
     let staging = StagingFs::new(fs);
     let mut errors = Vec::new();
+    let mut applied = Vec::new();
     for (path, chunks) in format.file_chunks() {
         let result = stage_file(&staging, path, chunks);
         if let Err(errs) = result {
             errors.extend(errs);
         }
+        applied.extend(chunks.iter().cloned());
     }
     if !errors.is_empty() {
         // `staging` drops here: every staged temp file is removed, targets untouched.
@@ -374,8 +516,16 @@ pub fn apply_format_to_fs(format: &mut Format, fs: &dyn FileSystem) -> Result<()
         failures
             .into_iter()
             .map(|(path, source)| ApplyError::ModifyError { path, source })
-            .collect()
-    })
+            .collect::<Vec<_>>()
+    })?;
+
+    // The files now hold the chunks' content, so move each chunk to where its
+    // text ended up. Going last to first, every chunk a rebase moves is still at
+    // its original position relative to the chunk being rebased after.
+    for chunk in applied.iter().rev() {
+        format.rebase_after_apply(&chunk.path, chunk, chunk.line_delta());
+    }
+    Ok(())
 }
 
 /// Stream one file's reconstruction into the staging filesystem.
@@ -393,6 +543,150 @@ fn stage_file(
     Ok(())
 }
 
+/// Lines of unchanged context around each hunk of [`apply_format_to_fs_diff`]
+const DIFF_CONTEXT: usize = 3;
+
+/// Render the changes a format would make as a multi-file unified diff, without
+/// writing anything.
+///
+/// The output can be piped to `git apply` or `patch -p1`. Each chunk is compared
+/// with the lines it replaces, and lines it leaves as they were are trimmed, so a
+/// chunk that's only context produces no hunk. Like `diff -u`, hunks get three
+/// lines of context and are joined when their context would overlap.
+///
+/// # Errors
+/// Returns every error [`verify_format_to_fs`] finds, or an error if a file
+/// can't be read.
+#[allow(dead_code)]
+pub fn apply_format_to_fs_diff(
+    format: &mut Format,
+    fs: &dyn FileSystem,
+) -> Result<String, Vec<ApplyError>> {
+    verify_format_to_fs(format, fs)?;
+
+    let mut diff = String::new();
+    for (path, chunks) in format.file_chunks() {
+        let content = fs
+            .read_to_string(path)
+            .map_err(|e| vec![modify_err(path, e)])?;
+        let old: Vec<&str> = content.split_inclusive('\n').collect();
+        let edits: Vec<Edit<'_>> = chunks
+            .iter()
+            .filter_map(|chunk| Edit::new(chunk, &old))
+            .collect();
+        if edits.is_empty() {
+            continue;
+        }
+
+        diff.push_str(&format!("--- a/{}\n", path.display()));
+        diff.push_str(&format!("+++ b/{}\n", path.display()));
+        write_hunks(&mut diff, &old, &edits);
+    }
+    Ok(diff)
+}
+
+/// The lines of a file one chunk really changes, with unchanged lines trimmed
+struct Edit<'a> {
+    /// 0-indexed range of the original lines removed
+    old: std::ops::Range<usize>,
+    /// Lines added in their place
+    new: Vec<&'a str>,
+}
+
+impl<'a> Edit<'a> {
+    /// `None` if the chunk leaves the file as it is
+    fn new(chunk: &'a Chunk, old: &[&str]) -> Option<Self> {
+        let start = chunk.start_line - 1;
+        let mut range = start..(start + chunk.num_lines).min(old.len());
+        let mut new: Vec<&str> = chunk.content.split_inclusive('\n').collect();
+
+        let prefix = old[range.clone()]
+            .iter()
+            .zip(&new)
+            .take_while(|(a, b)| a == b)
+            .count();
+        range.start += prefix;
+        new.drain(..prefix);
+
+        let suffix = old[range.clone()]
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        range.end -= suffix;
+        new.truncate(new.len() - suffix);
+
+        (!range.is_empty() || !new.is_empty()).then_some(Self { old: range, new })
+    }
+}
+
+/// Write one file's `edits` (sorted, non-overlapping) as unified diff hunks
+fn write_hunks(diff: &mut String, old: &[&str], edits: &[Edit<'_>]) {
+    // Lines added minus lines removed by the hunks written so far
+    let mut delta = 0isize;
+    let mut rest = edits;
+    while let Some(first) = rest.first() {
+        let len = 1 + rest
+            .windows(2)
+            .take_while(|pair| pair[1].old.start <= pair[0].old.end + 2 * DIFF_CONTEXT)
+            .count();
+        let (group, tail) = rest.split_at(len);
+        rest = tail;
+
+        let start = first.old.start.saturating_sub(DIFF_CONTEXT);
+        let end = (group[len - 1].old.end + DIFF_CONTEXT).min(old.len());
+        let mut body = String::new();
+        let (mut old_len, mut new_len) = (0, 0);
+        let mut line = start;
+        for edit in group {
+            for context in &old[line..edit.old.start] {
+                push_diff_line(&mut body, ' ', context);
+            }
+            for removed in &old[edit.old.clone()] {
+                push_diff_line(&mut body, '-', removed);
+            }
+            for added in &edit.new {
+                push_diff_line(&mut body, '+', added);
+            }
+            old_len += edit.old.start - line + edit.old.len();
+            new_len += edit.old.start - line + edit.new.len();
+            line = edit.old.end;
+        }
+        for context in &old[line..end] {
+            push_diff_line(&mut body, ' ', context);
+        }
+        old_len += end - line;
+        new_len += end - line;
+
+        let (old_start, old_len) = hunk_range(start, old_len);
+        let (new_start, new_len) = hunk_range(start.saturating_add_signed(delta), new_len);
+        diff.push_str(&format!(
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@\n"
+        ));
+        diff.push_str(&body);
+        delta += new_len as isize - old_len as isize;
+    }
+}
+
+/// The 1-indexed `start,len` of a hunk side; an empty side names the line
+/// before it
+fn hunk_range(start: usize, len: usize) -> (usize, usize) {
+    if len == 0 {
+        (start, 0)
+    } else {
+        (start + 1, len)
+    }
+}
+
+fn push_diff_line(diff: &mut String, marker: char, line: &str) {
+    diff.push(marker);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -484,6 +778,114 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_apply_chunks_sharing_a_line_overlap() {
+        // Lines 2-3 and 3: line 3 can't be replaced twice
+        let content = "line1\nline2\nline3\nline4\n";
+        let chunks = vec![
+            Chunk::new(PathBuf::from("test.txt"), 2, 2, "two\nthree\n".to_string()),
+            Chunk::new(PathBuf::from("test.txt"), 3, 1, "THREE\n".to_string()),
+        ];
+        let result = apply_format(&chunks, content);
+        assert!(matches!(
+            result.as_ref().map_err(|r| r.as_slice()),
+            Err([ApplyError::OverlappingChunks(2, 3, 3, 3)])
+        ));
+    }
+
+    #[test]
+    fn test_apply_insertion_and_replacement_at_one_line_overlap() {
+        let content = "line1\nline2\nline3\n";
+        for chunks in [
+            vec![
+                Chunk::new(PathBuf::from("test.txt"), 2, 0, "new\n".to_string()),
+                Chunk::new(PathBuf::from("test.txt"), 2, 1, "TWO\n".to_string()),
+            ],
+            vec![
+                Chunk::new(PathBuf::from("test.txt"), 2, 1, "TWO\n".to_string()),
+                Chunk::new(PathBuf::from("test.txt"), 2, 0, "new\n".to_string()),
+            ],
+        ] {
+            let result = apply_format(&chunks, content);
+            assert!(matches!(
+                result.as_ref().map_err(|r| r.as_slice()),
+                Err([ApplyError::OverlappingChunks(2, 2, 2, 2)])
+            ));
+        }
+    }
+
+    #[test]
+    fn test_apply_grows_replaced_lines() {
+        let content = "line1\nline2\nline3\nline4\n";
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            2,
+            2,
+            "a\nb\nc\nd\ne\n".to_string(),
+        )];
+        let result = apply_format(&chunks, content).unwrap();
+        assert_eq!(result, "line1\na\nb\nc\nd\ne\nline4\n");
+    }
+
+    #[test]
+    fn test_apply_shrinks_replaced_lines() {
+        let content = "line1\nline2\nline3\nline4\nline5\n";
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            2,
+            3,
+            "only\n".to_string(),
+        )];
+        let result = apply_format(&chunks, content).unwrap();
+        assert_eq!(result, "line1\nonly\nline5\n");
+    }
+
+    #[test]
+    fn test_apply_line_count_changes_keep_later_chunks_in_place() {
+        // Later chunks use the original numbering, however earlier ones resize
+        let content = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let chunks = vec![
+            Chunk::new(PathBuf::from("test.txt"), 1, 3, "one\n".to_string()),
+            Chunk::new(
+                PathBuf::from("test.txt"),
+                4,
+                1,
+                "four\nfour b\nfour c\n".to_string(),
+            ),
+            Chunk::new(PathBuf::from("test.txt"), 5, 2, String::new()),
+            Chunk::new(PathBuf::from("test.txt"), 8, 1, "eight\n".to_string()),
+        ];
+        let result = apply_format(&chunks, content).unwrap();
+        assert_eq!(result, "one\nfour\nfour b\nfour c\n7\neight\n");
+    }
+
+    #[test]
+    fn test_apply_resizes_last_line_without_newline() {
+        let content = "line1\nline2\nline3";
+
+        // Grow: the last two lines become three, still without a final newline
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            2,
+            2,
+            "a\nb\nc".to_string(),
+        )];
+        assert_eq!(apply_format(&chunks, content).unwrap(), "line1\na\nb\nc");
+
+        // Shrink: the last three lines become one
+        let chunks = vec![Chunk::new(PathBuf::from("test.txt"), 1, 3, "x".to_string())];
+        assert_eq!(apply_format(&chunks, content).unwrap(), "x");
+
+        // Shrink, adding the final newline
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            2,
+            2,
+            "end\n".to_string(),
+        )];
+        assert_eq!(apply_format(&chunks, content).unwrap(), "line1\nend\n");
+    }
+
     #[test]
     fn test_apply_chunk_out_of_bounds() {
         let content = "line1\nline2\nline3";
@@ -529,6 +931,77 @@ mod tests {
 
     // ---- streaming-core tests --------------------------------------------------
 
+    #[test]
+    fn test_apply_fuzzy_corrects_drift() {
+        let content = "a\nb\nc\nd\ne\nf\ng\n";
+        // Meant to target lines 3-5 (c..e) but claims to start at line 5
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            5,
+            3,
+            "c\nD\ne\n".to_string(),
+        )];
+
+        // Applied as-is, the chunk lands two lines too low
+        assert_eq!(
+            apply_format(&chunks, content).unwrap(),
+            "a\nb\nc\nd\nc\nD\ne\n"
+        );
+
+        let result = apply_format_fuzzy(&chunks, content, 2).unwrap();
+        assert_eq!(result, "a\nb\nc\nD\ne\nf\ng\n");
+    }
+
+    #[test]
+    fn test_apply_fuzzy_prefers_exact_position() {
+        let content = "x\ny\nx\ny\n";
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            3,
+            2,
+            "x\nY\ny\n".to_string(),
+        )];
+
+        let result = apply_format_fuzzy(&chunks, content, 2).unwrap();
+        assert_eq!(result, "x\ny\nx\nY\ny\n");
+    }
+
+    #[test]
+    fn test_apply_fuzzy_reports_drift_beyond_tolerance() {
+        // Past the end of the file, and no line within 2 lines is "b"
+        let content = "a\nb\nc\nd\ne\nf\ng\n";
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            9,
+            1,
+            "b\n".to_string(),
+        )];
+
+        let errors = apply_format_fuzzy(&chunks, content, 2).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [ApplyError::LineNumberDrifted {
+                line: 9,
+                tolerance: 2
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_apply_fuzzy_keeps_in_place_edit_of_every_line() {
+        // A `-C 0` edit shares no line with the region it replaces
+        let content = "a\nb\nc\nd\n";
+        let chunks = vec![Chunk::new(
+            PathBuf::from("test.txt"),
+            3,
+            1,
+            "C\n".to_string(),
+        )];
+
+        let result = apply_format_fuzzy(&chunks, content, 2).unwrap();
+        assert_eq!(result, "a\nb\nC\nd\n");
+    }
+
     fn stream(chunks: &[Chunk], input: &str) -> Result<String, Vec<ApplyError>> {
         let mut out = Vec::new();
         apply_format_streaming(chunks, input.as_bytes(), &mut out)?;
@@ -570,6 +1043,27 @@ mod tests {
         assert_eq!(stream(&chunks, &big).unwrap(), "Y");
     }
 
+    #[test]
+    fn test_stream_inserts_zero_line_chunks() {
+        let chunks = vec![
+            Chunk::new(PathBuf::from("f"), 1, 0, "top\n".to_string()),
+            Chunk::new(PathBuf::from("f"), 2, 1, "B\n".to_string()),
+            Chunk::new(PathBuf::from("f"), 3, 0, "after b\n".to_string()),
+            Chunk::new(PathBuf::from("f"), 4, 0, "end\n".to_string()),
+        ];
+        assert_eq!(
+            stream(&chunks, "a\nb\nc\n").unwrap(),
+            "top\na\nB\nafter b\nc\nend\n"
+        );
+
+        // There's no line to insert after the last one until it ends in a newline
+        let err = stream(&chunks[3..], "a\nb\nc").unwrap_err();
+        assert!(matches!(
+            err.as_slice(),
+            [ApplyError::ChunkOutOfBounds { line: 4, .. }]
+        ));
+    }
+
     #[test]
     fn test_stream_eof_mid_chunk_is_out_of_bounds() {
         // File has 3 lines; chunk wants to replace lines 3..=4.
@@ -585,6 +1079,69 @@ mod tests {
 
     use crate::filesystem::memory::MemoryFS;
 
+    #[test]
+    fn test_apply_to_fs_diff_two_files() {
+        let fs = MemoryFS::new();
+        let a = PathBuf::from("src/a.rs");
+        let b = PathBuf::from("src/b.rs");
+        fs.add_file(&a, "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n").unwrap();
+        fs.add_file(&b, "x\ny").unwrap();
+
+        let mut format = Format(vec![
+            // Only line 6 changes; lines 4-5 and 7 are unchanged context
+            Chunk::new(a.clone(), 4, 4, "4\n5\nsix\n7\n".to_string()),
+            Chunk::new(a.clone(), 9, 1, "9\nnine and a half\n".to_string()),
+            Chunk::new(b.clone(), 2, 1, "why\n".to_string()),
+        ]);
+        let diff = apply_format_to_fs_diff(&mut format, &fs).unwrap();
+
+        assert_eq!(
+            diff,
+            "\
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -3,8 +3,9 @@
+ 3
+ 4
+ 5
+-6
++six
+ 7
+ 8
+ 9
++nine and a half
+ 10
+--- a/src/b.rs
++++ b/src/b.rs
+@@ -1,2 +1,2 @@
+ x
+-y
+\\ No newline at end of file
++why
+"
+        );
+        // Nothing was written
+        assert_eq!(fs.read_to_string(&b).unwrap(), "x\ny");
+    }
+
+    #[test]
+    fn test_apply_to_fs_diff_separate_hunks_track_offsets() {
+        let fs = MemoryFS::new();
+        let a = PathBuf::from("a.txt");
+        let content: String = (1..=20).map(|n| format!("{n}\n")).collect();
+        fs.add_file(&a, &content).unwrap();
+
+        let mut format = Format(vec![
+            Chunk::new(a.clone(), 2, 1, String::new()),
+            Chunk::new(a.clone(), 18, 1, "18\n18b\n".to_string()),
+            // Unchanged chunks produce no hunk
+            Chunk::new(a.clone(), 10, 1, "10\n".to_string()),
+        ]);
+        let diff = apply_format_to_fs_diff(&mut format, &fs).unwrap();
+        let headers: Vec<_> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(headers, vec!["@@ -1,5 +1,4 @@", "@@ -16,5 +15,6 @@"]);
+    }
+
     #[test]
     fn test_apply_to_fs_multi_file_happy_path() {
         let fs = MemoryFS::new();
@@ -606,6 +1163,33 @@ mod tests {
         assert_eq!(fs.file_count(), 2);
     }
 
+    #[test]
+    fn test_apply_to_fs_rebases_later_chunks() {
+        let fs = MemoryFS::new();
+        let a = PathBuf::from("/a.txt");
+        let b = PathBuf::from("/b.txt");
+        fs.add_file(&a, "1\n2\n3\n4\n5\n6\n").unwrap();
+        fs.add_file(&b, "1\n2\n3\n").unwrap();
+
+        let mut format = Format(vec![
+            Chunk::new(a.clone(), 2, 1, "2\n2a\n2b\n2c\n".to_string()),
+            Chunk::new(a.clone(), 5, 1, "five\n".to_string()),
+            Chunk::new(b.clone(), 3, 1, "three\n".to_string()),
+        ]);
+        apply_format_to_fs(&mut format, &fs).unwrap();
+
+        // Adding 3 lines at line 2 moves line 5 of a.txt to line 8; b.txt is separate
+        let starts: Vec<_> = format.iter().map(|c| c.start_line).collect();
+        assert_eq!(starts, vec![2, 8, 3]);
+        let lines: Vec<String> = fs
+            .read_to_string(&a)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(lines[7], "five");
+    }
+
     #[test]
     fn test_apply_to_fs_is_atomic_across_files() {
         let fs = MemoryFS::new();
@@ -645,4 +1229,62 @@ mod tests {
         assert!(verify_format_to_fs(&mut bad, &fs).is_err());
         assert_eq!(fs.read_to_string(&a).unwrap(), "a1\na2\n");
     }
+
+    #[test]
+    fn test_apply_to_fs_commit_failure_is_reported_and_cleaned_up() {
+        let fs = MemoryFS::new();
+        let a = PathBuf::from("/a.txt");
+        fs.add_file(&a, "a1\na2\n").unwrap();
+        // Staging into the temp file works, but moving it onto the target fails.
+        fs.fail_write(&a);
+
+        let mut format = Format(vec![Chunk::new(a.clone(), 1, 1, "A1\n".to_string())]);
+        let errors = apply_format_to_fs(&mut format, &fs).unwrap_err();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [ApplyError::ModifyError { path, .. }] if *path == a
+        ));
+        assert_eq!(fs.read_to_string(&a).unwrap(), "a1\na2\n");
+        // The staged temp file was removed.
+        assert_eq!(fs.file_count(), 1);
+    }
+
+    fn input(name: &str, src: &str) -> (String, Format) {
+        (name.to_string(), src.parse().unwrap())
+    }
+
+    #[test]
+    fn test_merge_inputs_non_overlapping_same_file() {
+        let format = merge_inputs(vec![
+            input("a.bk", "@f.txt:4:1\nFOUR\n@@@\n"),
+            input("b.bk", "@f.txt:1:2\nONE\nTWO\n@@@\n"),
+        ])
+        .unwrap();
+        assert_eq!(format.len(), 2);
+
+        let content = "1\n2\n3\n4\n";
+        let result = apply_format(&format.0, content).unwrap();
+        assert_eq!(result, "ONE\nTWO\n3\nFOUR\n");
+    }
+
+    #[test]
+    fn test_merge_inputs_reports_every_conflict() {
+        let errors = merge_inputs(vec![
+            input("a.bk", "@f.txt:1:3\nx\ny\nz\n@@@\n@g.txt:5:1\nfive\n@@@\n"),
+            input("b.bk", "@f.txt:3:2\nZ\nw\n@@@\n@g.txt:5:1\nfive\n@@@\n"),
+            input("c.bk", "@g.txt:5:1\nFIVE\n@@@\n"),
+        ])
+        .unwrap_err();
+
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "a.bk and b.bk both edit f.txt (lines 1-3 and 3-4)",
+                "a.bk and c.bk both edit g.txt (lines 5-5 and 5-5)",
+                "b.bk and c.bk both edit g.txt (lines 5-5 and 5-5)",
+            ]
+        );
+    }
 }
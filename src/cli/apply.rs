@@ -1,10 +1,12 @@
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 
 use clap::Args;
 
-use crate::apply::{apply_format_to_fs, verify_format_to_fs};
-use crate::filesystem;
+use crate::apply::{
+    ApplyError, apply_format, apply_format_to_fs, merge_inputs, verify_format_to_fs,
+};
+use crate::filesystem::{self, FileSystem};
 use crate::format::Format;
 
 #[derive(Args, Debug)]
@@ -15,9 +17,8 @@ into the right place in each file. Text outside chunks is ignored, so notes and
 comments you leave in the file are harmless.
 
 Before writing, every chunk is validated together (errors are reported all at
-once, not one at a time): chunks must stay sorted, must not overlap, must point
-at lines that exist in the file, and must have a non-zero length. If anything
-fails, nothing is written.
+once, not one at a time): chunks must stay sorted, must not overlap, and must
+point at lines that exist in the file. If anything fails, nothing is written.
 
 THE CHUNK FORMAT:
   @path/to/file.rs:<start-line>:<num-lines>
@@ -26,8 +27,11 @@ THE CHUNK FORMAT:
 
   * Use `@@@-` instead of `@@@` to mean \"no trailing newline at end of file\".
   * Inside content, write `\\@` for a literal `@` and `\\\\` for a literal `\\`.
+    A literal `@@@` is written `\\@@@`.
   * You may add, remove, or change lines freely inside a chunk — the line count
     in the header describes the ORIGINAL lines being replaced.
+  * A line count of 0 replaces nothing: the content is inserted before
+    <start-line> (one past the last line appends to the file).
 
 EXAMPLES:
   # preview what would change, without touching anything
@@ -36,35 +40,79 @@ EXAMPLES:
   # apply the edits from a file
   bulked apply --input edits.bk
 
+  # check that saved search output still matches the files, before editing it
+  bulked apply --input edits.bk --verify
+
   # apply edits straight from a pipe
-  bulked ingest locations.csv | my-edit-script | bulked apply")]
+  bulked ingest locations.csv | my-edit-script | bulked apply
+
+  # apply a patch from `git diff` (a/ and b/ prefixes stripped, like -p1)
+  git diff > change.patch
+  bulked apply --patch change.patch
+
+  # apply several edit files at once (adjacent chunks are merged)
+  bulked apply -i renames.bk -i docs.bk
+  bulked apply renames.bk docs.bk
+
+  # editor filter: transform a buffer on stdin, print the result, touch no files
+  bulked apply --input edits.bk --in-place-stdin < src/main.rs > /tmp/main.rs")]
 pub(super) struct ApplyArgs {
-    /// Edited chunk file to apply (reads from stdin if not specified)
-    #[arg(short, long)]
-    input: Option<PathBuf>,
+    /// Edited chunk files to apply (repeatable; reads from stdin if none are
+    /// given, '-' also means stdin)
+    #[arg(short, long = "input", value_name = "INPUT")]
+    inputs: Vec<PathBuf>,
+
+    /// More edited chunk files to apply, same as --input
+    #[arg(value_name = "FILES")]
+    files: Vec<PathBuf>,
+
+    /// Apply a unified diff (from `git diff` or `diff -u`) instead of chunks
+    /// ('-' means stdin). Hunks must match the files exactly: they aren't fuzzed
+    /// or moved
+    #[arg(short, long, value_name = "PATCH", conflicts_with_all = ["inputs", "files"])]
+    patch: Option<PathBuf>,
+
+    /// Remove N leading components from the file names in --patch, like
+    /// `patch -p` (the default, 1, suits `git diff`)
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "patch")]
+    strip: usize,
 
     /// Validate and report what would change, without writing any files
     #[arg(short, long)]
     dry_run: bool,
+
+    /// Read one file's original content from stdin and print the edited content
+    /// to stdout instead of touching the filesystem. The format (from --input)
+    /// must reference a single path.
+    #[arg(long, requires = "inputs", conflicts_with = "dry_run")]
+    in_place_stdin: bool,
+
+    /// Check that every chunk still matches the lines it covers in its file
+    /// (e.g. that saved search output isn't stale), without writing any files
+    #[arg(long, conflicts_with_all = ["dry_run", "in_place_stdin"])]
+    verify: bool,
 }
 
 impl ApplyArgs {
     pub fn handle(self) -> Result<(), super::Error> {
-        // Read format from input file or stdin
-        let input = match self.input {
-            Some(path) => std::fs::read_to_string(&path)?,
-            None => {
-                let mut buffer = String::new();
-                io::stdin().read_to_string(&mut buffer)?;
-                buffer
-            }
+        let mut format = match &self.patch {
+            Some(patch) => read_patch(patch, self.strip)?,
+            None => self.read_inputs()?,
         };
 
-        // Parse the format
-        let mut format = input.parse::<Format>()?;
+        if self.in_place_stdin {
+            let mut original = String::new();
+            io::stdin().read_to_string(&mut original)?;
+            let edited = apply_to_content(&mut format, &original)?;
+            io::stdout().write_all(edited.as_bytes())?;
+            return Ok(());
+        }
 
-        let fs = filesystem::physical::PhysicalFS;
-        if self.dry_run {
+        let fs = filesystem::physical::PhysicalFS::new();
+        if self.verify {
+            verify_unchanged(&format, &fs)?;
+            println!("All {} chunks match their files", format.len());
+        } else if self.dry_run {
             // Phase 1 only: verify every file (reads + reconstructs, writes nothing).
             verify_format_to_fs(&mut format, &fs).map_err(super::Error::ApplyMultiple)?;
             format.file_chunks().into_iter().for_each(|(path, chunks)| {
@@ -77,4 +125,207 @@ impl ApplyArgs {
 
         Ok(())
     }
+
+    /// Read and combine every format from its input file, or stdin
+    fn read_inputs(&self) -> Result<Format, super::Error> {
+        let paths: Vec<&PathBuf> = self.inputs.iter().chain(&self.files).collect();
+        let mut sources = Vec::with_capacity(paths.len().max(1));
+        if paths.is_empty() {
+            sources.push(("<stdin>".to_string(), read_stdin()?));
+        }
+        for path in paths {
+            if path.as_os_str() == "-" {
+                sources.push(("<stdin>".to_string(), read_stdin()?));
+            } else {
+                let content = std::fs::read_to_string(path)?;
+                sources.push((path.display().to_string(), content));
+            }
+        }
+        parse_formats(&sources)
+    }
+}
+
+/// Read a unified diff from `path` ('-' for stdin) as a format
+fn read_patch(path: &Path, strip: usize) -> Result<Format, super::Error> {
+    let diff = if path.as_os_str() == "-" {
+        read_stdin()?
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    Ok(Format::from_unified_diff(&diff, strip)?)
+}
+
+fn read_stdin() -> Result<String, super::Error> {
+    let mut buffer = String::new();
+    io::stdin().read_to_string(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Parse each `(name, content)` source as a format and combine them into one
+///
+/// With several sources, they're merged by [`merge_inputs`]: touching chunks
+/// become one, and overlapping edits from different sources are an error.
+fn parse_formats(sources: &[(String, String)]) -> Result<Format, super::Error> {
+    if let [(_, source)] = sources {
+        return Ok(source.parse()?);
+    }
+
+    let mut inputs = Vec::with_capacity(sources.len());
+    for (name, source) in sources {
+        inputs.push((name.clone(), source.parse::<Format>()?));
+    }
+    merge_inputs(inputs).map_err(super::Error::ApplyMultiple)
+}
+
+/// Fail with every chunk of `format` that no longer matches its file
+fn verify_unchanged(format: &Format, fs: &dyn FileSystem) -> Result<(), super::Error> {
+    let errors = format.verify_against_fs(fs);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(super::Error::VerifyMultiple(errors))
+    }
+}
+
+/// Apply a single-file `format` to that file's `original` content
+fn apply_to_content(format: &mut Format, original: &str) -> Result<String, super::Error> {
+    let files = format.file_chunks();
+    let [(_, chunks)] = files.as_slice() else {
+        return Err(ApplyError::MixedPaths.into());
+    };
+    apply_format(chunks, original).map_err(super::Error::ApplyMultiple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystem;
+    use crate::filesystem::memory::MemoryFS;
+    use std::path::Path;
+
+    fn source(name: &str, content: &str) -> (String, String) {
+        (name.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_multiple_inputs_conflict() {
+        let err = parse_formats(&[
+            source("one.bk", "@a.txt:1:2\nx\ny\n@@@\n"),
+            source("two.bk", "@a.txt:2:1\nY\n@@@\n"),
+        ])
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            super::super::Error::ApplyMultiple(ref errors)
+                if matches!(errors.as_slice(), [ApplyError::ConflictingInputs { .. }])
+        ));
+    }
+
+    #[test]
+    fn test_multiple_inputs_apply_to_each_path() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("a.txt"), "a1\na2\n").unwrap();
+        fs.add_file(Path::new("b.txt"), "b1\nb2\n").unwrap();
+
+        let mut format = parse_formats(&[
+            source("one.bk", "@a.txt:1:1\nA1\n@@@\n"),
+            source("two.bk", "@b.txt:2:1\nB2\n@@@\n"),
+        ])
+        .unwrap();
+        apply_format_to_fs(&mut format, &fs).unwrap();
+
+        assert_eq!(fs.read_to_string(Path::new("a.txt")).unwrap(), "A1\na2\n");
+        assert_eq!(fs.read_to_string(Path::new("b.txt")).unwrap(), "b1\nB2\n");
+    }
+
+    #[test]
+    fn test_multiple_inputs_for_one_path_are_merged() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("a.txt"), "1\n2\n3\n4\n").unwrap();
+
+        let mut format = parse_formats(&[
+            source("one.bk", "@a.txt:3:2\nthree\nfour\n@@@\n"),
+            source("two.bk", "@a.txt:1:2\none\ntwo\n@@@\n"),
+        ])
+        .unwrap();
+        assert_eq!(format.len(), 1);
+        assert_eq!(format.0[0].num_lines, 4);
+
+        apply_format_to_fs(&mut format, &fs).unwrap();
+        assert_eq!(
+            fs.read_to_string(Path::new("a.txt")).unwrap(),
+            "one\ntwo\nthree\nfour\n"
+        );
+    }
+
+    #[test]
+    fn test_verify_unchanged_reports_stale_chunks() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("a.txt"), "a1\na2\n").unwrap();
+
+        let fresh = parse_formats(&[source("one.bk", "@a.txt:1:2\na1\na2\n@@@\n")]).unwrap();
+        verify_unchanged(&fresh, &fs).unwrap();
+
+        fs.add_file(Path::new("a.txt"), "a1\nA2\n").unwrap();
+        let err = verify_unchanged(&fresh, &fs).unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::VerifyMultiple(ref errors) if errors.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_patch_applies_to_each_file() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("src/a.rs"), "fn a() {\n    one();\n}\n")
+            .unwrap();
+        fs.add_file(Path::new("b.txt"), "1\n2\n3\n").unwrap();
+
+        let diff = "\
+diff --git a/src/a.rs b/src/a.rs
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,3 +1,3 @@
+ fn a() {
+-    one();
++    two();
+ }
+diff --git a/b.txt b/b.txt
+--- a/b.txt
++++ b/b.txt
+@@ -2,0 +3 @@
++2.5
+";
+        let mut format = Format::from_unified_diff(diff, 1).unwrap();
+        apply_format_to_fs(&mut format, &fs).unwrap();
+
+        assert_eq!(
+            fs.read_to_string(Path::new("src/a.rs")).unwrap(),
+            "fn a() {\n    two();\n}\n"
+        );
+        assert_eq!(
+            fs.read_to_string(Path::new("b.txt")).unwrap(),
+            "1\n2\n2.5\n3\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_to_content_single_file() {
+        let mut format: Format = "@main.rs:2:1\nEDITED\n@@@\n".parse().unwrap();
+
+        let edited = apply_to_content(&mut format, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(edited, "one\nEDITED\nthree\n");
+    }
+
+    #[test]
+    fn test_apply_to_content_rejects_multiple_paths() {
+        let mut format: Format = "@a.rs:1:1\nA\n@@@\n@b.rs:1:1\nB\n@@@\n".parse().unwrap();
+
+        let err = apply_to_content(&mut format, "one\n").unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::Apply(ApplyError::MixedPaths)
+        ));
+    }
 }
@@ -9,12 +9,47 @@ pub enum Error {
     #[error(transparent)]
     Format(#[from] crate::format::types::FormatError),
 
+    #[error(transparent)]
+    Diff(#[from] crate::format::diff::DiffError),
+
     #[error(transparent)]
     Json(#[from] serde_json::Error),
 
     #[error(transparent)]
     Csv(#[from] csv::Error),
 
+    #[cfg(feature = "yaml")]
+    #[error(transparent)]
+    Yaml(#[from] serde_norway::Error),
+
+    #[cfg(not(feature = "yaml"))]
+    #[error("bulked was built without the `yaml` feature, so yaml input can't be read")]
+    YamlUnsupported,
+
+    #[error("--path-field and --line-field only apply to yaml input")]
+    FieldsWithoutYaml,
+
+    #[cfg(feature = "yaml")]
+    #[error("yaml record {index} has no `{field}` field")]
+    YamlMissingField { index: usize, field: String },
+
+    #[error("invalid record on input line {line}: {source}")]
+    ParseRecord {
+        line: usize,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error(
+        "invalid line number {value} for {}: line numbers are whole numbers starting at 1",
+        .0.display(),
+        value = .1
+    )]
+    InvalidLineNumber(std::path::PathBuf, String),
+
+    #[error("record for {} has neither a line nor a byte_offset", .0.display())]
+    MissingLocation(std::path::PathBuf),
+
     #[error("csv does not contain the right headers. It must be at least path,line_number")]
     CsvMissingHeaders,
 
@@ -24,6 +59,17 @@ pub enum Error {
     #[error("csv coould not parse {0}")]
     CsvCouldNotParse(&'static str),
 
+    #[error("no patterns found in {}", .0.display())]
+    NoPatterns(std::path::PathBuf),
+
+    #[error("'-' (stdin) must be the only path to search")]
+    StdinWithPaths,
+
+    #[error(
+        "{0} output can't be applied back, so it needs --plain, --heading, --json, --count, --only-files or --format csv"
+    )]
+    UneditableOutput(&'static str),
+
     #[error(transparent)]
     Execute(#[from] crate::execute::ExecuteError),
 
@@ -35,9 +81,12 @@ pub enum Error {
 
     #[error("Failed to apply changes:\n{}", format_apply_errors(.0))]
     ApplyMultiple(Vec<crate::apply::ApplyError>),
+
+    #[error("Chunks don't match their files:\n{}", format_apply_errors(.0))]
+    VerifyMultiple(Vec<crate::format::ChunkVerificationError>),
 }
 
-fn format_apply_errors(errors: &[crate::apply::ApplyError]) -> String {
+fn format_apply_errors(errors: &[impl std::fmt::Display]) -> String {
     errors
         .iter()
         .map(|e| format!("  - {}", e))
@@ -1,7 +1,8 @@
 use std::{
     collections::VecDeque,
     io::{BufRead, BufReader, BufWriter, IsTerminal, Read, Write},
-    path::PathBuf,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -13,6 +14,24 @@ enum Format {
     Json,
     Csv,
     Grep,
+    Yaml,
+}
+
+/// Where a YAML record keeps its path and line, as dotted paths of keys (or
+/// list indices) from the record, e.g. `location.range.start.line`
+#[derive(Debug, Clone)]
+struct FieldSelector {
+    path: String,
+    line: String,
+}
+
+impl Default for FieldSelector {
+    fn default() -> Self {
+        Self {
+            path: "path".to_string(),
+            line: "line".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -23,9 +42,13 @@ enum FormatOptions {
 }
 
 impl FormatOptions {
-    fn parse<R: Read>(self, mut r: R) -> impl Iterator<Item = Result<IngestRecord, super::Error>> {
+    fn parse<R: Read>(
+        self,
+        mut r: R,
+        fields: Option<&FieldSelector>,
+    ) -> impl Iterator<Item = Result<IngestRecord, super::Error>> {
         if let Self::Format(format) = self {
-            return EitherIter::Right(EitherIter::Left(format.parse(r)));
+            return EitherIter::Right(EitherIter::Left(format.parse(r, fields)));
         }
 
         let mut total = 0;
@@ -50,7 +73,7 @@ impl FormatOptions {
 
         let guess = Format::guess(&cur[..total]);
         let buf: VecDeque<u8> = VecDeque::from(cur[..total].to_vec());
-        EitherIter::Right(EitherIter::Right(guess.parse(buf.chain(r))))
+        EitherIter::Right(EitherIter::Right(guess.parse(buf.chain(r), fields)))
     }
 }
 
@@ -61,6 +84,7 @@ impl ValueEnum for FormatOptions {
             FormatOptions::Format(Format::Json),
             FormatOptions::Format(Format::Grep),
             FormatOptions::Format(Format::Csv),
+            FormatOptions::Format(Format::Yaml),
             FormatOptions::Auto,
         ]
     }
@@ -79,6 +103,9 @@ impl ValueEnum for FormatOptions {
             Self::Format(Format::Csv) => {
                 clap::builder::PossibleValue::new("csv").help("parse as a csv")
             }
+            Self::Format(Format::Yaml) => {
+                clap::builder::PossibleValue::new("yaml").help("parse as a yaml list")
+            }
             Self::Auto => clap::builder::PossibleValue::new("auto").help("auto-detect format"),
         })
     }
@@ -111,11 +138,29 @@ where
     }
 }
 
+/// Attach the 1-indexed input `line` a record failed to parse on
+fn at_line(line: usize) -> impl FnOnce(super::Error) -> super::Error {
+    move |source| super::Error::ParseRecord {
+        line,
+        source: Box::new(source),
+    }
+}
+
+/// Check that a line number read as `value` is a positive integer that fits a
+/// `usize` (line numbers are 1-indexed)
+fn validate_line(path: &Path, value: &str) -> Result<NonZeroUsize, super::Error> {
+    value
+        .parse()
+        .map_err(|_| super::Error::InvalidLineNumber(path.to_path_buf(), value.to_string()))
+}
+
 impl Format {
     fn parse_jsonl<R: Read>(r: R) -> impl Iterator<Item = Result<IngestRecord, super::Error>> {
-        BufReader::new(r).lines().map(|r| {
-            let line = r?;
-            Ok(serde_json::from_str(&line)?)
+        BufReader::new(r).lines().enumerate().map(|(idx, r)| {
+            let parse = || -> Result<IngestRecord, super::Error> {
+                serde_json::from_str::<JsonRecord>(&r?)?.try_into()
+            };
+            parse().map_err(at_line(idx + 1))
         })
     }
 
@@ -125,7 +170,11 @@ impl Format {
             .read_to_string(&mut content)
             .map_err(Into::into)
             .map(move |_| content)
-            .and_then(|content| Ok(serde_json::from_str(&content)?));
+            .and_then(|content| {
+                serde_json::from_str::<Vec<JsonRecord>>(&content)
+                    .map_err(|e| at_line(e.line())(e.into()))
+            })
+            .and_then(|records| records.into_iter().map(TryInto::try_into).collect());
 
         match res {
             Ok(v) => EitherIter::Left(v.into_iter().map(Ok)),
@@ -198,66 +247,124 @@ impl Format {
                 .ok()
                 .cloned()
                 .ok_or(super::Error::CsvMissingHeaders)?;
-            let r = r.map_err(super::Error::Csv)?;
-
-            Ok(IngestRecord {
-                path: PathBuf::from(
-                    r.get(headers.file_path)
-                        .ok_or(super::Error::CsvMissingFields("file path"))?
-                        .to_string(),
-                ),
-                line: r
-                    .get(headers.line_number)
-                    .ok_or(super::Error::CsvMissingFields("line number"))?
-                    .parse()
-                    .map_err(|_| super::Error::CsvCouldNotParse("line number"))?,
-            })
+            let r = r.map_err(|e| {
+                let line = e.position().map_or(0, |p| p.line() as usize);
+                at_line(line)(super::Error::Csv(e))
+            })?;
+            let line = r.position().map_or(0, |p| p.line() as usize);
+            let field = |idx, name| {
+                r.get(idx)
+                    .ok_or(super::Error::CsvMissingFields(name))
+                    .map_err(at_line(line))
+            };
+
+            let path = PathBuf::from(field(headers.file_path, "file path")?);
+            let value = field(headers.line_number, "line number")?.trim();
+            // Numbers (even `-1`, `2.5` or `NaN`) get the line number error below
+            if value.parse::<f64>().is_err() {
+                return Err(at_line(line)(super::Error::CsvCouldNotParse("line number")));
+            }
+            let line = validate_line(&path, value).map_err(at_line(line))?;
+            Ok(IngestRecord::at_line(path, line))
         })
     }
 
     fn parse_grep<R: Read>(r: R) -> impl Iterator<Item = Result<IngestRecord, super::Error>> {
-        BufReader::new(r).lines().filter_map(|r| {
-            r.map(|line| {
-                let line_no_split = line
-                    .split_inclusive(':')
-                    .scan(0usize, |bytes, c| {
-                        let prev = *bytes;
-                        *bytes += c.len();
-                        Some((prev, c))
-                    })
-                    .find_map(|(pos, st)| match st.chars().nth(1)? {
-                        '0'..='9' => Some(pos),
-                        _ => None,
-                    })?;
-
-                let file = &line[..line_no_split - 1];
-                let nums = line[line_no_split..]
-                    .split_once(|c: char| !c.is_numeric())?
-                    .0;
-
-                let line_no: usize = nums.parse().ok()?;
-                let file = PathBuf::from_str(file).ok()?;
-                Some(IngestRecord {
-                    path: file,
-                    line: line_no,
-                })
+        BufReader::new(r)
+            .lines()
+            .enumerate()
+            .filter_map(|(idx, r)| {
+                let record = r.map(|line| {
+                    let line_no_split = line
+                        .split_inclusive(':')
+                        .scan(0usize, |bytes, c| {
+                            let prev = *bytes;
+                            *bytes += c.len();
+                            Some((prev, c))
+                        })
+                        .find_map(|(pos, st)| match st.chars().nth(1)? {
+                            '0'..='9' => Some(pos),
+                            _ => None,
+                        })?;
+
+                    let file = &line[..line_no_split - 1];
+                    let nums = line[line_no_split..]
+                        .split_once(|c: char| !c.is_numeric())?
+                        .0;
+
+                    let file = PathBuf::from_str(file).ok()?;
+                    // Only digits get here, so this rejects `0` and overflow
+                    Some(validate_line(&file, nums).map(|line| IngestRecord::at_line(file, line)))
+                });
+                match record {
+                    Ok(record) => record.map(|r| r.map_err(at_line(idx + 1))),
+                    Err(e) => Some(Err(at_line(idx + 1)(e.into()))),
+                }
             })
-            .map_err(Into::into)
-            .transpose()
-        })
     }
 
-    pub fn parse<R: Read>(self, r: R) -> impl Iterator<Item = Result<IngestRecord, super::Error>> {
-        match self {
+    /// Parse a YAML list of records, reading each one's path and line through
+    /// `fields`
+    #[cfg(feature = "yaml")]
+    fn parse_yaml<R: Read>(
+        r: R,
+        fields: &FieldSelector,
+    ) -> impl Iterator<Item = Result<IngestRecord, super::Error>> + use<R> {
+        let res: Result<Vec<IngestRecord>, super::Error> =
+            serde_norway::from_reader::<_, Vec<serde_norway::Value>>(r)
+                .map_err(Into::into)
+                .and_then(|records| {
+                    records
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, record)| yaml_record(idx + 1, record, fields))
+                        .collect()
+                });
+
+        match res {
+            Ok(v) => EitherIter::Left(v.into_iter().map(Ok)),
+            Err(v) => EitherIter::Right(std::iter::once(Err(v))),
+        }
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    fn parse_yaml<R: Read>(
+        _r: R,
+        _fields: &FieldSelector,
+    ) -> impl Iterator<Item = Result<IngestRecord, super::Error>> + use<R> {
+        std::iter::once(Err(super::Error::YamlUnsupported))
+    }
+
+    /// Parse `r` as this format; `fields` are only allowed for YAML, and
+    /// default to `path` and `line`
+    pub fn parse<R: Read>(
+        self,
+        r: R,
+        fields: Option<&FieldSelector>,
+    ) -> impl Iterator<Item = Result<IngestRecord, super::Error>> {
+        let fields = match (&self, fields) {
+            (Self::Yaml, fields) => fields.cloned().unwrap_or_default(),
+            (_, Some(_)) => {
+                return EitherIter::Left(std::iter::once(Err(super::Error::FieldsWithoutYaml)));
+            }
+            (_, None) => FieldSelector::default(),
+        };
+        let fields = &fields;
+        EitherIter::Right(match self {
             Self::Json => EitherIter::Left(EitherIter::Left(Self::parse_json(r))),
             Self::Jsonl => EitherIter::Left(EitherIter::Right(Self::parse_jsonl(r))),
-            Self::Grep => EitherIter::Right(EitherIter::Left(Self::parse_grep(r))),
-            Self::Csv => EitherIter::Right(EitherIter::Right(Self::parse_csv(r))),
-        }
+            Self::Grep => {
+                EitherIter::Right(EitherIter::Left(EitherIter::Left(Self::parse_grep(r))))
+            }
+            Self::Csv => EitherIter::Right(EitherIter::Left(EitherIter::Right(Self::parse_csv(r)))),
+            Self::Yaml => EitherIter::Right(EitherIter::Right(Self::parse_yaml(r, fields))),
+        })
     }
 
     pub fn guess(line: &[u8]) -> Self {
-        if line.starts_with(b"[{") {
+        if line.starts_with(b"---") || line.starts_with(b"- path:") {
+            Self::Yaml
+        } else if line.starts_with(b"[{") {
             Self::Json
         } else if line.starts_with(b"{") {
             Self::Jsonl
@@ -273,6 +380,40 @@ impl Format {
     }
 }
 
+/// Read one YAML record's path and line (record `index`, 1-indexed) through
+/// `fields`
+#[cfg(feature = "yaml")]
+fn yaml_record(
+    index: usize,
+    record: &serde_norway::Value,
+    fields: &FieldSelector,
+) -> Result<IngestRecord, super::Error> {
+    let missing = |field: &str| super::Error::YamlMissingField {
+        index,
+        field: field.to_string(),
+    };
+    let path = select(record, &fields.path)
+        .and_then(serde_norway::Value::as_str)
+        .ok_or_else(|| missing(&fields.path))?;
+    let path = PathBuf::from(path);
+    let line = match select(record, &fields.line).ok_or_else(|| missing(&fields.line))? {
+        serde_norway::Value::Number(line) => line.to_string(),
+        serde_norway::Value::String(line) => line.clone(),
+        _ => return Err(missing(&fields.line)),
+    };
+    let line = validate_line(&path, &line)?;
+    Ok(IngestRecord::at_line(path, line))
+}
+
+/// Follow a dotted path of mapping keys and list indices from `value`
+#[cfg(feature = "yaml")]
+fn select<'a>(value: &'a serde_norway::Value, dotted: &str) -> Option<&'a serde_norway::Value> {
+    dotted.split('.').try_fold(value, |value, key| match value {
+        serde_norway::Value::Sequence(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
 #[derive(Args, Debug)]
 #[command(after_long_help = "\
 `ingest` reads a list of file locations from stdin (or a file) and, for each one,
@@ -285,9 +426,13 @@ in your editor, then pipe it to `bulked apply` to write the changes back.
 
 INPUT FORMATS (auto-detected, override with --format):
   jsonl  one JSON object per line, e.g. {\"path\":\"src/a.rs\",\"line\":12}
+         (or {\"path\":\"src/a.rs\",\"byte_offset\":340} for tools that report offsets)
   json   a JSON array of those same objects
   csv    a header row naming a path column and a line column, then rows
   grep   classic `path:line:...` lines, e.g. `grep -n` / `rg -n` output
+  yaml   a YAML list of {path, line} mappings (starting with `---` or `- path:`);
+         --path-field / --line-field pick the fields out of nested records
+         (yaml only; needs the `yaml` feature, on by default)
 
 EXAMPLES:
   # plain `grep -n` style output straight into the editable format
@@ -302,16 +447,38 @@ EXAMPLES:
   # a JSON array of {\"path\", \"line\"} objects
   bulked ingest --format json locations.json -o edits.bk
 
+  # YAML diagnostics with nested locations
+  bulked ingest --format yaml report.yaml \\
+      --path-field location.file --line-field location.range.start.line
+
+  # merge several location files (duplicates are dropped)
+  bulked ingest -i lint.jsonl -i grep.txt -o edits.bk
+
 Now edit edits.bk and run `bulked apply --input edits.bk`.")]
 pub(super) struct IngestArgs {
     /// File of locations to read (default: stdin). Use '-' to force stdin.
     #[arg(default_value = None)]
     path: Option<PathBuf>,
 
-    /// Input format. `auto` sniffs jsonl/json/csv/grep from the first bytes.
+    /// Additional file of locations to read (repeatable). Locations from every
+    /// input are merged, deduplicated, and sorted.
+    #[arg(short, long = "input", value_name = "PATH")]
+    inputs: Vec<PathBuf>,
+
+    /// Input format. `auto` sniffs jsonl/json/csv/grep/yaml from the first bytes.
     #[arg(short, long = "format", default_value = "auto")]
     format: FormatOptions,
 
+    /// Dotted path to each YAML record's file path, e.g. `location.file`
+    /// (default: `path`). Only for YAML input.
+    #[arg(long, value_name = "FIELD")]
+    path_field: Option<String>,
+
+    /// Dotted path to each YAML record's line number, e.g.
+    /// `location.range.start.line` (default: `line`). Only for YAML input.
+    #[arg(long, value_name = "FIELD")]
+    line_field: Option<String>,
+
     /// Write the editable format to this file instead of stdout
     #[arg(short, long)]
     output: Option<PathBuf>,
@@ -325,54 +492,118 @@ pub(super) struct IngestArgs {
     plain: bool,
 }
 
-#[derive(Debug, serde::Deserialize)]
+/// A location to ingest: a line, or a byte offset resolved to one later
+#[derive(Debug)]
 struct IngestRecord {
     path: PathBuf,
-    line: usize,
+    line: Option<NonZeroUsize>,
+    byte_offset: Option<u64>,
+}
+
+impl IngestRecord {
+    fn at_line(path: PathBuf, line: NonZeroUsize) -> Self {
+        Self {
+            path,
+            line: Some(line),
+            byte_offset: None,
+        }
+    }
+}
+
+/// A JSON / JSONL record as written, before its line number is validated
+///
+/// `line` is kept as any JSON number so negative, zero, fractional and
+/// too-large values all get the same [`super::Error::InvalidLineNumber`].
+/// A record may give a `byte_offset` instead, as some tools report those.
+#[derive(Debug, serde::Deserialize)]
+struct JsonRecord {
+    path: PathBuf,
+    line: Option<serde_json::Number>,
+    byte_offset: Option<u64>,
+}
+
+impl TryFrom<JsonRecord> for IngestRecord {
+    type Error = super::Error;
+
+    fn try_from(value: JsonRecord) -> Result<Self, Self::Error> {
+        let line = match (&value.line, value.byte_offset) {
+            (Some(line), _) => Some(validate_line(&value.path, &line.to_string())?),
+            (None, Some(_)) => None,
+            (None, None) => return Err(super::Error::MissingLocation(value.path)),
+        };
+        Ok(Self {
+            line,
+            byte_offset: value.byte_offset,
+            path: value.path,
+        })
+    }
 }
 
 impl From<IngestRecord> for crate::types::IngestInput {
     fn from(value: IngestRecord) -> Self {
         Self {
             file_path: value.path,
-            line_number: value.line,
+            line_number: value.line.map_or(0, NonZeroUsize::get),
+            byte_offset: value.byte_offset,
+        }
+    }
+}
+
+/// Parse every reader (each auto-detected separately), then merge the locations
+/// sorted by `(path, line)` with duplicates removed.
+fn read_inputs<R: Read>(
+    format: &FormatOptions,
+    fields: Option<&FieldSelector>,
+    readers: impl IntoIterator<Item = R>,
+) -> Result<Vec<crate::types::IngestInput>, super::Error> {
+    let mut inputs = Vec::new();
+    for reader in readers {
+        for record in format.clone().parse(reader, fields) {
+            inputs.push(crate::types::IngestInput::from(record?));
         }
     }
+
+    inputs.sort_by(|a, b| (&a.file_path, a.line_number).cmp(&(&b.file_path, b.line_number)));
+    inputs.dedup();
+    Ok(inputs)
 }
 
 impl IngestArgs {
     fn get_inputs(&self) -> Result<Vec<crate::types::IngestInput>, super::Error> {
-        let mut stdin;
-        let mut file;
-
-        let stream: &mut dyn std::io::Read = match &self.path {
-            Some(buf) if buf.as_os_str() != "-" => {
-                file = std::fs::File::open(buf)?;
-                &mut file
+        let fields = (self.path_field.is_some() || self.line_field.is_some()).then(|| {
+            let defaults = FieldSelector::default();
+            FieldSelector {
+                path: self.path_field.clone().unwrap_or(defaults.path),
+                line: self.line_field.clone().unwrap_or(defaults.line),
             }
-            _ => {
-                stdin = std::io::stdin();
-                &mut stdin
-            }
-        };
+        });
+        let paths: Vec<&PathBuf> = self.path.iter().chain(&self.inputs).collect();
+        if paths.is_empty() {
+            return read_inputs(&self.format, fields.as_ref(), [std::io::stdin()]);
+        }
 
-        self.format
-            .clone()
-            .parse(stream)
-            .map(|r| r.map(|i| i.into()))
-            .collect()
+        let mut readers: Vec<Box<dyn Read>> = Vec::with_capacity(paths.len());
+        for path in paths {
+            if path.as_os_str() == "-" {
+                readers.push(Box::new(std::io::stdin()));
+            } else {
+                readers.push(Box::new(std::fs::File::open(path)?));
+            }
+        }
+        read_inputs(&self.format, fields.as_ref(), readers)
     }
 
     pub fn handle(self) -> Result<(), super::Error> {
         let inputs = self.get_inputs()?;
 
         let result = crate::ingest::ingest(
-            &crate::filesystem::physical::PhysicalFS,
+            &crate::filesystem::physical::PhysicalFS::new(),
             inputs,
             self.context,
         )?;
 
-        let format = crate::format::Format::from_matches(&result);
+        let mut format = crate::format::Format::from_matches(&result);
+        format.normalize();
 
         // When writing to a file, never colorize (it's not a terminal).
         let mut sink: Box<dyn Write> = match &self.output {
@@ -399,3 +630,177 @@ impl IngestArgs {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IngestInput;
+
+    #[test]
+    fn test_read_inputs_merges_dedupes_and_sorts() {
+        let first: &[u8] = b"{\"path\":\"b.rs\",\"line\":3}\n{\"path\":\"a.rs\",\"line\":9}\n";
+        let second: &[u8] = b"{\"path\":\"a.rs\",\"line\":9}\n{\"path\":\"a.rs\",\"line\":2}\n";
+
+        let inputs = read_inputs(&FormatOptions::Auto, None, [first, second]).unwrap();
+
+        let input = |path: &str, line| IngestInput {
+            file_path: PathBuf::from(path),
+            line_number: line,
+            byte_offset: None,
+        };
+        assert_eq!(
+            inputs,
+            vec![input("a.rs", 2), input("a.rs", 9), input("b.rs", 3)]
+        );
+    }
+
+    #[test]
+    fn test_malformed_record_names_its_line() {
+        let jsonl: &[u8] = b"{\"path\":\"a.rs\",\"line\":1}\n{\"path\":\"b.rs\",\"line\":2}\n{\"path\":\"c.rs\",\"line\":\n";
+
+        let err = read_inputs(&FormatOptions::Auto, None, [jsonl]).unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::ParseRecord { line: 3, .. }
+        ));
+        assert!(err.to_string().contains("line 3"), "{err}");
+
+        let csv: &[u8] = b"path,line\na.rs,1\nb.rs,two\n";
+        let err = read_inputs(&FormatOptions::Auto, None, [csv]).unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::ParseRecord { line: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_line_numbers_are_rejected() {
+        let too_big = format!("{}0", usize::MAX);
+        for line in ["0", "-1", "2.5", too_big.as_str()] {
+            let jsonl = format!("{{\"path\":\"a.rs\",\"line\":{line}}}\n");
+            let json = format!("[{{\"path\":\"a.rs\",\"line\":{line}}}]");
+            let csv = format!("path,line\na.rs,{line}\n");
+
+            for input in [jsonl, json, csv] {
+                let err = read_inputs(&FormatOptions::Auto, None, [input.as_bytes()]).unwrap_err();
+                let message = err.to_string();
+                assert!(
+                    message.contains("invalid line number"),
+                    "{input}: {message}"
+                );
+            }
+        }
+
+        // grep output only has unsigned digits, so only overflow can be invalid
+        let grep = format!("a.rs:{too_big}:text\n");
+        let err = read_inputs(&FormatOptions::Auto, None, [grep.as_bytes()]).unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::ParseRecord { line: 1, ref source }
+                if matches!(**source, super::super::Error::InvalidLineNumber(..))
+        ));
+    }
+
+    #[test]
+    fn test_byte_offset_record_resolves_to_its_line() {
+        let fs = crate::filesystem::memory::MemoryFS::new();
+        let path = PathBuf::from("src/lib.rs");
+        // Each line is 10 bytes, so byte 42 is on line 5
+        let content: String = (1..=8).map(|n| format!("line {n:04}\n")).collect();
+        fs.add_file(&path, &content).unwrap();
+
+        let jsonl: &[u8] = b"{\"path\":\"src/lib.rs\",\"byte_offset\":42}\n";
+        let inputs = read_inputs(&FormatOptions::Auto, None, [jsonl]).unwrap();
+        assert_eq!(inputs[0].line_number, 0);
+        assert_eq!(inputs[0].byte_offset, Some(42));
+
+        let result = crate::ingest::ingest(&fs, inputs, 0).unwrap();
+        assert_eq!(result[0].line_number, 5);
+        assert_eq!(result[0].line_content, "line 0005\n");
+
+        let neither: &[u8] = b"{\"path\":\"src/lib.rs\"}\n";
+        let err = read_inputs(&FormatOptions::Auto, None, [neither]).unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::ParseRecord { line: 1, ref source }
+                if matches!(**source, super::super::Error::MissingLocation(..))
+        ));
+    }
+
+    #[test]
+    fn test_read_inputs_detects_each_format_separately() {
+        let jsonl: &[u8] = b"{\"path\":\"a.rs\",\"line\":1}\n";
+        let grep: &[u8] = b"a.rs:14:let x = 1;\n";
+
+        let inputs = read_inputs(&FormatOptions::Auto, None, [jsonl, grep]).unwrap();
+
+        let lines: Vec<usize> = inputs.iter().map(|i| i.line_number).collect();
+        assert_eq!(lines, vec![1, 14]);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_records() {
+        let yaml: &[u8] = b"- path: src/a.rs\n  line: 3\n- path: src/b.rs\n  line: 10\n- path: src/c.rs\n  line: 1\n";
+
+        let inputs = read_inputs(&FormatOptions::Auto, None, [yaml]).unwrap();
+
+        let records: Vec<(&Path, usize)> = inputs
+            .iter()
+            .map(|i| (i.file_path.as_path(), i.line_number))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (Path::new("src/a.rs"), 3),
+                (Path::new("src/b.rs"), 10),
+                (Path::new("src/c.rs"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fields_are_rejected_for_non_yaml_formats() {
+        let jsonl: &[u8] = b"{\"path\": \"src/a.rs\", \"line\": 3}\n";
+        let err = read_inputs(
+            &FormatOptions::Auto,
+            Some(&FieldSelector::default()),
+            [jsonl],
+        )
+        .unwrap_err();
+        assert!(matches!(err, super::super::Error::FieldsWithoutYaml));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_nested_fields() {
+        let yaml: &[u8] = b"---
+- diagnostics:
+    file: src/lib.rs
+    location:
+      range:
+        start: {line: 7, character: 2}
+- diagnostics:
+    file: src/main.rs
+    location:
+      range:
+        start: {line: 12, character: 0}
+";
+        let fields = FieldSelector {
+            path: "diagnostics.file".to_string(),
+            line: "diagnostics.location.range.start.line".to_string(),
+        };
+
+        let inputs = read_inputs(&FormatOptions::Auto, Some(&fields), [yaml]).unwrap();
+        let lines: Vec<usize> = inputs.iter().map(|i| i.line_number).collect();
+        assert_eq!(inputs[0].file_path, PathBuf::from("src/lib.rs"));
+        assert_eq!(lines, vec![7, 12]);
+
+        // The default fields aren't there
+        let err = read_inputs(&FormatOptions::Auto, None, [yaml]).unwrap_err();
+        assert!(matches!(
+            err,
+            super::super::Error::YamlMissingField { index: 1, ref field } if field == "path"
+        ));
+    }
+}
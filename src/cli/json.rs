@@ -0,0 +1,220 @@
+//! JSON output for `search --json`
+//!
+//! Every line is one self-contained object tagged with a `type`, so matches and
+//! per-file errors can be interleaved in a single stream that stays parseable.
+//! With `--count`, the output is instead one array of per-file match counts.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::types::MatchResult;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(super) enum Record<'a> {
+    /// One matching line
+    Match {
+        path: &'a Path,
+        line: usize,
+        /// 1-indexed column of the match in characters, see [`MatchResult::column`]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        column: Option<usize>,
+        line_content: &'a str,
+        /// Every occurrence of the pattern on the line
+        submatches: Vec<Submatch<'a>>,
+    },
+    /// A file that couldn't be searched
+    Error { path: &'a Path, message: String },
+}
+
+/// One occurrence of the pattern on a matching line, like ripgrep's submatches
+///
+/// `start` and `end` are byte offsets within the record's `line_content`.
+#[derive(Debug, Serialize)]
+pub(super) struct Submatch<'a> {
+    #[serde(rename = "match")]
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl<'a> Record<'a> {
+    /// `spans` are the byte ranges of every occurrence in `m.line_content`
+    pub(super) fn from_match(
+        m: &'a MatchResult,
+        tab_width: Option<usize>,
+        spans: &[std::ops::Range<usize>],
+    ) -> Self {
+        let submatches = spans
+            .iter()
+            .filter_map(|span| {
+                Some(Submatch {
+                    text: m.line_content.get(span.clone())?,
+                    start: span.start,
+                    end: span.end,
+                })
+            })
+            .collect();
+        Self::Match {
+            path: &m.file_path,
+            line: m.line_number,
+            column: m.column(tab_width),
+            line_content: &m.line_content,
+            submatches,
+        }
+    }
+
+    pub(super) fn error(path: &'a Path, err: &impl std::fmt::Display) -> Self {
+        Self::Error {
+            path,
+            message: err.to_string(),
+        }
+    }
+
+    /// Write this record as a single line of JSON
+    pub(super) fn write(&self, w: &mut dyn Write) -> Result<(), super::Error> {
+        serde_json::to_writer(&mut *w, self)?;
+        writeln!(w)?;
+        Ok(())
+    }
+}
+
+/// How many lines matched in one file, for `search --count`
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub(super) struct FileCount {
+    pub path: PathBuf,
+    pub count: usize,
+}
+
+impl FileCount {
+    /// Count the matches of each searched file, in search order
+    ///
+    /// Files without matches aren't yielded by the searcher, so they're left out.
+    pub(super) fn collect<E>(
+        results: impl Iterator<Item = (PathBuf, Result<Vec<MatchResult>, E>)>,
+    ) -> Result<Vec<Self>, E> {
+        results
+            .map(|(path, matches)| {
+                Ok(Self {
+                    path,
+                    count: matches?.len(),
+                })
+            })
+            .collect()
+    }
+
+    /// Write `counts` as a single JSON array
+    pub(super) fn write_all(counts: &[Self], w: &mut dyn Write) -> Result<(), super::Error> {
+        serde_json::to_writer(&mut *w, counts)?;
+        writeln!(w)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_records_are_tagged_json_lines() {
+        let m = MatchResult {
+            file_path: PathBuf::from("src/lib.rs"),
+            line_number: 3,
+            line_content: "\tTARGET\n".to_string(),
+            line_match: Some(1..7),
+            byte_offset: 0,
+            context_before: vec![],
+            context_after: vec![],
+            function_header: None,
+            match_lines: 1,
+            file_metadata: None,
+        };
+        let err = std::io::Error::other("permission denied");
+
+        let mut out = Vec::new();
+        let spans = vec![m.line_match.clone().unwrap()];
+        Record::from_match(&m, Some(4), &spans)
+            .write(&mut out)
+            .unwrap();
+        Record::error(Path::new("secret.rs"), &err)
+            .write(&mut out)
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                serde_json::json!({
+                    "type": "match",
+                    "path": "src/lib.rs",
+                    "line": 3,
+                    "column": 5,
+                    "line_content": "\tTARGET\n",
+                    "submatches": [{ "match": "TARGET", "start": 1, "end": 7 }],
+                }),
+                serde_json::json!({
+                    "type": "error",
+                    "path": "secret.rs",
+                    "message": "permission denied",
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_record_has_a_submatch_per_occurrence() {
+        use crate::matcher::Matcher;
+        use crate::matcher::regex::GrepMatcher;
+
+        let matcher = GrepMatcher::compile(r"T\w+").unwrap();
+        let mut m = matcher.search_in_content("let Tx = Tyy;\n").remove(0);
+        let spans = matcher.find_spans(&m.line_content);
+        m.line_match = None;
+        let m = MatchResult::from_match_info(m, PathBuf::from("a.rs"));
+
+        let json = serde_json::to_value(Record::from_match(&m, None, &spans)).unwrap();
+        assert_eq!(
+            json["submatches"],
+            serde_json::json!([
+                { "match": "Tx", "start": 4, "end": 6 },
+                { "match": "Tyy", "start": 9, "end": 12 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_count_by_file_json() {
+        use crate::filesystem::memory::MemoryFS;
+        use crate::matcher::Matcher;
+        use crate::matcher::regex::GrepMatcher;
+        use crate::searcher::Searcher;
+        use crate::walker::simple::SimpleWalker;
+
+        let fs = MemoryFS::new();
+        let (a, b) = (PathBuf::from("src/a.rs"), PathBuf::from("src/b.rs"));
+        fs.add_file(&a, "TARGET\nx\nTARGET TARGET\n").unwrap();
+        fs.add_file(&b, "y\nTARGET\n").unwrap();
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+        let searcher = Searcher::new(fs, matcher, SimpleWalker::new(vec![a, b]));
+
+        let counts = FileCount::collect(searcher.search_all_with_paths()).unwrap();
+        let mut out = Vec::new();
+        FileCount::write_all(&counts, &mut out).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "path": "src/a.rs", "count": 2 },
+                { "path": "src/b.rs", "count": 1 },
+            ])
+        );
+    }
+}
@@ -3,9 +3,12 @@ use clap::{Parser, Subcommand};
 mod apply;
 mod error;
 mod ingest;
+mod json;
 mod search;
 
 use crate::cli::ingest::IngestArgs;
+use crate::execute::ExecuteError;
+use crate::types::SearchError;
 
 use self::apply::ApplyArgs;
 use self::search::SearchArgs;
@@ -81,7 +84,7 @@ enum Command {
     /// STEP 1: turn a stream of (path, line) locations into the editable format
     Ingest(IngestArgs),
     /// STEP 1 (alt): find regex matches yourself and emit the editable format
-    Search(SearchArgs),
+    Search(Box<SearchArgs>),
     /// STEP 3: validate edited chunks and write the changes back to your files
     Apply(ApplyArgs),
 }
@@ -100,9 +103,21 @@ pub fn run() -> Result<(), Error> {
             .init();
     }
 
-    match cli.command {
+    let result = match cli.command {
         Command::Ingest(args) => args.handle(),
         Command::Search(args) => args.handle(),
         Command::Apply(args) => args.handle(),
+    };
+
+    // The error line lists every failed file; spell them out one per line too
+    if cli.verbose
+        && let Err(Error::Execute(ExecuteError::SearchError {
+            source: err @ SearchError::Multiple(_),
+        })) = &result
+    {
+        for err in err.errors() {
+            eprintln!("  {err}");
+        }
     }
+    result
 }
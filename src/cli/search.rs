@@ -1,11 +1,70 @@
 use std::fs::File;
-use std::io::{BufWriter, IsTerminal, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 
-use clap::Args;
+use clap::{Args, ValueEnum};
 
-use crate::execute::{Execute, ExecuteConfig};
-use crate::format::Format;
+use super::json::{FileCount, Record};
+use crate::execute::{DEFAULT_FUNCTION_HEADER_PATTERN, Execute, ExecuteConfig, SortBy};
+use crate::matcher::{NonUtf8Strategy, combine_patterns, parse_pattern_file};
+
+/// How `search` decodes file contents
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    /// Skip files that aren't valid UTF-8, with a warning
+    Utf8,
+    /// Search every file, replacing invalid UTF-8 with U+FFFD (needs output that
+    /// isn't applied back, such as --plain, or applying would write the U+FFFD)
+    Lossy,
+    /// Search the raw bytes of files that aren't valid UTF-8, printing invalid
+    /// bytes as \xNN (needs output that isn't applied back, such as --plain)
+    Binary,
+}
+
+impl From<Encoding> for NonUtf8Strategy {
+    fn from(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Utf8 => Self::Skip,
+            Encoding::Lossy => Self::Lossy,
+            Encoding::Binary => Self::Binary,
+        }
+    }
+}
+
+/// What `search` prints
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// The editable chunk format
+    #[default]
+    Chunk,
+    /// The editable chunk format, written once the search is done as one set of
+    /// chunks merged and sorted per file, ready for `apply` as-is
+    Merged,
+    /// One CSV row per match, with a header row
+    Csv,
+}
+
+/// The order `search` reports matches in
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SortKey {
+    /// By file path, then line (waits for the whole search before printing)
+    #[default]
+    Path,
+    /// By line number across all files, then path (also waits)
+    Line,
+    /// As the files are walked, printing as soon as each file is searched
+    None,
+}
+
+impl From<SortKey> for SortBy {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::Path => Self::Path,
+            SortKey::Line => Self::Line,
+            SortKey::None => Self::None,
+        }
+    }
+}
 
 #[derive(Args, Debug)]
 #[command(after_long_help = "\
@@ -23,28 +82,156 @@ EXAMPLES:
   bulked search 'TODO' src/ > edits.bk
   bulked search 'TODO' src/ -o edits.bk
 
+  # collect the results of several searches in one file
+  bulked search 'TODO' src/ -o edits.bk
+  bulked search 'FIXME' src/ -o edits.bk --append
+
   # tighter context, include hidden files, ignore .gitignore
   bulked search 'fn main' . -C 5 --hidden --no-ignore
 
   # also search previously generated .bk files
   bulked search 'TODO' . --include-bk
 
+  # also audit a gitignored vendored file, but skip generated code
+  bulked search 'unsafe' . --override-glob 'vendor/util.rs' --override-glob '!*.gen.rs'
+
+  # search everything, even gitignored files, except dependency directories
+  bulked search 'TODO' . --no-ignore --exclude-dir node_modules --exclude-dir target
+
+  # only the 5 most recently modified log files
+  bulked search 'ERROR' logs/ --newest 5
+
+  # guardrail for untrusted trees: read at most ~100 MB in total
+  bulked search 'password' . --max-total-bytes 100000000
+
+  # show the enclosing function of each match, even outside the context
+  bulked search 'unwrap()' src/ -C 2 --show-function
+
+  # emit the whole enclosing { ... } block of each match instead of N lines
+  bulked search 'unwrap()' src/ --block-context
+
+  # every line of each file that matches, with the matches highlighted
+  bulked search 'TODO' src/ --passthru --plain
+
+  # preview a rename as editable chunks, review/edit them, then apply
+  bulked search 'old_(\\w+)' src/ --replace 'new_$1' -C 0 -o rename.bk
+  bulked apply -i rename.bk
+
+  # how much would that rename touch?
+  bulked search 'old_(\\w+)' src/ --replace 'new_$1' --summary
+
+  # search for every pattern listed in a file (one per line, # for comments)
+  bulked search -f patterns.txt src/
+
+  # why wasn't a file matched? count the files searched and skipped
+  bulked search 'TODO' . --stats
+
+  # only the crates of the current Cargo workspace
+  bulked search 'unsafe' --workspace
+
+  # search another command's output, like grep
+  cargo build 2>&1 | bulked search 'warning' -
+
+  # search exactly the files another tool lists
+  git diff --name-only | bulked search 'TODO' --files-from -
+
+  # skip sorting directory entries while walking
+  bulked search 'TODO' . --sort-by none
+
+  # every file's first lines first, e.g. to review license headers together
+  bulked search 'Copyright' src/ --sort-by line --plain
+
+  # leave some cores free on a shared CI runner
+  bulked search 'TODO' . --sort-by line --threads 2
+
   # human-readable view (not meant for `apply`)
   bulked search 'TODO' src/ --plain
 
+  # grep-style lines with where each matched line starts, for tools that seek
+  bulked search 'TODO' src/ --plain -b
+
+  # human-readable view, each file's name printed once above its matches
+  bulked search 'TODO' src/ --heading
+
+  # machine-readable JSON Lines, with unreadable files reported as error records
+  bulked search 'TODO' src/ --json
+
+  # only lines that are exactly `}` (no substring matches), like grep -x
+  bulked search '\\}' src/ -x
+
+  # files with Windows line endings: `;$` still matches before the `\\r`
+  bulked search ';$' src/ --crlf
+
+  # a construct split over two lines: `if` lines followed by a lone `{`
+  bulked search -U 'if .*\\n\\s*\\{$' src/
+
+  # a whole block from `begin` to the next `end`, however many lines apart
+  bulked search -U --multiline-dotall 'begin.*?end' src/
+
+  # records separated by NUL bytes, e.g. from `find -print0`
+  find . -print0 > paths.bin
+  bulked search 'node_modules' paths.bin --null-data --json
+
+  # ASCII-only matching for big log files
+  bulked search '\\w+@\\w+' logs/ --no-unicode
+
+  # files whose name or contents mention the parser, each listed once
+  bulked search 'parser' src/ --only-files --name 'parser'
+
+  # files whose name alone matches (an empty content pattern)
+  bulked search '' src/ --only-files --name '_test\\.rs$'
+
+  # save edits with every file's chunks merged, ready to apply later
+  bulked search 'TODO' src/ --format merged -o edits.bk
+
+  # a spreadsheet-friendly list of matches
+  bulked search 'TODO' src/ --format csv -o todos.csv
+
+  # per-file match counts, as text or as one JSON array
+  bulked search 'TODO' src/ --count
+  bulked search 'TODO' src/ --count --json
+
+  # report columns as an editor with 4-wide tabs would show them
+  bulked search 'TODO' src/ --json --tab-width 4
+
 Then edit edits.bk and run `bulked apply --input edits.bk`.")]
 pub(super) struct SearchArgs {
-    /// Regex pattern to search for
-    pattern: String,
+    /// Regex pattern to search for (omit when using -f)
+    #[arg(required_unless_present_any = ["pattern_file", "name"])]
+    pattern: Option<String>,
 
-    /// Directory or file to search (default: current directory)
-    #[arg(default_value = ".")]
+    /// Directory or file to search (default: current directory; '-' searches
+    /// stdin, reported as <stdin>)
     paths: Vec<PathBuf>,
 
+    /// Search only the files listed in PATH, one per line ('-' for stdin), skipping
+    /// the directory walk and its ignore rules
+    #[arg(long, value_name = "PATH", conflicts_with = "paths")]
+    files_from: Option<PathBuf>,
+
+    /// Search only the member directories of a workspace: a `Cargo.toml`
+    /// (default: ./Cargo.toml) or a list file of directories/globs, one per line
+    #[arg(
+        long,
+        value_name = "MANIFEST",
+        num_args = 0..=1,
+        default_missing_value = "Cargo.toml",
+        conflicts_with_all = ["paths", "files_from"]
+    )]
+    workspace: Option<PathBuf>,
+
+    /// Read patterns from PATH, one per line; blank lines and `#` comments are skipped
+    #[arg(short = 'f', long = "file", value_name = "PATH")]
+    pattern_file: Option<PathBuf>,
+
     /// Write the editable format to this file instead of stdout
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Append to the --output file instead of overwriting it
+    #[arg(long, requires = "output")]
+    append: bool,
+
     /// Lines of context to include before and after each match
     #[arg(short = 'C', long, default_value = "20")]
     context: usize,
@@ -57,49 +244,386 @@ pub(super) struct SearchArgs {
     #[arg(long)]
     hidden: bool,
 
-    /// Also search bulked's own `.bk` output files (excluded by default)
+    /// Also search bulked's own `.bk` output files (excluded by default, unless
+    /// named directly as a path)
     #[arg(long)]
     include_bk: bool,
 
+    /// Force-include files matching GLOB even if ignored; prefix with `!` to force-exclude.
+    /// GLOB is relative to each searched path
+    #[arg(long = "override-glob", value_name = "GLOB")]
+    override_globs: Vec<String>,
+
+    /// Skip every directory named NAME, at any depth, even with --no-ignore
+    /// (repeatable)
+    #[arg(long = "exclude-dir", value_name = "NAME")]
+    exclude_dirs: Vec<String>,
+
+    /// Only search the N most recently modified files
+    #[arg(long, value_name = "N")]
+    newest: Option<usize>,
+
+    /// Stop searching once the files searched add up to more than N bytes, keeping
+    /// the results so far (approximate: the file that crosses N is searched in full)
+    #[arg(long, value_name = "N")]
+    max_total_bytes: Option<u64>,
+
+    /// Show the enclosing function header above each match, like `git grep -p`
+    #[arg(long)]
+    show_function: bool,
+
+    /// Regex for lines that start a function (used with --show-function)
+    #[arg(long, value_name = "REGEX", requires = "show_function")]
+    function_pattern: Option<String>,
+
+    /// Use the whole enclosing `{ ... }` block of each match as its context, found
+    /// by brace counting (a heuristic for C-like languages: braces in strings and
+    /// comments are counted too)
+    #[arg(long, conflicts_with = "context")]
+    block_context: bool,
+
+    /// Print every line of each file that has a match, not just the context
+    /// around matches (like `rg --passthru`)
+    #[arg(long, conflicts_with_all = ["context", "block_context"])]
+    passthru: bool,
+
+    /// Replace every match with REPLACEMENT in the emitted chunks (`$1`/`${name}`
+    /// refer to capture groups); files aren't changed until you `bulked apply`
+    #[arg(short, long, value_name = "REPLACEMENT")]
+    replace: Option<String>,
+
+    /// With --replace, only print how many occurrences, lines and files the
+    /// replacement would change, instead of the chunks
+    #[arg(
+        long,
+        requires = "replace",
+        conflicts_with_all = ["plain", "heading", "json", "format", "passthru", "block_context"]
+    )]
+    summary: bool,
+
+    /// Match ASCII-only: `\w`, `\d`, `\s` and `\b` ignore non-ASCII characters and
+    /// `.` matches any single byte (faster on large ASCII files such as logs)
+    #[arg(long)]
+    no_unicode: bool,
+
+    /// Only match lines that are entirely the pattern, like `grep -x`
+    #[arg(short = 'x', long)]
+    line_regexp: bool,
+
+    /// Treat CRLF (`\r\n`) as the line terminator: `$` matches before the `\r`.
+    /// Chunks keep the `\r\n` so apply preserves it; --plain and --json drop the `\r`
+    #[arg(long)]
+    crlf: bool,
+
+    /// Let matches span lines, like `rg -U`: `\n` in the pattern matches a line
+    /// break, and a match is reported once, with every line it spans
+    #[arg(short = 'U', long)]
+    multiline: bool,
+
+    /// With --multiline, let `.` match newlines too, so `begin.*?end` can span
+    /// lines
+    #[arg(long, requires = "multiline")]
+    multiline_dotall: bool,
+
+    /// Search NUL-separated records instead of lines, like `rg --null-data`:
+    /// each match is a whole record, line numbers count records, and no context
+    /// is shown. Record numbers aren't line numbers, so this needs --plain,
+    /// --heading, --json, --count, --only-files or --format csv
+    #[arg(long, conflicts_with_all = ["crlf", "multiline"])]
+    null_data: bool,
+
+    /// How to handle files that aren't valid UTF-8
+    #[arg(long, value_enum, default_value_t = Encoding::Utf8)]
+    encoding: Encoding,
+
     /// Print human-readable text instead of the editable chunk format
     #[arg(long)]
     plain: bool,
+
+    /// With --plain, print each match as `path:line:byte:content`, where byte is
+    /// the 0-based offset of the start of the matched line (not of the match)
+    #[arg(short = 'b', long, requires = "plain", conflicts_with_all = ["json", "count"])]
+    byte_offset: bool,
+
+    /// Output format: editable chunks, editable chunks merged per file across the
+    /// whole search, or CSV with one row per match (file_path, line_number,
+    /// byte_offset, line_content and context line counts)
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        conflicts_with_all = ["plain", "heading", "json", "count", "only_files"]
+    )]
+    format: OutputFormat,
+
+    /// Order of the results: path (files are walked in path order, streaming
+    /// results), line (global line number across files, printed once the whole
+    /// search is done), or none (unsorted walk order). --json and --count keep
+    /// each file together, so line orders them by path
+    #[arg(long, value_enum, value_name = "KEY", default_value_t)]
+    sort_by: SortKey,
+
+    /// Number of threads to search files with when the whole search is collected
+    /// before printing (--sort-by line, --format csv or merged); 0 (the default)
+    /// uses one per logical CPU
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    threads: usize,
+
+    /// Print human-readable text grouped by file: each file's name once, then its
+    /// matches and context, like `grep --heading`
+    #[arg(long, overrides_with = "no_heading", conflicts_with = "plain")]
+    heading: bool,
+
+    /// Give every chunk its own `@path:line:num` header (the default)
+    #[arg(long, overrides_with = "heading")]
+    no_heading: bool,
+
+    /// Print one JSON object per line: a `match` record per matching line, and an
+    /// `error` record (instead of aborting) for each file that can't be searched
+    #[arg(long, conflicts_with_all = ["plain", "heading"])]
+    json: bool,
+
+    /// Only count matching lines per file, printing `path:count` lines (or, with
+    /// --json, one array of `{"path", "count"}` objects); no context is read
+    #[arg(
+        short = 'c',
+        long,
+        conflicts_with_all = [
+            "plain",
+            "heading",
+            "block_context",
+            "passthru",
+            "show_function",
+            "replace"
+        ]
+    )]
+    count: bool,
+
+    /// Print the path of each matching file, once, instead of its matches
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "plain",
+            "heading",
+            "json",
+            "count",
+            "block_context",
+            "passthru",
+            "show_function",
+            "replace"
+        ]
+    )]
+    only_files: bool,
+
+    /// Also report files whose name matches NAME_PATTERN, whether or not their
+    /// contents match; an empty PATTERN ('') then checks names only
+    #[arg(long, value_name = "NAME_PATTERN", requires = "only_files")]
+    name: Option<String>,
+
+    /// After searching, print how many files were searched, and how many were
+    /// skipped as binary, invalid UTF-8 or over the byte budget, or errored
+    #[arg(long)]
+    stats: bool,
+
+    /// Count each leading tab as N columns in reported match columns (default:
+    /// each character is one column)
+    #[arg(long, value_name = "N", requires = "json")]
+    tab_width: Option<usize>,
 }
 
 impl SearchArgs {
-    pub fn handle(self) -> Result<(), super::Error> {
+    /// Whether the output is the chunk format `apply` reads
+    fn editable_output(&self) -> bool {
+        !(self.plain
+            || self.heading
+            || self.json
+            || self.count
+            || self.only_files
+            || self.summary
+            || self.format == OutputFormat::Csv)
+    }
+
+    pub fn handle(mut self) -> Result<(), super::Error> {
+        let pattern = match &self.pattern_file {
+            Some(file) => {
+                // Like `grep -f`, the first positional is then a path, not a pattern
+                if let Some(path) = self.pattern.take() {
+                    self.paths.insert(0, PathBuf::from(path));
+                }
+                let content = std::fs::read_to_string(file)?;
+                let patterns = parse_pattern_file(&content);
+                if patterns.is_empty() {
+                    return Err(super::Error::NoPatterns(file.clone()));
+                }
+                combine_patterns(&patterns)
+            }
+            None => self.pattern.take().unwrap_or_default(),
+        };
+        if self.paths.is_empty() {
+            self.paths.push(PathBuf::from("."));
+        }
+        let files = match &self.files_from {
+            Some(list) => Some(read_files_from(list, std::io::stdin())?),
+            None => None,
+        };
+        let stdin = read_stdin_path(&self.paths, std::io::stdin())?;
+        if self.null_data && self.editable_output() {
+            return Err(super::Error::UneditableOutput("--null-data"));
+        }
+        if self.editable_output() {
+            match self.encoding {
+                Encoding::Utf8 => {}
+                Encoding::Lossy => return Err(super::Error::UneditableOutput("--encoding lossy")),
+                Encoding::Binary => {
+                    return Err(super::Error::UneditableOutput("--encoding binary"));
+                }
+            }
+        }
+
         // Configure and execute search
-        let config = ExecuteConfig::new(self.pattern, self.paths)
-            .with_context_lines(self.context)
+        // Counting never looks at context, and passthru reads whole files itself,
+        // so don't pay to extract it
+        let context = if self.count || self.passthru || self.only_files || self.summary {
+            0
+        } else {
+            self.context
+        };
+        let mut config = ExecuteConfig::new(pattern, self.paths)
+            .with_context_lines(context)
             .with_respect_gitignore(!self.no_ignore)
             .with_hidden(self.hidden)
-            .with_include_bk(self.include_bk);
+            .with_include_bk(self.include_bk)
+            .with_override_globs(self.override_globs)
+            .with_exclude_dirs(self.exclude_dirs)
+            .with_block_context(self.block_context)
+            .with_passthru(self.passthru)
+            .with_unicode(!self.no_unicode)
+            .with_line_anchoring(self.line_regexp)
+            .with_crlf(self.crlf)
+            .with_multiline(self.multiline)
+            .with_multiline_dotall(self.multiline_dotall)
+            .with_line_terminator(if self.null_data { b'\0' } else { b'\n' })
+            .with_non_utf8_strategy(self.encoding.into())
+            .with_sort_by(self.sort_by.into())
+            .with_threads(self.threads)
+            .with_stats(self.stats);
+        if let Some(n) = self.newest {
+            config = config.with_newest(n);
+        }
+        if let Some(bytes) = self.max_total_bytes {
+            config = config.with_max_total_bytes(bytes);
+        }
+        // The summary counts the pattern in the unreplaced lines
+        if let Some(replacement) = self.replace.as_ref().filter(|_| !self.summary) {
+            config = config.with_replacement(replacement);
+        }
+        if let Some(files) = files {
+            config = config.with_files(files);
+        }
+        if let Some(content) = stdin {
+            config = config.with_stdin(content);
+        }
+        if let Some(manifest) = &self.workspace {
+            config = config.with_workspace(manifest);
+        }
+        if let Some(name) = &self.name {
+            config = config.with_name_pattern(name);
+        }
+        if let Some(path) = &self.output {
+            config = config.with_output_path(path).with_append(self.append);
+        }
+        if self.show_function {
+            let pattern = self
+                .function_pattern
+                .as_deref()
+                .unwrap_or(DEFAULT_FUNCTION_HEADER_PATTERN);
+            config = config.with_function_header(pattern);
+        }
 
         let result = Execute::new(&config)?;
 
         // When writing to a file, never colorize (it's not a terminal).
-        let mut sink: Box<dyn Write> = match &self.output {
-            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
-            None => Box::new(std::io::stdout()),
-        };
         let is_tty = self.output.is_none() && std::io::stdout().is_terminal();
 
-        let mut chunks = 0;
-        for page in result.search_iter() {
-            let result = page?;
-            let format = Format::from_matches(&result.matches);
-            chunks += format.len();
-            write!(sink, "{}", format.display(self.plain, is_tty))?;
-        }
+        let (written, singular, plural) = if self.only_files {
+            let mut sink = result.output_writer()?;
+            let mut files = 0;
+            for path in result.matching_files() {
+                writeln!(sink, "{}", path?.display())?;
+                files += 1;
+            }
+            sink.flush()?;
+            (files, "file", "files")
+        } else if self.summary {
+            let summary = result.replace_summary()?;
+            let mut sink = result.output_writer()?;
+            writeln!(sink, "{summary}")?;
+            sink.flush()?;
+            (summary.files, "file", "files")
+        } else if self.count {
+            let mut sink = result.output_writer()?;
+            let counts = FileCount::collect(result.search_iter_with_paths())?;
+            if self.json {
+                FileCount::write_all(&counts, &mut *sink)?;
+            } else {
+                for count in &counts {
+                    writeln!(sink, "{}:{}", count.path.display(), count.count)?;
+                }
+            }
+            sink.flush()?;
+            (counts.len(), "file count", "file counts")
+        } else if self.format == OutputFormat::Csv {
+            let found = result.search_whole()?;
+            let mut sink = result.output_writer()?;
+            sink.write_all(found.to_csv()?.as_bytes())?;
+            sink.flush()?;
+            (found.matches.len(), "match", "matches")
+        } else if self.json {
+            let mut sink = result.output_writer()?;
+            // Per-file errors become records too, so one bad file doesn't end the stream
+            let mut matches = 0;
+            for (path, found) in result.search_iter_with_paths() {
+                match found {
+                    Ok(results) => {
+                        matches += results.len();
+                        for mut m in results {
+                            if self.crlf {
+                                m.strip_cr();
+                            }
+                            let spans = result.match_spans(&m.line_content);
+                            Record::from_match(&m, self.tab_width, &spans).write(&mut *sink)?;
+                        }
+                    }
+                    Err(err) => Record::error(&path, &err).write(&mut *sink)?,
+                }
+            }
+            sink.flush()?;
+            (matches, "match", "matches")
+        } else if self.byte_offset {
+            let matches = result.write_byte_offsets()?;
+            (matches, "match", "matches")
+        } else if self.heading {
+            let files = result.write_headings(is_tty)?;
+            (files, "file", "files")
+        } else if self.format == OutputFormat::Merged {
+            let chunks = result.write_merged(is_tty)?;
+            (chunks, "chunk", "chunks")
+        } else {
+            let chunks = result.write_output(self.plain, is_tty)?;
+            (chunks, "chunk", "chunks")
+        };
 
-        sink.flush()?;
+        // Stats go to stderr so they never end up in the editable output
+        if self.stats {
+            eprintln!("files: {}", result.stats());
+        }
 
         // When the output went to a file, report a status line to stderr.
         if let Some(path) = &self.output {
-            let plural = if chunks == 1 { "chunk" } else { "chunks" };
+            let plural = if written == 1 { singular } else { plural };
             eprintln!(
                 "bulked search wrote {} {} to {}",
-                chunks,
+                written,
                 plural,
                 path.display()
             );
@@ -108,3 +632,145 @@ impl SearchArgs {
         Ok(())
     }
 }
+
+/// Read a newline-delimited file list from `list`, or from `stdin` if it's `-`.
+/// Blank lines are skipped.
+fn read_files_from(list: &Path, stdin: impl Read) -> Result<Vec<PathBuf>, super::Error> {
+    let reader: Box<dyn BufRead> = if list.as_os_str() == "-" {
+        Box::new(BufReader::new(stdin))
+    } else {
+        Box::new(BufReader::new(File::open(list)?))
+    };
+
+    let mut files = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if !line.trim().is_empty() {
+            files.push(PathBuf::from(line));
+        }
+    }
+    Ok(files)
+}
+
+/// Read all of `stdin` if `paths` is just `-`, like grep
+///
+/// `-` can't be mixed with other paths: stdin is searched on its own.
+fn read_stdin_path(
+    paths: &[PathBuf],
+    mut stdin: impl Read,
+) -> Result<Option<String>, super::Error> {
+    match paths {
+        [path] if path.as_os_str() == "-" => {
+            let mut content = String::new();
+            stdin.read_to_string(&mut content)?;
+            Ok(Some(content))
+        }
+        _ if paths.iter().any(|path| path.as_os_str() == "-") => Err(super::Error::StdinWithPaths),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walker::Walker;
+    use crate::walker::simple::SimpleWalker;
+
+    #[derive(clap::Parser)]
+    struct Cli {
+        #[command(flatten)]
+        search: SearchArgs,
+    }
+
+    /// Parse `bulked TODO <args>` as search arguments
+    fn parse(args: &[&str]) -> SearchArgs {
+        <Cli as clap::Parser>::try_parse_from(["bulked", "TODO"].iter().chain(args))
+            .unwrap()
+            .search
+    }
+
+    #[test]
+    fn test_files_from_stdin_lists_exactly_those_files() {
+        let stdin: &[u8] = b"src/a.rs\r\n\nsrc/b.rs\nREADME.md\n";
+
+        let files = read_files_from(Path::new("-"), stdin).unwrap();
+        let walker = SimpleWalker::new(files);
+
+        let walked: Vec<PathBuf> = walker.files().collect();
+        assert_eq!(
+            walked,
+            vec![
+                PathBuf::from("src/a.rs"),
+                PathBuf::from("src/b.rs"),
+                PathBuf::from("README.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dash_path_searches_stdin() {
+        let stdin: &[u8] = b"one\nTODO: two\n";
+        let content = read_stdin_path(&[PathBuf::from("-")], stdin)
+            .unwrap()
+            .unwrap();
+
+        let config = ExecuteConfig::new("TODO", Vec::new())
+            .with_context_lines(0)
+            .with_stdin(content);
+        let found: Vec<_> = Execute::new(&config)
+            .unwrap()
+            .search_iter()
+            .flat_map(|result| result.unwrap().matches)
+            .map(|m| (m.file_path, m.line_number))
+            .collect();
+        assert_eq!(found, vec![(PathBuf::from("<stdin>"), 2)]);
+
+        let stdin: &[u8] = b"unread";
+        assert!(
+            read_stdin_path(&[PathBuf::from("src")], stdin)
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            read_stdin_path(&[PathBuf::from("src"), PathBuf::from("-")], stdin),
+            Err(super::super::Error::StdinWithPaths)
+        ));
+    }
+
+    #[test]
+    fn test_no_heading_overrides_heading() {
+        assert!(!parse(&[]).heading);
+        assert!(parse(&["--heading"]).heading);
+        assert!(!parse(&["--heading", "--no-heading"]).heading);
+    }
+
+    #[test]
+    fn test_record_and_binary_searches_need_uneditable_output() {
+        assert!(matches!(
+            parse(&["--null-data"]).handle(),
+            Err(super::super::Error::UneditableOutput("--null-data"))
+        ));
+        assert!(matches!(
+            parse(&["--null-data", "--format", "merged"]).handle(),
+            Err(super::super::Error::UneditableOutput(_))
+        ));
+        assert!(!parse(&["--null-data", "--plain"]).editable_output());
+        assert!(!parse(&["--null-data", "--format", "csv"]).editable_output());
+
+        assert!(matches!(
+            parse(&["--encoding", "binary"]).handle(),
+            Err(super::super::Error::UneditableOutput("--encoding binary"))
+        ));
+        assert!(matches!(
+            parse(&["--encoding", "lossy"]).handle(),
+            Err(super::super::Error::UneditableOutput("--encoding lossy"))
+        ));
+    }
+
+    #[test]
+    fn test_files_from_missing_list_is_an_error() {
+        let stdin: &[u8] = b"src/a.rs\n";
+        assert!(read_files_from(Path::new("/does/not/exist.txt"), stdin).is_err());
+    }
+}
@@ -3,13 +3,21 @@
 //! This module provides a convenient API for executing searches with sensible
 //! defaults and production implementations (`PhysicalFS`, `GrepMatcher`, `IgnoreWalker`).
 
+use crate::filesystem::memory::MemoryFS;
 use crate::filesystem::physical::PhysicalFS;
+use crate::filesystem::{FileSystem, FilesystemError};
+use crate::format::{Format, FunctionHeaders};
 use crate::matcher::regex::GrepMatcher;
-use crate::matcher::{Matcher, MatcherError};
+use crate::matcher::{Matcher, MatcherError, NonUtf8Strategy};
 use crate::searcher::Searcher;
-use crate::types::{SearchError, SearchResult};
-use crate::walker::ignore_walker::IgnoreWalker;
-use std::path::PathBuf;
+use crate::types::{MatchResult, SearchError, SearchResult, SearchStats};
+use crate::walker::Walker;
+use crate::walker::ignore_walker::{IgnoreWalker, exclude_dir_glob};
+use crate::walker::manifest::{ManifestError, ManifestWalker};
+use crate::walker::simple::SimpleWalker;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 use thiserror::Error;
 
 /// Errors that can occur during search execution
@@ -28,6 +36,96 @@ pub enum ExecuteError {
         #[from]
         source: SearchError,
     },
+
+    /// Requested more context lines than [`MAX_CONTEXT_LINES`]
+    #[error("Context of {lines} lines is too large (maximum: {MAX_CONTEXT_LINES})")]
+    ContextTooLarge { lines: usize },
+
+    /// The output file couldn't be opened
+    #[error("Failed to open output: {source}")]
+    OutputError {
+        #[from]
+        source: FilesystemError,
+    },
+
+    /// Writing the search output failed
+    #[error("Failed to write output: {source}")]
+    WriteError {
+        #[from]
+        source: std::io::Error,
+    },
+
+    /// The workspace manifest couldn't be read or resolved
+    #[error(transparent)]
+    ManifestError {
+        #[from]
+        source: ManifestError,
+    },
+
+    /// Walker configuration was invalid (e.g. a bad override glob)
+    #[error("Invalid override glob: {source}")]
+    InvalidOverride {
+        #[from]
+        source: ignore::Error,
+    },
+}
+
+/// Largest accepted `context_lines`
+///
+/// Context never extends past the file, so this isn't a memory bound; it rejects
+/// values that can only be typos (e.g. `-C 10000000000`).
+pub const MAX_CONTEXT_LINES: usize = 1_000_000;
+
+/// Default `--show-function` pattern: common function/block openers in Rust,
+/// Python, Go, JavaScript/TypeScript and C-family class declarations
+pub const DEFAULT_FUNCTION_HEADER_PATTERN: &str = r#"^\s*(pub(\([^)]*\))?\s+)?(export\s+(default\s+)?)?((async|const|unsafe|static|extern\s+"[^"]*")\s+)*(fn|impl|trait|mod|def|func|function|class|interface)\b"#;
+
+/// A walked file paired with its matches, or the error searching it
+pub type FileMatches = (PathBuf, Result<Vec<MatchResult>, ExecuteError>);
+
+/// The order search results are reported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    /// By file path, then line: files are walked in path order, so results
+    /// still stream as they're found
+    #[default]
+    Path,
+
+    /// By line number, then file path, across all files: the whole search is
+    /// collected before any of it is written
+    Line,
+
+    /// In the order the files were walked, streamed as they're searched (not
+    /// deterministic with a parallel walk)
+    None,
+}
+
+impl SortBy {
+    /// Put `result`'s matches in this order
+    pub fn sort(self, result: &mut SearchResult) {
+        match self {
+            Self::Path => result.sort_by_file_and_line(),
+            Self::Line => result.sort_by_line(),
+            Self::None => {}
+        }
+    }
+}
+
+/// The path matches in searched stdin are reported under, see
+/// [`ExecuteConfig::with_stdin`]
+pub const STDIN_PATH: &str = "<stdin>";
+
+/// What a search does when a file can't be read or searched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorBehavior {
+    /// Report the error, ending the search there
+    #[default]
+    FailFast,
+
+    /// Keep searching the remaining files, setting the error aside for
+    /// [`Execute::collected_errors`]
+    #[allow(dead_code)]
+    CollectErrors,
 }
 
 /// Configuration for executing a search with production adapters
@@ -50,6 +148,88 @@ pub struct ExecuteConfig {
 
     /// Whether to include bulked's own `.bk` output files in the search
     pub include_bk: bool,
+
+    /// Only search the N most recently modified files
+    pub newest: Option<usize>,
+
+    /// Stop searching once the files searched add up to more than this many bytes
+    pub max_total_bytes: Option<u64>,
+
+    /// Globs that force-include (or, with a leading `!`, force-exclude) paths
+    pub override_globs: Vec<String>,
+
+    /// Names of directories to skip wherever they appear, even with ignore
+    /// files disabled
+    pub exclude_dirs: Vec<String>,
+
+    /// Regex for function header lines to show above each match, if enabled
+    pub function_header: Option<String>,
+
+    /// Whether to use each match's enclosing `{ ... }` block as its context
+    pub block_context: bool,
+
+    /// Whether to emit every line of each matching file, not just the context
+    pub passthru: bool,
+
+    /// Whether the pattern is Unicode-aware (`\w`, `\b`, `.` and friends)
+    pub unicode: bool,
+
+    /// Whether the pattern must match whole lines (`grep -x`)
+    pub line_anchoring: bool,
+
+    /// Whether lines end at `\r\n`, which is stripped to `\n` in `--plain` output
+    pub crlf: bool,
+
+    /// Whether matches may span lines
+    pub multiline: bool,
+
+    /// Whether `.` matches newlines too (multiline mode only)
+    pub multiline_dotall: bool,
+
+    /// The byte that ends each "line", `\n` unless searching e.g. NUL-separated
+    /// records
+    pub line_terminator: u8,
+
+    /// Text to replace every match with in the emitted match lines, if set
+    pub replacement: Option<String>,
+
+    /// How files that aren't valid UTF-8 are searched
+    pub non_utf8_strategy: NonUtf8Strategy,
+
+    /// Search exactly these files instead of walking `paths`
+    pub files: Option<Vec<PathBuf>>,
+
+    /// Search this text, read from stdin, as the only file instead of walking
+    /// `paths`
+    pub stdin: Option<String>,
+
+    /// Walk the members this manifest lists instead of `paths`
+    pub workspace: Option<PathBuf>,
+
+    /// Regex for file names that count as found, see [`Execute::matching_files`]
+    pub name_pattern: Option<String>,
+
+    /// What to do when a file can't be searched
+    pub error_behavior: ErrorBehavior,
+
+    /// Whether to search a file reached through several paths only once
+    pub dedup_files: bool,
+
+    /// Whether [`Execute::stats`] also counts binary and over-budget files
+    pub stats: bool,
+
+    /// The order results are reported in
+    pub sort_by: SortBy,
+
+    /// How many threads search files when results are collected before they're
+    /// reported (`0`: one per logical CPU, `1`: no thread pool)
+    pub threads: usize,
+
+    /// Write the output to this file instead of stdout
+    pub output: Option<PathBuf>,
+
+    /// Whether to append to `output` instead of overwriting it
+    pub append: bool,
 }
 
 impl ExecuteConfig {
@@ -62,10 +242,38 @@ impl ExecuteConfig {
             respect_gitignore: true,
             hidden: false,
             include_bk: false,
+            newest: None,
+            max_total_bytes: None,
+            override_globs: Vec::new(),
+            exclude_dirs: Vec::new(),
+            function_header: None,
+            block_context: false,
+            passthru: false,
+            unicode: true,
+            line_anchoring: false,
+            crlf: false,
+            multiline: false,
+            multiline_dotall: false,
+            line_terminator: b'\n',
+            replacement: None,
+            non_utf8_strategy: NonUtf8Strategy::default(),
+            files: None,
+            stdin: None,
+            workspace: None,
+            name_pattern: None,
+            error_behavior: ErrorBehavior::default(),
+            dedup_files: false,
+            stats: false,
+            sort_by: SortBy::default(),
+            threads: 0,
+            output: None,
+            append: false,
         }
     }
 
     /// Set the number of context lines (default: 20)
+    ///
+    /// Values above [`MAX_CONTEXT_LINES`] are rejected by [`Execute::new`].
     #[must_use]
     pub fn with_context_lines(mut self, lines: usize) -> Self {
         self.context_lines = lines;
@@ -92,6 +300,230 @@ impl ExecuteConfig {
         self.include_bk = include_bk;
         self
     }
+
+    /// Only search the `n` most recently modified files (default: all files)
+    #[must_use]
+    pub fn with_newest(mut self, n: usize) -> Self {
+        self.newest = Some(n);
+        self
+    }
+
+    /// Set globs that override ignore rules (default: none)
+    ///
+    /// See [`IgnoreWalker::with_override_patterns`] for the pattern semantics.
+    #[must_use]
+    pub fn with_override_globs(mut self, globs: Vec<String>) -> Self {
+        self.override_globs = globs;
+        self
+    }
+
+    /// Skip every directory with one of these names, at any depth (default: none)
+    ///
+    /// Each becomes a force-exclude override glob, see [`exclude_dir_glob`], so
+    /// it applies whether or not ignore files are respected.
+    #[must_use]
+    pub fn with_exclude_dirs(mut self, names: Vec<String>) -> Self {
+        self.exclude_dirs = names;
+        self
+    }
+
+    /// Show the nearest line matching `pattern` above each match (default: off)
+    ///
+    /// See [`DEFAULT_FUNCTION_HEADER_PATTERN`] for a pattern covering common languages.
+    #[must_use]
+    pub fn with_function_header(mut self, pattern: impl Into<String>) -> Self {
+        self.function_header = Some(pattern.into());
+        self
+    }
+
+    /// Use each match's enclosing brace-delimited block as its context instead of
+    /// `context_lines` (default: false)
+    #[must_use]
+    pub fn with_block_context(mut self, block_context: bool) -> Self {
+        self.block_context = block_context;
+        self
+    }
+
+    /// Emit every line of each file with a match, like `rg --passthru`
+    /// (default: false), see [`Searcher::with_passthru`]
+    #[must_use]
+    pub fn with_passthru(mut self, passthru: bool) -> Self {
+        self.passthru = passthru;
+        self
+    }
+
+    /// Match the pattern Unicode-aware, or ASCII-only with `.` matching any
+    /// byte (default: true), see [`GrepMatcher::compile_with_unicode`]
+    #[must_use]
+    pub fn with_unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Only match lines that are entirely the pattern (default: false), see
+    /// [`GrepMatcher::anchor_line`]
+    #[must_use]
+    pub fn with_line_anchoring(mut self, line_anchoring: bool) -> Self {
+        self.line_anchoring = line_anchoring;
+        self
+    }
+
+    /// Treat `\r\n` as the line terminator (default: false), see
+    /// [`GrepMatcher::crlf`]
+    #[must_use]
+    pub fn with_crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Let matches span lines (default: false), see
+    /// [`GrepMatcher::with_multiline`]
+    #[must_use]
+    pub fn with_multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    /// Let `.` match newlines (default: false), see
+    /// [`GrepMatcher::with_multiline_dotall`]; building the search fails if
+    /// this is on without [`ExecuteConfig::with_multiline`]
+    #[must_use]
+    pub fn with_multiline_dotall(mut self, dotall: bool) -> Self {
+        self.multiline_dotall = dotall;
+        self
+    }
+
+    /// Search `content` (typically read from stdin) as a single file named
+    /// [`STDIN_PATH`], instead of walking `paths` or `files`
+    #[must_use]
+    pub fn with_stdin(mut self, content: impl Into<String>) -> Self {
+        self.stdin = Some(content.into());
+        self
+    }
+
+    /// Report results in `sort_by` order (default: [`SortBy::Path`])
+    #[must_use]
+    pub fn with_sort_by(mut self, sort_by: SortBy) -> Self {
+        self.sort_by = sort_by;
+        self
+    }
+
+    /// Search files on `threads` threads (default: 0, one per logical CPU; 1
+    /// searches on the calling thread), see [`Searcher::search_all_parallel`]
+    ///
+    /// Only searches whose results are collected before being reported run in
+    /// parallel: [`SortBy::Line`], CSV, and merged output.
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Count binary and over-budget files in [`Execute::stats`] (default:
+    /// false), see [`Searcher::with_stats`]
+    #[must_use]
+    pub fn with_stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Search each file once even if several roots or symlinks reach it
+    /// (default: false), see [`Searcher::with_dedup_files`]
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_dedup_files(mut self, dedup_files: bool) -> Self {
+        self.dedup_files = dedup_files;
+        self
+    }
+
+    /// Pick whether a file that can't be searched ends the search (default:
+    /// [`ErrorBehavior::FailFast`])
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_error_behavior(mut self, behavior: ErrorBehavior) -> Self {
+        self.error_behavior = behavior;
+        self
+    }
+
+    /// Split input into "lines" at `terminator` (default: `b'\n'`), see
+    /// [`GrepMatcher::with_line_terminator`]
+    #[must_use]
+    pub fn with_line_terminator(mut self, terminator: u8) -> Self {
+        self.line_terminator = terminator;
+        self
+    }
+
+    /// Stop searching once the files searched add up to more than `bytes`,
+    /// returning the results found so far (default: no limit)
+    ///
+    /// Approximate, see [`Searcher::with_max_total_bytes`].
+    #[must_use]
+    pub fn with_max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    /// Rewrite each match line with every match replaced by `replacement`, which
+    /// may use `$1`/`${name}` capture references (default: lines are left as-is)
+    ///
+    /// Files aren't touched: the result is a preview in the editable format, which
+    /// `apply` then writes back.
+    #[must_use]
+    pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+
+    /// Search files that aren't valid UTF-8 as `strategy` says (default:
+    /// [`NonUtf8Strategy::Skip`]), see [`GrepMatcher::with_non_utf8_strategy`]
+    #[must_use]
+    pub fn with_non_utf8_strategy(mut self, strategy: NonUtf8Strategy) -> Self {
+        self.non_utf8_strategy = strategy;
+        self
+    }
+
+    /// Write the output to `path` instead of stdout, see [`Execute::write_output`]
+    #[must_use]
+    pub fn with_output_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    /// Append to the output path instead of overwriting it (default: false)
+    ///
+    /// Lets several searches build up one file of chunks, e.g. to pre-cache the
+    /// results of many patterns.
+    #[must_use]
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Search exactly `files`, bypassing the walker (default: walk `paths`)
+    ///
+    /// Ignore rules, hidden-file filtering and override globs only apply to walks,
+    /// so none of them filter this list.
+    #[must_use]
+    pub fn with_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    /// Walk the member directories `manifest` lists instead of `paths`: a
+    /// `Cargo.toml` workspace, or a list file, see [`ManifestWalker::new`]
+    #[must_use]
+    pub fn with_workspace(mut self, manifest: impl Into<PathBuf>) -> Self {
+        self.workspace = Some(manifest.into());
+        self
+    }
+
+    /// Also find files whose name matches this regex, see
+    /// [`Execute::matching_files`] (default: contents only)
+    #[must_use]
+    pub fn with_name_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.name_pattern = Some(pattern.into());
+        self
+    }
 }
 
 /// Execute a search with production adapters (`PhysicalFS`, `GrepMatcher`, `IgnoreWalker`)
@@ -110,35 +542,583 @@ impl ExecuteConfig {
 /// # Errors
 ///
 /// Returns an error if:
-/// - The regex pattern is invalid (`ExecuteError::PatternError`)
-/// - Walker initialization fails (`ExecuteError::SearchError`)
+/// - The regex pattern or function header pattern is invalid (`ExecuteError::PatternError`)
+/// - An override glob is invalid (`ExecuteError::InvalidOverride`)
+/// - The context exceeds [`MAX_CONTEXT_LINES`] (`ExecuteError::ContextTooLarge`)
 pub struct Execute {
-    searcher: Searcher<PhysicalFS, GrepMatcher, IgnoreWalker>,
+    searcher: Searcher<Box<dyn FileSystem>, GrepMatcher, Box<dyn Walker>>,
+    name: Option<GrepMatcher>,
+    search_contents: bool,
+    error_behavior: ErrorBehavior,
+    sort_by: SortBy,
+    threads: usize,
+    crlf: bool,
+    output: Option<PathBuf>,
+    append: bool,
+    /// Errors set aside under [`ErrorBehavior::CollectErrors`]
+    collected_errors: Mutex<Vec<SearchError>>,
+}
+
+impl<W: Walker> Searcher<PhysicalFS, GrepMatcher, W> {
+    /// Build a searcher over the real filesystem with a production regex matcher,
+    /// walking files with a caller-provided `walker`
+    ///
+    /// This is the middle ground between [`Execute`] (which also picks the walker)
+    /// and [`Searcher::new`] (which takes every adapter).
+    ///
+    /// # Errors
+    /// Returns an error if `pattern` is not a valid regex.
+    #[allow(dead_code)]
+    pub fn production(pattern: &str, context: usize, walker: W) -> Result<Self, MatcherError> {
+        let matcher = GrepMatcher::compile(pattern)?.with_context(context);
+        Ok(Self::new(PhysicalFS::new(), matcher, walker))
+    }
+}
+
+/// Open `path` on `fs` for writing, appending to it if `append` is set
+///
+/// # Errors
+/// Returns an error if the file can't be created or opened.
+pub fn open_output(
+    fs: &dyn FileSystem,
+    path: &Path,
+    append: bool,
+) -> Result<Box<dyn Write>, ExecuteError> {
+    let writer = if append {
+        fs.appender(path)?
+    } else {
+        fs.writer(path)?
+    };
+    Ok(writer)
+}
+
+/// Write each page of search results to `sink` as non-overlapping chunks,
+/// normalized with [`Format::normalize`], returning how many chunks were written
+///
+/// # Errors
+/// Returns the first search error, or an error if writing fails.
+pub fn write_format<E>(
+    sink: &mut dyn Write,
+    pages: impl Iterator<Item = Result<SearchResult, E>>,
+    plain: bool,
+    highlight: bool,
+) -> Result<usize, ExecuteError>
+where
+    ExecuteError: From<E>,
+{
+    let mut chunks = 0;
+    for page in pages {
+        let page = page?;
+        let mut format = Format::from_matches_grouped(&page.matches);
+        format.normalize();
+        let headers = FunctionHeaders::from_matches(&page.matches);
+        chunks += format.len();
+        write!(
+            sink,
+            "{}",
+            format
+                .display(plain, highlight)
+                .with_function_headers(&headers)
+        )?;
+    }
+    Ok(chunks)
+}
+
+/// Write every page of search results to `sink` as one format, normalized with
+/// [`Format::normalize`] (sorted, deduplicated and merged per file), returning
+/// how many chunks were written
+///
+/// Unlike [`write_format`], nothing is written until the search is done, so
+/// chunks from different pages can never overlap and the output is ready for
+/// `apply` as-is.
+///
+/// # Errors
+/// Returns the first search error, or an error if writing fails.
+pub fn write_merged_format<E>(
+    sink: &mut dyn Write,
+    pages: impl Iterator<Item = Result<SearchResult, E>>,
+    highlight: bool,
+) -> Result<usize, ExecuteError>
+where
+    ExecuteError: From<E>,
+{
+    let mut matches = Vec::new();
+    for page in pages {
+        matches.extend(page?.matches);
+    }
+    let mut format = Format::from_matches_grouped(&matches);
+    format.normalize();
+    let headers = FunctionHeaders::from_matches(&matches);
+    write!(
+        sink,
+        "{}",
+        format
+            .display(false, highlight)
+            .with_function_headers(&headers)
+    )?;
+    Ok(format.len())
+}
+
+/// Write each page of search results to `sink` grouped under one heading per
+/// file (see [`Format::display_headings`]), returning how many files were written
+///
+/// # Errors
+/// Returns the first search error, or an error if writing fails.
+pub fn write_headings<E>(
+    sink: &mut dyn Write,
+    pages: impl Iterator<Item = Result<SearchResult, E>>,
+    highlight: bool,
+) -> Result<usize, ExecuteError>
+where
+    ExecuteError: From<E>,
+{
+    let mut files = 0;
+    for page in pages {
+        for (_, matches) in page?.by_file() {
+            let format = Format::from_matches_grouped(&matches);
+            let headers = FunctionHeaders::from_matches(&matches);
+            files += 1;
+            write!(
+                sink,
+                "{}",
+                format
+                    .display_headings(highlight)
+                    .with_function_headers(&headers)
+            )?;
+        }
+    }
+    Ok(files)
+}
+
+/// Write each match to `sink` as `path:line:byte:content`, like `grep -nb`,
+/// returning how many matches were written
+///
+/// `byte` is [`MatchResult::byte_offset`]: where the matched line starts in
+/// the file, counting from 0.
+///
+/// # Errors
+/// Returns the first search error, or an error if writing fails.
+pub fn write_byte_offsets<E>(
+    sink: &mut dyn Write,
+    pages: impl Iterator<Item = Result<SearchResult, E>>,
+) -> Result<usize, ExecuteError>
+where
+    ExecuteError: From<E>,
+{
+    let mut matches = 0;
+    for page in pages {
+        for m in page?.matches {
+            writeln!(
+                sink,
+                "{}:{}:{}:{}",
+                m.file_path.display(),
+                m.line_number,
+                m.byte_offset,
+                m.line_content.trim_end_matches(['\r', '\n'])
+            )?;
+            matches += 1;
+        }
+    }
+    Ok(matches)
+}
+
+/// How much a `--replace` would change, see [`Execute::replace_summary`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplaceSummary {
+    /// Matches of the pattern, counting each one on a line
+    pub occurrences: usize,
+    /// Lines with at least one match
+    pub lines: usize,
+    /// Files with at least one match
+    pub files: usize,
+}
+
+impl ReplaceSummary {
+    /// Tally each file's matches, counting the occurrences of `matcher` in
+    /// every match line
+    ///
+    /// The matches must be unreplaced, or the occurrences can't be found.
+    ///
+    /// # Errors
+    /// Returns the first file's error.
+    pub fn collect<E>(
+        files: impl Iterator<Item = (PathBuf, Result<Vec<MatchResult>, E>)>,
+        matcher: &GrepMatcher,
+    ) -> Result<Self, E> {
+        let mut summary = Self::default();
+        for (_, matches) in files {
+            let matches = matches?;
+            if matches.is_empty() {
+                continue;
+            }
+            summary.files += 1;
+            for m in &matches {
+                summary.lines += m.line_count();
+                summary.occurrences += matcher.find_spans(&m.line_content).len().max(1);
+            }
+        }
+        Ok(summary)
+    }
+}
+
+impl std::fmt::Display for ReplaceSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: usize, word: &str| format!("{n} {word}{}", if n == 1 { "" } else { "s" });
+        write!(
+            f,
+            "Would replace {} on {} across {}",
+            plural(self.occurrences, "occurrence"),
+            plural(self.lines, "line"),
+            plural(self.files, "file")
+        )
+    }
+}
+
+/// A match transformer that replaces every match of `matcher` in the match line
+/// with `replacement`, for [`Searcher::with_try_match_transformer`]
+///
+/// Context lines are left alone, so a chunk built from the result rewrites just
+/// the matched lines when applied.
+pub fn replace_matches(
+    matcher: GrepMatcher,
+    replacement: &str,
+) -> impl Fn(MatchResult) -> Result<Option<MatchResult>, MatcherError> + Send + Sync + 'static {
+    let replacement = replacement.to_string();
+    move |mut m| {
+        m.line_content = matcher.replace_all(&m.line_content, &replacement)?;
+        // The old match range no longer points at anything meaningful
+        m.line_match = None;
+        Ok(Some(m))
+    }
 }
 
 impl Execute {
     pub fn new(config: &ExecuteConfig) -> Result<Self, ExecuteError> {
-        // Create production adapters
-        let fs = PhysicalFS::new();
+        if config.context_lines > MAX_CONTEXT_LINES {
+            return Err(ExecuteError::ContextTooLarge {
+                lines: config.context_lines,
+            });
+        }
 
-        let matcher = GrepMatcher::compile(&config.pattern)?.with_context(config.context_lines);
+        // Stdin is searched as the one file of an in-memory filesystem
+        let fs: Box<dyn FileSystem> = match &config.stdin {
+            Some(content) => {
+                let fs = MemoryFS::new();
+                fs.add_file(Path::new(STDIN_PATH), content)
+                    .map_err(SearchError::from)?;
+                Box::new(fs)
+            }
+            None => Box::new(PhysicalFS::new()),
+        };
+        let walker: Box<dyn Walker> = match (&config.stdin, &config.files) {
+            (Some(_), _) => Box::new(SimpleWalker::new(vec![PathBuf::from(STDIN_PATH)])),
+            (None, Some(files)) => {
+                let mut files = files.clone();
+                if config.sort_by != SortBy::None {
+                    files.sort();
+                }
+                Box::new(SimpleWalker::new(files))
+            }
+            (None, None) => {
+                let exclude_dirs: Vec<String> = config
+                    .exclude_dirs
+                    .iter()
+                    .map(|name| exclude_dir_glob(name))
+                    .collect();
+                let override_globs: Vec<&str> = config
+                    .override_globs
+                    .iter()
+                    .chain(&exclude_dirs)
+                    .map(String::as_str)
+                    .collect();
+                match &config.workspace {
+                    Some(manifest) => Box::new(
+                        ManifestWalker::new(
+                            manifest,
+                            config.respect_gitignore,
+                            config.hidden,
+                            config.include_bk,
+                        )?
+                        .with_sorted(config.sort_by != SortBy::None)
+                        .with_override_patterns(&override_globs)?,
+                    ),
+                    None => Box::new(
+                        IgnoreWalker::new(
+                            config.paths.clone(),
+                            config.respect_gitignore,
+                            config.hidden,
+                            config.include_bk,
+                        )
+                        .with_sorted(config.sort_by != SortBy::None)
+                        .with_override_patterns(&override_globs)?,
+                    ),
+                }
+            }
+        };
 
-        let walker = IgnoreWalker::new(
-            config.paths.clone(),
-            config.respect_gitignore,
-            config.hidden,
-            config.include_bk,
-        );
+        // The pattern as compiled for both searching and `--replace`
+        let pattern_matcher = GrepMatcher::compile_with_unicode(&config.pattern, config.unicode)?
+            .anchor_line(config.line_anchoring)?
+            .crlf(config.crlf)?
+            .with_multiline(config.multiline)?
+            .with_multiline_dotall(config.multiline_dotall)?
+            .with_line_terminator(config.line_terminator)?;
+        let matcher = pattern_matcher
+            .clone()
+            .with_context(config.context_lines)
+            .with_non_utf8_strategy(config.non_utf8_strategy);
+        let mut searcher = Searcher::new(fs, matcher, walker)
+            .with_block_context(config.block_context)
+            .with_passthru(config.passthru)
+            .with_dedup_files(config.dedup_files)
+            .with_stats(config.stats);
+        if let Some(n) = config.newest {
+            searcher = searcher.with_newest(n);
+        }
+        if let Some(bytes) = config.max_total_bytes {
+            searcher = searcher.with_max_total_bytes(bytes);
+        }
+        if let Some(pattern) = &config.function_header {
+            searcher = searcher.with_show_function(GrepMatcher::compile(pattern)?);
+        }
+        if let Some(replacement) = &config.replacement {
+            searcher =
+                searcher.with_try_match_transformer(replace_matches(pattern_matcher, replacement));
+        }
+
+        let name = match &config.name_pattern {
+            Some(pattern) => Some(GrepMatcher::compile_with_unicode(pattern, config.unicode)?),
+            None => None,
+        };
 
         Ok(Self {
-            searcher: Searcher::new(fs, matcher, walker),
+            searcher,
+            name,
+            // With a name pattern, an empty content pattern means "names only"
+            search_contents: config.name_pattern.is_none() || !config.pattern.is_empty(),
+            error_behavior: config.error_behavior,
+            sort_by: config.sort_by,
+            threads: config.threads,
+            crlf: config.crlf,
+            output: config.output.clone(),
+            append: config.append,
+            collected_errors: Mutex::new(Vec::new()),
         })
     }
 
-    pub fn search_iter(&self) -> impl Iterator<Item = Result<SearchResult, ExecuteError>> {
+    /// Open the configured output path (honoring append mode), or stdout if
+    /// none was set
+    ///
+    /// # Errors
+    /// Returns an error if the output file can't be created or opened.
+    pub fn output_writer(&self) -> Result<Box<dyn Write>, ExecuteError> {
+        match &self.output {
+            Some(path) => open_output(&PhysicalFS::new(), path, self.append),
+            None => Ok(Box::new(std::io::stdout())),
+        }
+    }
+
+    /// Run the search and write the results as chunks to the configured output
+    /// (or stdout), returning how many chunks were written
+    ///
+    /// `plain` and `highlight` pick the display, see [`Format::display`]. Only the
+    /// plain display drops the `\r` of `--crlf` lines, since chunks keep it for apply.
+    ///
+    /// # Errors
+    /// Returns an error if the search fails or the output can't be written.
+    pub fn write_output(&self, plain: bool, highlight: bool) -> Result<usize, ExecuteError> {
+        let mut sink = self.output_writer()?;
+        let strip_cr = plain && self.crlf;
+        let pages = self.search_iter().map(|page| {
+            page.map(|mut page| {
+                if strip_cr {
+                    page.matches.iter_mut().for_each(MatchResult::strip_cr);
+                }
+                page
+            })
+        });
+        let written = write_format(&mut *sink, pages, plain, highlight)?;
+        sink.flush()?;
+        Ok(written)
+    }
+
+    /// Write the results as one merged format, see [`write_merged_format`],
+    /// returning how many chunks were written
+    ///
+    /// # Errors
+    /// Returns an error if the search fails or the output can't be written.
+    pub fn write_merged(&self, highlight: bool) -> Result<usize, ExecuteError> {
+        let mut sink = self.output_writer()?;
+        let written =
+            write_merged_format(&mut *sink, std::iter::once(self.search_whole()), highlight)?;
+        sink.flush()?;
+        Ok(written)
+    }
+
+    /// Write each match as `path:line:byte:content`, see [`write_byte_offsets`],
+    /// returning how many matches were written
+    ///
+    /// # Errors
+    /// Returns an error if the search fails or the output can't be written.
+    pub fn write_byte_offsets(&self) -> Result<usize, ExecuteError> {
+        let mut sink = self.output_writer()?;
+        let written = write_byte_offsets(&mut *sink, self.search_iter())?;
+        sink.flush()?;
+        Ok(written)
+    }
+
+    /// Write the results grouped by file, see [`write_headings`], returning how
+    /// many files were written
+    ///
+    /// # Errors
+    /// Returns an error if the search fails or the output can't be written.
+    pub fn write_headings(&self, highlight: bool) -> Result<usize, ExecuteError> {
+        let mut sink = self.output_writer()?;
+        let written = write_headings(&mut *sink, self.search_iter(), highlight)?;
+        sink.flush()?;
+        Ok(written)
+    }
+
+    /// Each matching file's results in walk order (path order unless
+    /// [`SortBy::None`]), or with [`SortBy::Line`], all of them as one sorted
+    /// result
+    ///
+    /// With [`ErrorBehavior::CollectErrors`], files that can't be searched are
+    /// left out instead of being yielded as errors, see [`Execute::collected_errors`].
+    pub fn search_iter(&self) -> Box<dyn Iterator<Item = Result<SearchResult, ExecuteError>> + '_> {
+        let collect_errors = self.error_behavior == ErrorBehavior::CollectErrors;
+        if self.sort_by != SortBy::Line {
+            let pages = self
+                .searcher
+                .search_all()
+                .filter_map(move |result| match result {
+                    Err(err) if collect_errors => {
+                        self.collect_error(err);
+                        None
+                    }
+                    result => Some(result.map_err(|e| ExecuteError::SearchError { source: e })),
+                });
+            return Box::new(pages);
+        }
+
+        Box::new(std::iter::once_with(move || self.search_whole()))
+    }
+
+    /// The whole search as one result, in [`SortBy`] order, with the files
+    /// searched on [`ExecuteConfig::threads`] threads
+    ///
+    /// Like [`Execute::search_iter`], the first file that fails (in walk order)
+    /// ends the search, unless errors are collected.
+    ///
+    /// # Errors
+    /// Returns the first search error.
+    pub fn search_whole(&self) -> Result<SearchResult, ExecuteError> {
+        let collect_errors = self.error_behavior == ErrorBehavior::CollectErrors;
+        let mut all = SearchResult::new();
+        for (_, found) in self
+            .searcher
+            .search_all_parallel(self.threads, !collect_errors)
+        {
+            match found {
+                Ok(matches) => all.matches.extend(matches),
+                Err(err) if collect_errors => self.collect_error(err),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.sort_by.sort(&mut all);
+        Ok(all)
+    }
+
+    /// Search every file before reporting any error, so that all the files
+    /// that failed are reported at once (as [`SearchError::Multiple`] if there
+    /// were several)
+    ///
+    /// With [`ErrorBehavior::CollectErrors`] the failures are set aside for
+    /// [`Execute::collected_errors`] instead.
+    ///
+    /// # Errors
+    /// Returns the combined errors of every file that failed.
+    #[allow(dead_code)]
+    pub fn search_collected(&self) -> Result<SearchResult, ExecuteError> {
+        let mut result = SearchResult::new();
+        let mut errors = Vec::new();
+        for (_, found) in self.searcher.search_all_parallel(self.threads, false) {
+            match found {
+                Ok(matches) => result.matches.extend(matches),
+                Err(err) => errors.push(err),
+            }
+        }
+        self.sort_by.sort(&mut result);
+        if self.error_behavior == ErrorBehavior::CollectErrors {
+            errors.into_iter().for_each(|err| self.collect_error(err));
+            return Ok(result);
+        }
+        match SearchError::from_errors(errors) {
+            Some(err) => Err(err.into()),
+            None => Ok(result),
+        }
+    }
+
+    /// Count what replacing every match would change, without replacing
+    /// anything, see [`ReplaceSummary`]
+    ///
+    /// Build this `Execute` without a replacement: the counts come from the
+    /// original match lines.
+    ///
+    /// # Errors
+    /// Returns the first search error.
+    pub fn replace_summary(&self) -> Result<ReplaceSummary, ExecuteError> {
+        ReplaceSummary::collect(self.search_iter_with_paths(), self.searcher.matcher())
+    }
+
+    /// Each file whose name matches the name pattern or whose contents match
+    /// the pattern, once, see [`Searcher::matching_files`]
+    pub fn matching_files(&self) -> impl Iterator<Item = Result<PathBuf, ExecuteError>> {
         self.searcher
-            .search_all()
-            .map(|result| result.map_err(|e| ExecuteError::SearchError { source: e }))
+            .matching_files(self.name.as_ref(), self.search_contents)
+            .map(|result| result.map_err(ExecuteError::from))
+    }
+
+    /// The byte range of every match of the pattern in `line`
+    pub fn match_spans(&self, line: &str) -> Vec<std::ops::Range<usize>> {
+        self.searcher.matcher().find_spans(line)
+    }
+
+    /// The errors of every file the searches so far skipped under
+    /// [`ErrorBehavior::CollectErrors`], in the order they were met, taken so a
+    /// later call only returns newer ones
+    #[allow(dead_code)]
+    pub fn collected_errors(&self) -> Vec<SearchError> {
+        std::mem::take(
+            &mut self
+                .collected_errors
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+
+    fn collect_error(&self, err: SearchError) {
+        self.collected_errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(err);
+    }
+
+    /// How many files the search so far looked at and skipped, see
+    /// [`Searcher::stats`]
+    pub fn stats(&self) -> SearchStats {
+        self.searcher.stats()
+    }
+
+    /// Like [`Execute::search_iter`], but yields each file's path alongside its
+    /// matches or error, so one unreadable file doesn't have to end the search
+    ///
+    /// Files stay whole and are walked in path order, so any [`SortBy`] but
+    /// `None` orders them by path.
+    pub fn search_iter_with_paths(&self) -> impl Iterator<Item = FileMatches> + '_ {
+        self.searcher
+            .search_all_with_paths()
+            .map(|(path, result)| (path, result.map_err(ExecuteError::from)))
     }
 }
 
@@ -146,7 +1126,6 @@ impl Execute {
 mod tests {
     use super::*;
     use crate::filesystem::memory::MemoryFS;
-    use crate::walker::simple::SimpleWalker;
 
     #[test]
     fn test_execute_config_builder() {
@@ -166,6 +1145,410 @@ mod tests {
 
         assert_eq!(config.context_lines, 20);
         assert!(config.respect_gitignore);
+        assert_eq!(config.threads, 0);
+    }
+
+    #[test]
+    fn test_execute_rejects_unreasonable_context() {
+        let config =
+            ExecuteConfig::new("pattern", vec!["/some/path".into()]).with_context_lines(usize::MAX);
+
+        assert!(matches!(
+            Execute::new(&config),
+            Err(ExecuteError::ContextTooLarge { lines: usize::MAX })
+        ));
+    }
+
+    #[test]
+    fn test_default_function_header_pattern() {
+        let matcher = GrepMatcher::compile(DEFAULT_FUNCTION_HEADER_PATTERN).unwrap();
+
+        assert!(matcher.is_match("fn main() {"));
+        assert!(matcher.is_match("    pub(crate) async fn load(&self) -> Result<()> {"));
+        assert!(matcher.is_match("impl<T> Walker for Vec<T> {"));
+        assert!(matcher.is_match("def handler(event):"));
+        assert!(!matcher.is_match("    let fn_name = \"main\";"));
+        assert!(!matcher.is_match("    main();"));
+    }
+
+    #[test]
+    fn test_replace_summary_counts_occurrences_lines_and_files() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("/a.rs"), "old old\nnew\nold\n")
+            .unwrap();
+        fs.add_file(Path::new("/b.rs"), "x = old_value;\n").unwrap();
+        fs.add_file(Path::new("/c.rs"), "nothing here\n").unwrap();
+
+        let matcher = GrepMatcher::compile("old").unwrap().with_context(0);
+        let walker = SimpleWalker::new(vec!["/a.rs".into(), "/b.rs".into(), "/c.rs".into()]);
+        let searcher = Searcher::new(fs, matcher, walker);
+
+        let summary =
+            ReplaceSummary::collect(searcher.search_all_with_paths(), searcher.matcher()).unwrap();
+        assert_eq!(
+            summary,
+            ReplaceSummary {
+                occurrences: 4,
+                lines: 3,
+                files: 2,
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "Would replace 4 occurrences on 3 lines across 2 files"
+        );
+    }
+
+    #[test]
+    fn test_stdin_is_searched_as_a_pseudo_file() {
+        let config = ExecuteConfig::new("TARGET", vec!["/ignored".into()])
+            .with_context_lines(0)
+            .with_stdin("first\nTARGET here\nlast\n");
+
+        let execute = Execute::new(&config).unwrap();
+        let matches: Vec<MatchResult> = execute
+            .search_iter()
+            .flat_map(|result| result.unwrap().matches)
+            .collect();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].file_path, PathBuf::from(STDIN_PATH));
+        assert_eq!(matches[0].line_number, 2);
+        assert_eq!(matches[0].line_content, "TARGET here\n");
+    }
+
+    #[test]
+    fn test_error_behavior_collect_errors_skips_failed_files() {
+        let missing = PathBuf::from("/does/not/exist.txt");
+        let config = ExecuteConfig::new("TARGET", Vec::new()).with_files(vec![missing]);
+
+        let execute = Execute::new(&config).unwrap();
+        let results: Vec<_> = execute.search_iter().collect();
+        assert!(matches!(
+            results[..],
+            [Err(ExecuteError::SearchError { .. })]
+        ));
+
+        let config = config.with_error_behavior(ErrorBehavior::CollectErrors);
+        let execute = Execute::new(&config).unwrap();
+        assert!(
+            execute
+                .search_iter()
+                .all(|page| page.unwrap().matches.is_empty())
+        );
+        let collected = execute.collected_errors();
+        assert!(matches!(collected[..], [SearchError::FileReadError { .. }]));
+        assert!(execute.collected_errors().is_empty());
+    }
+
+    #[test]
+    fn test_searcher_production_with_custom_walker() {
+        let walker = SimpleWalker::new(vec![PathBuf::from("/does/not/exist.txt")]);
+        let searcher = Searcher::production("TARGET", 2, walker).unwrap();
+
+        // The custom walker's file list is what gets searched
+        let results: Vec<_> = searcher.search_all().collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+
+        let walker = SimpleWalker::new(vec![]);
+        assert!(Searcher::production("[unclosed", 2, walker).is_err());
+    }
+
+    /// search -> replace -> format -> apply reproduces the replacement on disk
+    #[test]
+    fn test_replace_preview_round_trips_through_apply() {
+        use crate::apply::apply_format_to_fs;
+        use crate::filesystem::FileSystem;
+        use crate::format::Format;
+
+        let fs = MemoryFS::new();
+        let a = PathBuf::from("/src/a.rs");
+        let b = PathBuf::from("/src/b.rs");
+        fs.add_file(&a, "fn a() {\n    old_name(1);\n    old_name(2);\n}\n")
+            .unwrap();
+        fs.add_file(&b, "use x::old_name;\nfn b() {}").unwrap();
+
+        let matcher = GrepMatcher::compile(r"old_(\w+)").unwrap().with_context(1);
+        let replace = replace_matches(GrepMatcher::compile(r"old_(\w+)").unwrap(), "new_$1");
+        let walker = SimpleWalker::new(vec![a.clone(), b.clone()]);
+        let searcher =
+            Searcher::new(fs.clone(), matcher, walker).with_try_match_transformer(replace);
+
+        let matches: Vec<_> = searcher
+            .search_all()
+            .flat_map(|result| result.unwrap().matches)
+            .collect();
+        let mut preview = Format::from_matches(&matches);
+        preview.normalize();
+
+        // Nothing is written until the previewed format is applied
+        assert!(fs.read_to_string(&a).unwrap().contains("old_name"));
+
+        let mut format: Format = preview.to_string().parse().unwrap();
+        apply_format_to_fs(&mut format, &fs).unwrap();
+
+        assert_eq!(
+            fs.read_to_string(&a).unwrap(),
+            "fn a() {\n    new_name(1);\n    new_name(2);\n}\n"
+        );
+        assert_eq!(
+            fs.read_to_string(&b).unwrap(),
+            "use x::new_name;\nfn b() {}"
+        );
+    }
+
+    #[test]
+    fn test_multiline_replace_round_trips_through_apply() {
+        use crate::apply::apply_format_to_fs;
+        use crate::filesystem::FileSystem;
+        use crate::format::Format;
+
+        let path = PathBuf::from("/a.txt");
+        let compile = || {
+            GrepMatcher::compile(r"foo\nbar")
+                .unwrap()
+                .with_multiline(true)
+                .unwrap()
+        };
+        let builds: [fn(&[MatchResult]) -> Format; 2] =
+            [Format::from_matches, Format::from_matches_grouped];
+        for build in builds {
+            let fs = MemoryFS::new();
+            fs.add_file(&path, "one\nfoo\nbar\nfoo\nbaz\n").unwrap();
+            let searcher = Searcher::new(fs.clone(), compile().with_context(1), vec![path.clone()])
+                .with_try_match_transformer(replace_matches(compile(), "X"));
+            let (result, _) = searcher.search_all_collecting_errors();
+            assert_eq!(result.matches[0].line_count(), 2);
+
+            let mut format: Format = build(&result.matches).to_string().parse().unwrap();
+            apply_format_to_fs(&mut format, &fs).unwrap();
+            assert_eq!(fs.read_to_string(&path).unwrap(), "one\nX\nfoo\nbaz\n");
+        }
+    }
+
+    #[test]
+    fn test_crlf_chunks_keep_line_endings_through_apply() {
+        use crate::apply::apply_format_to_fs;
+        use crate::filesystem::FileSystem;
+        use crate::format::Format;
+
+        let path = PathBuf::from("/a.txt");
+        let fs = MemoryFS::new();
+        fs.add_file(&path, "one\r\nfoo;\r\nthree\r\n").unwrap();
+        let matcher = GrepMatcher::compile(";$").unwrap().crlf(true).unwrap();
+        let searcher = Searcher::new(fs.clone(), matcher.with_context(1), vec![path.clone()])
+            .with_match_transformer(|mut m| {
+                m.line_content = m.line_content.replace(';', "");
+                Some(m)
+            });
+        let (result, _) = searcher.search_all_collecting_errors();
+
+        let mut format: Format = Format::from_matches(&result.matches)
+            .to_string()
+            .parse()
+            .unwrap();
+        apply_format_to_fs(&mut format, &fs).unwrap();
+        assert_eq!(fs.read_to_string(&path).unwrap(), "one\r\nfoo\r\nthree\r\n");
+
+        let mut plain = result.matches[0].clone();
+        plain.strip_cr();
+        assert_eq!(plain.line_content, "foo\n");
+        assert_eq!(plain.context_before[0].content, "one\n");
+    }
+
+    #[test]
+    fn test_sort_by_path_streams_files_in_path_order() {
+        let files = vec![
+            PathBuf::from("/does/not/b.txt"),
+            PathBuf::from("/does/not/a.txt"),
+        ];
+        let config = ExecuteConfig::new("TARGET", Vec::new()).with_files(files);
+        let failed_paths = |sort_by| {
+            let execute = Execute::new(&config.clone().with_sort_by(sort_by)).unwrap();
+            execute
+                .search_iter()
+                .map(|page| page.unwrap_err().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // One page per file, as each is searched
+        let pages = failed_paths(SortBy::Path);
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].contains("a.txt") && pages[1].contains("b.txt"));
+
+        let pages = failed_paths(SortBy::None);
+        assert!(pages[0].contains("b.txt") && pages[1].contains("a.txt"));
+
+        // Sorting by line needs every file first
+        assert_eq!(failed_paths(SortBy::Line).len(), 1);
+
+        // Collected output (csv, merged) stops at the first file in walk order,
+        // whichever thread searched it
+        for threads in [1, 4] {
+            let execute = Execute::new(&config.clone().with_threads(threads)).unwrap();
+            let err = execute.search_whole().unwrap_err().to_string();
+            assert!(err.contains("a.txt"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_sort_by_orders_matches() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("/c.rs"), "TARGET\n").unwrap();
+        fs.add_file(Path::new("/a.rs"), "x\nx\nTARGET\n").unwrap();
+        fs.add_file(Path::new("/b.rs"), "x\nTARGET\nTARGET\n")
+            .unwrap();
+        let walked = vec![
+            PathBuf::from("/c.rs"),
+            PathBuf::from("/a.rs"),
+            PathBuf::from("/b.rs"),
+        ];
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(0);
+        let searcher = Searcher::new(fs, matcher, walked);
+        let sorted = |sort_by: SortBy| {
+            let (mut result, _) = searcher.search_all_collecting_errors();
+            sort_by.sort(&mut result);
+            result
+                .matches
+                .into_iter()
+                .map(|m| (m.file_path.to_string_lossy().into_owned(), m.line_number))
+                .collect::<Vec<_>>()
+        };
+        let at = |path: &str, line| (path.to_string(), line);
+
+        assert_eq!(
+            sorted(SortBy::Path),
+            vec![
+                at("/a.rs", 3),
+                at("/b.rs", 2),
+                at("/b.rs", 3),
+                at("/c.rs", 1)
+            ]
+        );
+        assert_eq!(
+            sorted(SortBy::Line),
+            vec![
+                at("/c.rs", 1),
+                at("/b.rs", 2),
+                at("/a.rs", 3),
+                at("/b.rs", 3)
+            ]
+        );
+        assert_eq!(
+            sorted(SortBy::None),
+            vec![
+                at("/c.rs", 1),
+                at("/a.rs", 3),
+                at("/b.rs", 2),
+                at("/b.rs", 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_offsets_are_line_starts() {
+        let fs = MemoryFS::new();
+        fs.add_file(
+            Path::new("/a.txt"),
+            "one\nsay TARGET\n\nthree TARGET\nTARGET",
+        )
+        .unwrap();
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(0);
+        let searcher = Searcher::new(fs, matcher, vec![PathBuf::from("/a.txt")]);
+
+        let mut out = Vec::new();
+        assert_eq!(
+            write_byte_offsets(&mut out, searcher.search_all()).unwrap(),
+            3
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "/a.txt:2:4:say TARGET\n/a.txt:4:16:three TARGET\n/a.txt:5:29:TARGET\n"
+        );
+    }
+
+    fn search_to(fs: &MemoryFS, out: &Path, append: bool) -> usize {
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+        let walker = SimpleWalker::new(vec![PathBuf::from("/src/a.txt")]);
+        let searcher = Searcher::new(fs.clone(), matcher, walker);
+
+        let mut sink = open_output(fs, out, append).unwrap();
+        let written = write_format(&mut *sink, searcher.search_all(), false, false).unwrap();
+        sink.flush().unwrap();
+        written
+    }
+
+    #[test]
+    fn test_output_path_holds_valid_format() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("/src/a.txt"), "one\nTARGET\nthree\n")
+            .unwrap();
+        let out = Path::new("/out.bk");
+
+        assert_eq!(search_to(&fs, out, false), 1);
+        // Overwriting replaces the previous run
+        assert_eq!(search_to(&fs, out, false), 1);
+
+        let format: Format = fs.read_to_string(out).unwrap().parse().unwrap();
+        assert_eq!(format.len(), 1);
+        assert_eq!(format.0[0].start_line, 2);
+        assert_eq!(format.0[0].content, "TARGET\n");
+    }
+
+    #[test]
+    fn test_output_append_accumulates_runs() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("/src/a.txt"), "one\nTARGET\nthree\n")
+            .unwrap();
+        let out = Path::new("/out.bk");
+
+        search_to(&fs, out, true);
+        search_to(&fs, out, true);
+
+        let format: Format = fs.read_to_string(out).unwrap().parse().unwrap();
+        assert_eq!(format.len(), 2);
+        assert_eq!(format.0[0], format.0[1]);
+    }
+
+    #[test]
+    fn test_write_headings_groups_by_file() {
+        let fs = MemoryFS::new();
+        let (a, b) = (PathBuf::from("a.rs"), PathBuf::from("b.rs"));
+        fs.add_file(&a, "TARGET 1\nx\nx\nx\nTARGET 2\n").unwrap();
+        fs.add_file(&b, "y\nTARGET 3\n").unwrap();
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(1);
+        let searcher = Searcher::new(fs, matcher, SimpleWalker::new(vec![a, b]));
+
+        let mut out = Vec::new();
+        let files = write_headings(&mut out, searcher.search_all(), false).unwrap();
+        assert_eq!(files, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+a.rs
+     1 > TARGET 1
+     2 | x
+  --
+     4 | x
+     5 > TARGET 2
+
+b.rs
+     1 | y
+     2 > TARGET 3
+
+"
+        );
+
+        // Without headings, every chunk carries its own header
+        let mut out = Vec::new();
+        write_format(&mut out, searcher.search_all(), false, false).unwrap();
+        let headers = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with('@') && !line.starts_with("@@@"))
+            .count();
+        assert_eq!(headers, 3);
     }
 
     #[test]
@@ -3,12 +3,13 @@
 //! This module provides `MemoryFS`, a fake filesystem that stores all data in memory.
 //! It's used for hermetic testing without touching the real filesystem.
 
-use super::{FileSystem, FilesystemError};
+use super::{BINARY_SAMPLE_LEN, FileMetadata, FileSystem, FilesystemError};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 
 /// In-memory filesystem for testing
 ///
@@ -19,6 +20,20 @@ use std::sync::{Arc, RwLock};
 #[derive(Clone)]
 pub(crate) struct MemoryFS {
     files: Arc<RwLock<HashMap<PathBuf, Vec<u8>>>>,
+    modified: Arc<RwLock<HashMap<PathBuf, SystemTime>>>,
+    failures: Arc<RwLock<HashMap<PathBuf, InjectedFailure>>>,
+    normalizer: Option<PathNormalizer>,
+}
+
+/// Maps a path to the key it's stored under, see [`MemoryFS::with_path_normalizer`]
+type PathNormalizer = Arc<dyn Fn(&Path) -> PathBuf + Send + Sync>;
+
+/// Which operations fail on a path (see [`MemoryFS::fail_read`])
+#[derive(Debug, Clone, Copy, Default)]
+struct InjectedFailure {
+    read: bool,
+    write: bool,
+    metadata: bool,
 }
 
 #[allow(dead_code)]
@@ -27,6 +42,62 @@ impl MemoryFS {
     pub fn new() -> Self {
         Self {
             files: Arc::new(RwLock::new(HashMap::new())),
+            modified: Arc::new(RwLock::new(HashMap::new())),
+            failures: Arc::new(RwLock::new(HashMap::new())),
+            normalizer: None,
+        }
+    }
+
+    /// A new filesystem that treats paths case-insensitively, see
+    /// [`MemoryFS::with_case_insensitive`]
+    pub fn case_insensitive() -> Self {
+        Self::new().with_case_insensitive(true)
+    }
+
+    /// A new filesystem that resolves `.` and `..` in paths lexically, so
+    /// `/a/./b/../c.txt` and `/a/c.txt` name the same file
+    ///
+    /// Like [`Path::canonicalize`] without touching the disk: there are no
+    /// symlinks to follow, and `..` at the root stays at the root.
+    pub fn canonical() -> Self {
+        Self::new().with_path_normalizer(lexically_normalize)
+    }
+
+    /// Treat paths case-insensitively, like macOS (HFS+/APFS) or Windows (NTFS)
+    ///
+    /// Every path is lowercased before it's stored or looked up, so
+    /// `/A/B.txt` and `/a/b.txt` name the same file. Set this before adding files.
+    /// Replaces any normalizer set with [`MemoryFS::with_path_normalizer`].
+    #[must_use]
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        if case_insensitive {
+            self.with_path_normalizer(|path| PathBuf::from(path.to_string_lossy().to_lowercase()))
+        } else {
+            Self {
+                normalizer: None,
+                ..self
+            }
+        }
+    }
+
+    /// Store and look up every path as `normalizer(path)`, so paths that
+    /// normalize to the same key name the same file
+    ///
+    /// Set this before adding files: files already added keep their old keys.
+    #[must_use]
+    pub fn with_path_normalizer<F>(mut self, normalizer: F) -> Self
+    where
+        F: Fn(&Path) -> PathBuf + Send + Sync + 'static,
+    {
+        self.normalizer = Some(Arc::new(normalizer));
+        self
+    }
+
+    /// The key a path is stored under in the in-memory maps
+    fn key<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        match &self.normalizer {
+            Some(normalize) => Cow::Owned(normalize(path)),
+            None => Cow::Borrowed(path),
         }
     }
 
@@ -38,10 +109,88 @@ impl MemoryFS {
     /// Add a file to the filesystem with binary content
     pub fn add_file_bytes(&self, path: &Path, content: &[u8]) -> Result<(), FilesystemError> {
         let mut files = self.files.write().map_err(|_| FilesystemError::LockError)?;
-        files.insert(path.to_path_buf(), content.to_vec());
+        files.insert(self.key(path).into_owned(), content.to_vec());
         Ok(())
     }
 
+    /// Set the modification time reported by `metadata` for a file
+    ///
+    /// Files without an explicit time report `modified: None`.
+    pub fn set_modified(&self, path: &Path, time: SystemTime) -> Result<(), FilesystemError> {
+        let mut modified = self
+            .modified
+            .write()
+            .map_err(|_| FilesystemError::LockError)?;
+        modified.insert(self.key(path).into_owned(), time);
+        Ok(())
+    }
+
+    /// Make every read of `path` fail with [`FilesystemError::ReadError`]
+    ///
+    /// Covers `read`, `read_to_string` and `is_binary`. Lets tests exercise error
+    /// paths that a missing file can't reach (e.g. a file that exists but can't be
+    /// read). Lasts until [`MemoryFS::clear_failures`].
+    pub fn fail_read(&self, path: &Path) {
+        self.inject(path, |failure| failure.read = true);
+    }
+
+    /// Make every write to `path` fail with [`FilesystemError::WriteError`]
+    ///
+    /// Covers `write_string`, `writer`, `remove_file`, and `rename` to or from
+    /// `path`. Lasts until [`MemoryFS::clear_failures`].
+    pub fn fail_write(&self, path: &Path) {
+        self.inject(path, |failure| failure.write = true);
+    }
+
+    /// Make [`FileSystem::metadata`] of `path` fail with [`FilesystemError::ReadError`],
+    /// while its contents stay readable. Lasts until [`MemoryFS::clear_failures`].
+    pub fn fail_metadata(&self, path: &Path) {
+        self.inject(path, |failure| failure.metadata = true);
+    }
+
+    /// Remove all failures injected with `fail_read` / `fail_write` / `fail_metadata`
+    pub fn clear_failures(&self) {
+        if let Ok(mut failures) = self.failures.write() {
+            failures.clear();
+        }
+    }
+
+    fn inject(&self, path: &Path, update: impl FnOnce(&mut InjectedFailure)) {
+        if let Ok(mut failures) = self.failures.write() {
+            update(failures.entry(self.key(path).into_owned()).or_default());
+        }
+    }
+
+    /// Return the injected error for `path`, if reads of it should fail
+    fn check_read(&self, path: &Path) -> Result<(), FilesystemError> {
+        let failures = self
+            .failures
+            .read()
+            .map_err(|_| FilesystemError::LockError)?;
+        match failures.get(self.key(path).as_ref()) {
+            Some(failure) if failure.read => Err(FilesystemError::ReadError {
+                path: path.to_path_buf(),
+                source: std::io::Error::other("injected read failure"),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the injected error for `path`, if writes to it should fail
+    fn check_write(&self, path: &Path) -> Result<(), FilesystemError> {
+        let failures = self
+            .failures
+            .read()
+            .map_err(|_| FilesystemError::LockError)?;
+        match failures.get(self.key(path).as_ref()) {
+            Some(failure) if failure.write => Err(FilesystemError::WriteError {
+                path: path.to_path_buf(),
+                source: std::io::Error::other("injected write failure"),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     /// Number of files currently stored (test helper for asserting temp cleanup).
     #[cfg(test)]
     pub fn file_count(&self) -> usize {
@@ -53,6 +202,10 @@ impl MemoryFS {
         if let Ok(mut files) = self.files.write() {
             files.clear();
         }
+        if let Ok(mut modified) = self.modified.write() {
+            modified.clear();
+        }
+        self.clear_failures();
     }
 }
 
@@ -68,19 +221,27 @@ impl FileSystem for MemoryFS {
     }
 
     fn read(&self, path: &Path) -> Result<Box<dyn std::io::Read>, FilesystemError> {
-        let st = self.read_to_string(path)?;
-        let vec = Vec::from(st);
-        Ok(Box::new(std::io::Cursor::new(vec)))
+        self.check_read(path)?;
+        let files = self.files.read().map_err(|_| FilesystemError::LockError)?;
+        let bytes =
+            files
+                .get(self.key(path).as_ref())
+                .ok_or_else(|| FilesystemError::FileNotFound {
+                    path: path.to_path_buf(),
+                })?;
+        Ok(Box::new(std::io::Cursor::new(bytes.clone())))
     }
 
     fn read_to_string(&self, path: &Path) -> Result<String, FilesystemError> {
+        self.check_read(path)?;
         let files = self.files.read().map_err(|_| FilesystemError::LockError)?;
 
-        let bytes = files
-            .get(path)
-            .ok_or_else(|| FilesystemError::FileNotFound {
-                path: path.to_path_buf(),
-            })?;
+        let bytes =
+            files
+                .get(self.key(path).as_ref())
+                .ok_or_else(|| FilesystemError::FileNotFound {
+                    path: path.to_path_buf(),
+                })?;
 
         String::from_utf8(bytes.clone()).map_err(|source| FilesystemError::InvalidUtf8 {
             path: path.to_path_buf(),
@@ -88,45 +249,78 @@ impl FileSystem for MemoryFS {
         })
     }
 
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, FilesystemError> {
+        self.check_read(path)?;
+        let files = self.files.read().map_err(|_| FilesystemError::LockError)?;
+        files
+            .get(self.key(path).as_ref())
+            .cloned()
+            .ok_or_else(|| FilesystemError::FileNotFound {
+                path: path.to_path_buf(),
+            })
+    }
+
     fn write_string(&self, path: &Path, content: &str) -> Result<(), FilesystemError> {
+        self.write_bytes(path, content.as_bytes())
+    }
+
+    fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), FilesystemError> {
+        self.check_write(path)?;
         let mut files = self.files.write().map_err(|_| FilesystemError::LockError)?;
-        files.insert(path.to_path_buf(), content.as_bytes().to_vec());
+        files.insert(self.key(path).into_owned(), content.to_vec());
         Ok(())
     }
 
     fn writer(&self, path: &Path) -> Result<Box<dyn std::io::Write>, FilesystemError> {
+        self.check_write(path)?;
         Ok(Box::new(MemoryWriter {
             files: Arc::clone(&self.files),
-            path: path.to_path_buf(),
+            path: self.key(path).into_owned(),
             buf: Vec::new(),
         }))
     }
 
     fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
+        self.check_write(from)?;
+        self.check_write(to)?;
         let mut files = self.files.write().map_err(|_| FilesystemError::LockError)?;
-        let data = files
-            .remove(from)
-            .ok_or_else(|| FilesystemError::FileNotFound {
-                path: from.to_path_buf(),
-            })?;
-        files.insert(to.to_path_buf(), data);
+        let (from_key, to_key) = (self.key(from), self.key(to));
+        let data =
+            files
+                .remove(from_key.as_ref())
+                .ok_or_else(|| FilesystemError::FileNotFound {
+                    path: from.to_path_buf(),
+                })?;
+        files.insert(to_key.to_path_buf(), data);
+        let mut modified = self
+            .modified
+            .write()
+            .map_err(|_| FilesystemError::LockError)?;
+        if let Some(time) = modified.remove(from_key.as_ref()) {
+            modified.insert(to_key.into_owned(), time);
+        }
         Ok(())
     }
 
     fn remove_file(&self, path: &Path) -> Result<(), FilesystemError> {
+        self.check_write(path)?;
         let mut files = self.files.write().map_err(|_| FilesystemError::LockError)?;
+        let key = self.key(path);
         files
-            .remove(path)
+            .remove(key.as_ref())
             .ok_or_else(|| FilesystemError::FileNotFound {
                 path: path.to_path_buf(),
             })?;
+        if let Ok(mut modified) = self.modified.write() {
+            modified.remove(key.as_ref());
+        }
         Ok(())
     }
 
     fn exists(&self, path: &Path) -> bool {
         self.files
             .read()
-            .map(|files| files.contains_key(path))
+            .map(|files| files.contains_key(self.key(path).as_ref()))
             .unwrap_or(false)
     }
 
@@ -134,6 +328,84 @@ impl FileSystem for MemoryFS {
         // In MemoryFS, everything stored is a file
         self.exists(path)
     }
+
+    /// Directories only exist implicitly, as the parents of stored files, so a
+    /// directory with no files in it can't be listed
+    fn list_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FilesystemError> {
+        let key = self.key(path);
+        let files = self.files.read().map_err(|_| FilesystemError::LockError)?;
+        if files.contains_key(key.as_ref()) {
+            return Err(FilesystemError::NotAFile {
+                path: path.to_path_buf(),
+            });
+        }
+
+        // Each stored path under `path` contributes its first component below it
+        let mut entries: Vec<PathBuf> = files
+            .keys()
+            .filter_map(|stored| stored.strip_prefix(key.as_ref()).ok())
+            .filter_map(|rest| rest.components().next())
+            .map(|child| key.join(child))
+            .collect();
+        if entries.is_empty() {
+            return Err(FilesystemError::FileNotFound {
+                path: path.to_path_buf(),
+            });
+        }
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileMetadata, FilesystemError> {
+        let key = self.key(path);
+        let failures = self
+            .failures
+            .read()
+            .map_err(|_| FilesystemError::LockError)?;
+        if failures
+            .get(key.as_ref())
+            .is_some_and(|failure| failure.metadata)
+        {
+            return Err(FilesystemError::ReadError {
+                path: path.to_path_buf(),
+                source: std::io::Error::other("injected metadata failure"),
+            });
+        }
+        drop(failures);
+        let files = self.files.read().map_err(|_| FilesystemError::LockError)?;
+        let bytes = files
+            .get(key.as_ref())
+            .ok_or_else(|| FilesystemError::FileNotFound {
+                path: path.to_path_buf(),
+            })?;
+        let modified = self
+            .modified
+            .read()
+            .map_err(|_| FilesystemError::LockError)?;
+        Ok(FileMetadata {
+            len: bytes.len() as u64,
+            modified: modified.get(key.as_ref()).copied(),
+        })
+    }
+
+    /// The key `path` is stored under: as-is, unless a normalizer such as
+    /// [`MemoryFS::canonical`] maps several paths to one file
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, FilesystemError> {
+        Ok(self.key(path).into_owned())
+    }
+
+    fn is_binary(&self, path: &Path) -> Result<bool, FilesystemError> {
+        self.check_read(path)?;
+        let files = self.files.read().map_err(|_| FilesystemError::LockError)?;
+        let bytes =
+            files
+                .get(self.key(path).as_ref())
+                .ok_or_else(|| FilesystemError::FileNotFound {
+                    path: path.to_path_buf(),
+                })?;
+        Ok(bytes[..bytes.len().min(BINARY_SAMPLE_LEN)].contains(&0))
+    }
 }
 
 /// Streaming writer for [`MemoryFS`].
@@ -169,6 +441,26 @@ impl Drop for MemoryWriter {
     }
 }
 
+/// Resolve `.` and `..` components without consulting any filesystem
+fn lexically_normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `..` above the root is the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,6 +530,195 @@ mod tests {
         assert_eq!(fs.read_to_string(&path).unwrap(), content);
     }
 
+    #[test]
+    fn test_memory_fs_write_bytes() {
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("/fixture.bin");
+        let content = [0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe, b'\n'];
+
+        fs.write_bytes(&path, &content).unwrap();
+
+        assert_eq!(fs.read_bytes(&path).unwrap(), content);
+        assert!(fs.is_binary(&path).unwrap());
+        assert!(matches!(
+            fs.read_to_string(&path),
+            Err(FilesystemError::InvalidUtf8 { .. })
+        ));
+
+        // Through the trait object too, as the searcher sees it
+        let boxed: Box<dyn FileSystem> = Box::new(fs);
+        boxed.write_bytes(&path, &content[..4]).unwrap();
+        assert_eq!(boxed.read_bytes(&path).unwrap(), content[..4]);
+    }
+
+    #[test]
+    fn test_memory_fs_list_dir() {
+        let fs = MemoryFS::new();
+        for path in [
+            "/src/main.rs",
+            "/src/cli/mod.rs",
+            "/src/cli/search.rs",
+            "/README",
+        ] {
+            fs.add_file(Path::new(path), "").unwrap();
+        }
+
+        let listed = |path: &str| fs.list_dir(Path::new(path)).unwrap();
+        assert_eq!(
+            listed("/src"),
+            vec![PathBuf::from("/src/cli"), PathBuf::from("/src/main.rs")]
+        );
+        assert_eq!(
+            listed("/"),
+            vec![PathBuf::from("/README"), PathBuf::from("/src")]
+        );
+
+        // A file isn't a directory, and a prefix of a name isn't a parent
+        assert!(matches!(
+            fs.list_dir(Path::new("/README")),
+            Err(FilesystemError::NotAFile { .. })
+        ));
+        assert!(matches!(
+            fs.list_dir(Path::new("/sr")),
+            Err(FilesystemError::FileNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_memory_fs_case_insensitive() {
+        let fs = MemoryFS::new().with_case_insensitive(true);
+        fs.add_file(Path::new("/A/B.txt"), "content").unwrap();
+
+        let lower = Path::new("/a/b.txt");
+        assert!(fs.exists(lower));
+        assert!(fs.is_file(lower));
+        assert_eq!(fs.read_to_string(lower).unwrap(), "content");
+    }
+
+    #[test]
+    fn test_memory_fs_case_insensitive_constructor() {
+        let fs = MemoryFS::case_insensitive();
+        fs.add_file(Path::new("/Src/Main.rs"), "fn main() {}")
+            .unwrap();
+
+        assert!(fs.exists(Path::new("/src/main.rs")));
+        assert!(fs.exists(Path::new("/SRC/MAIN.RS")));
+    }
+
+    #[test]
+    fn test_memory_fs_canonical() {
+        let fs = MemoryFS::canonical();
+        fs.add_file(Path::new("/a/./b/../c.txt"), "content")
+            .unwrap();
+
+        assert_eq!(fs.read_to_string(Path::new("/a/c.txt")).unwrap(), "content");
+        assert!(fs.exists(Path::new("/a//x/../c.txt")));
+        assert!(fs.exists(Path::new("/../a/c.txt")));
+        assert!(!fs.exists(Path::new("/c.txt")));
+    }
+
+    #[test]
+    fn test_memory_fs_custom_path_normalizer() {
+        // Treat `\` as a separator, as Windows does
+        let fs = MemoryFS::new()
+            .with_path_normalizer(|path| PathBuf::from(path.to_string_lossy().replace('\\', "/")));
+        fs.add_file(Path::new("/src\\main.rs"), "content").unwrap();
+
+        assert!(fs.exists(Path::new("/src/main.rs")));
+    }
+
+    #[test]
+    fn test_memory_fs_case_sensitive_by_default() {
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("/A/B.txt"), "content").unwrap();
+
+        let lower = Path::new("/a/b.txt");
+        assert!(!fs.exists(lower));
+        assert!(fs.read_to_string(lower).is_err());
+    }
+
+    #[test]
+    fn test_memory_fs_metadata() {
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("/test.txt");
+        fs.add_file(&path, "hello").unwrap();
+
+        let metadata = fs.metadata(&path).unwrap();
+        assert_eq!(metadata.len, 5);
+        assert_eq!(metadata.modified, None);
+
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(100);
+        fs.set_modified(&path, time).unwrap();
+        assert_eq!(fs.metadata(&path).unwrap().modified, Some(time));
+
+        assert!(fs.metadata(Path::new("/missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_memory_fs_is_binary() {
+        let fs = MemoryFS::new();
+        let binary = PathBuf::from("/binary.bin");
+        let text = PathBuf::from("/text.txt");
+        fs.add_file_bytes(&binary, b"ELF\0\x01\x02 rest of file")
+            .unwrap();
+        fs.add_file(&text, "plain text\n").unwrap();
+
+        assert!(fs.is_binary(&binary).unwrap());
+        assert!(!fs.is_binary(&text).unwrap());
+        assert!(fs.is_binary(Path::new("/missing.bin")).is_err());
+    }
+
+    #[test]
+    fn test_memory_fs_is_binary_only_samples_prefix() {
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("/late_null.txt");
+        let mut content = vec![b'a'; BINARY_SAMPLE_LEN];
+        content.push(0);
+        fs.add_file_bytes(&path, &content).unwrap();
+
+        assert!(!fs.is_binary(&path).unwrap());
+    }
+
+    #[test]
+    fn test_memory_fs_fail_read() {
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("/test.txt");
+        fs.add_file(&path, "content").unwrap();
+
+        fs.fail_read(&path);
+        assert!(fs.exists(&path));
+        assert!(matches!(
+            fs.read_to_string(&path),
+            Err(FilesystemError::ReadError { .. })
+        ));
+        assert!(fs.read(&path).is_err());
+        // Writes are unaffected
+        fs.write_string(&path, "new").unwrap();
+
+        fs.clear_failures();
+        assert_eq!(fs.read_to_string(&path).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_memory_fs_fail_write() {
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("/test.txt");
+        fs.add_file(&path, "content").unwrap();
+
+        fs.fail_write(&path);
+        assert!(matches!(
+            fs.write_string(&path, "new"),
+            Err(FilesystemError::WriteError { .. })
+        ));
+        assert!(fs.writer(&path).is_err());
+        assert!(fs.remove_file(&path).is_err());
+        fs.add_file(Path::new("/other.txt"), "other").unwrap();
+        assert!(fs.rename(Path::new("/other.txt"), &path).is_err());
+
+        // Reads are unaffected, and the file is untouched
+        assert_eq!(fs.read_to_string(&path).unwrap(), "content");
+    }
+
     #[test]
     fn test_memory_fs_write_overwrites_existing() {
         let fs = MemoryFS::new();
@@ -10,8 +10,10 @@ pub mod staging;
 
 use std::{
     borrow::Cow,
+    io::{Read, Write},
     path::{Path, PathBuf},
     string::FromUtf8Error,
+    time::SystemTime,
 };
 use thiserror::Error;
 
@@ -53,6 +55,24 @@ pub enum FilesystemError {
     /// Lock error (for MemoryFS)
     #[error("Lock error")]
     LockError,
+
+    /// The filesystem doesn't implement this operation
+    #[error("Operation not supported for {path}")]
+    NotSupported { path: PathBuf },
+}
+
+/// How many leading bytes [`FileSystem::is_binary`] samples for a null byte
+///
+/// Matches the buffer size grep uses for its own binary detection heuristic.
+pub const BINARY_SAMPLE_LEN: usize = 8 * 1024;
+
+/// File metadata exposed through [`FileSystem::metadata`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// Size of the file in bytes
+    pub len: u64,
+    /// Last modification time, if the filesystem tracks one
+    pub modified: Option<SystemTime>,
 }
 
 /// Abstract filesystem interface
@@ -69,10 +89,46 @@ pub trait FileSystem: Send + Sync {
 
     fn read(&self, path: &Path) -> Result<Box<dyn std::io::Read>, FilesystemError>;
 
+    /// Read the entire contents of a file as bytes, whatever they hold
+    ///
+    /// The default reads [`FileSystem::read`] to the end.
+    ///
+    /// # Errors
+    /// Returns an error if the file doesn't exist or isn't readable.
+    #[allow(dead_code)]
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, FilesystemError> {
+        let mut bytes = Vec::new();
+        self.read(path)?
+            .read_to_end(&mut bytes)
+            .map_err(|source| FilesystemError::ReadError {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(bytes)
+    }
+
     // Whole-string write primitive; used by tests and as a convenience over `writer`.
     #[allow(dead_code)]
     fn write_string(&self, path: &Path, content: &str) -> Result<(), FilesystemError>;
 
+    /// Write `content` to `path`, creating it or truncating an existing file;
+    /// unlike [`FileSystem::write_string`] it needn't be UTF-8
+    ///
+    /// The default only handles UTF-8, passing it on to `write_string`.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written, or with the default, if
+    /// `content` isn't UTF-8.
+    #[allow(dead_code)]
+    fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), FilesystemError> {
+        let content =
+            String::from_utf8(content.to_vec()).map_err(|source| FilesystemError::InvalidUtf8 {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        self.write_string(path, &content)
+    }
+
     /// Open a streaming writer to `path`, creating it or truncating an existing file.
     ///
     /// This is the streaming dual of [`FileSystem::read`]; it lets callers write a
@@ -80,6 +136,30 @@ pub trait FileSystem: Send + Sync {
     /// contents up front.
     fn writer(&self, path: &Path) -> Result<Box<dyn std::io::Write>, FilesystemError>;
 
+    /// Open a streaming writer that appends to `path`, creating it if missing.
+    ///
+    /// The default copies the existing contents into a fresh [`FileSystem::writer`]
+    /// first; `PhysicalFS` opens the file in append mode instead.
+    fn appender(&self, path: &Path) -> Result<Box<dyn std::io::Write>, FilesystemError> {
+        let mut existing = Vec::new();
+        if self.exists(path) {
+            self.read(path)?
+                .read_to_end(&mut existing)
+                .map_err(|source| FilesystemError::ReadError {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+        }
+        let mut writer = self.writer(path)?;
+        writer
+            .write_all(&existing)
+            .map_err(|source| FilesystemError::WriteError {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(writer)
+    }
+
     /// Rename `from` to `to` within this filesystem (atomic on the real FS when both
     /// live on the same device).
     fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError>;
@@ -94,6 +174,114 @@ pub trait FileSystem: Send + Sync {
 
     /// Check if a path points to a file (not a directory)
     fn is_file(&self, path: &Path) -> bool;
+
+    /// Read a file's size and modification time without reading its contents
+    ///
+    /// # Errors
+    /// Returns an error if the file doesn't exist or its metadata can't be read.
+    fn metadata(&self, path: &Path) -> Result<FileMetadata, FilesystemError>;
+
+    /// Check whether a file looks binary without reading it fully
+    ///
+    /// Samples the first [`BINARY_SAMPLE_LEN`] bytes for a null byte, the same
+    /// heuristic the matcher uses to skip binaries mid-search.
+    ///
+    /// # Errors
+    /// Returns an error if the file doesn't exist or can't be read.
+    #[allow(dead_code)]
+    fn is_binary(&self, path: &Path) -> Result<bool, FilesystemError>;
+
+    /// The canonical form of `path`, the same for every path that reaches the
+    /// same file (through symlinks, `..`, or overlapping roots)
+    ///
+    /// The default returns `path` as-is, for filesystems without aliases.
+    ///
+    /// # Errors
+    /// Returns an error if the path can't be resolved.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, FilesystemError> {
+        Ok(path.to_path_buf())
+    }
+
+    /// List the entries of a directory, sorted: files and subdirectories alike,
+    /// each as `path` joined with its name
+    ///
+    /// Listing is optional; the default returns [`FilesystemError::NotSupported`].
+    ///
+    /// # Errors
+    /// Returns an error if `path` isn't a readable directory, or listing isn't
+    /// supported.
+    #[allow(dead_code)]
+    fn list_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FilesystemError> {
+        Err(FilesystemError::NotSupported {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl FileSystem for Box<dyn FileSystem> {
+    fn read_to_string(&self, path: &Path) -> Result<String, FilesystemError> {
+        (**self).read_to_string(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Box<dyn std::io::Read>, FilesystemError> {
+        (**self).read(path)
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, FilesystemError> {
+        (**self).read_bytes(path)
+    }
+
+    fn write_string(&self, path: &Path, content: &str) -> Result<(), FilesystemError> {
+        (**self).write_string(path, content)
+    }
+
+    fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), FilesystemError> {
+        (**self).write_bytes(path, content)
+    }
+
+    fn writer(&self, path: &Path) -> Result<Box<dyn std::io::Write>, FilesystemError> {
+        (**self).writer(path)
+    }
+
+    fn appender(&self, path: &Path) -> Result<Box<dyn std::io::Write>, FilesystemError> {
+        (**self).appender(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
+        (**self).rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), FilesystemError> {
+        (**self).remove_file(path)
+    }
+
+    fn as_real_path<'a>(&self, path: &'a Path) -> Option<Cow<'a, Path>> {
+        (**self).as_real_path(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        (**self).is_file(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileMetadata, FilesystemError> {
+        (**self).metadata(path)
+    }
+
+    fn is_binary(&self, path: &Path) -> Result<bool, FilesystemError> {
+        (**self).is_binary(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, FilesystemError> {
+        (**self).canonicalize(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FilesystemError> {
+        (**self).list_dir(path)
+    }
 }
 
 #[cfg(test)]
@@ -137,4 +325,18 @@ mod tests {
 
         test_filesystem_contract(fs, &test_path, test_content);
     }
+
+    #[test]
+    fn test_default_appender_keeps_existing_contents() {
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("/out.bk");
+
+        for line in ["first\n", "second\n"] {
+            let mut writer = fs.appender(&path).unwrap();
+            writer.write_all(line.as_bytes()).unwrap();
+            writer.flush().unwrap();
+        }
+
+        assert_eq!(fs.read_to_string(&path).unwrap(), "first\nsecond\n");
+    }
 }
@@ -3,38 +3,106 @@
 //! This module provides `PhysicalFS`, which uses the real OS filesystem.
 //! This is the production adapter used by the CLI.
 
-use super::{FileSystem, FilesystemError};
+use super::{BINARY_SAMPLE_LEN, FileMetadata, FileSystem, FilesystemError};
 use std::borrow::Cow;
 use std::fs;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Physical filesystem adapter
 ///
 /// This adapter uses `std::fs` to interact with the real filesystem.
 /// It's used in production but never in tests (tests use `MemoryFS`).
-#[derive(Debug, Clone, Copy, Default)]
-pub struct PhysicalFS;
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalFS {
+    retries: u32,
+    delay: Duration,
+    sleep: fn(Duration),
+}
+
+impl Default for PhysicalFS {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            delay: Duration::ZERO,
+            sleep: std::thread::sleep,
+        }
+    }
+}
 
 impl PhysicalFS {
     /// Create a new `PhysicalFS` instance
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Retry `read_to_string` and `write_string` up to `retries` times on
+    /// transient errors (`WouldBlock`, `Interrupted`), as seen on network mounts
+    /// or with antivirus software holding files
+    ///
+    /// The first retry waits `delay`, and each further retry doubles the wait.
+    /// Other errors, like `NotFound` or `PermissionDenied`, fail immediately.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_retry(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.delay = delay;
+        self
+    }
+
+    /// Replace the function used to wait between retries (tests pass a no-op)
+    #[cfg(test)]
+    fn with_sleep(mut self, sleep: fn(Duration)) -> Self {
+        self.sleep = sleep;
+        self
     }
+
+    /// Run `op`, retrying transient errors with exponential backoff
+    fn retry<T>(&self, mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+        let mut delay = self.delay;
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(err) if attempt < self.retries && is_transient(&err) => {
+                    tracing::debug!("Retrying after transient error: {}", err);
+                    (self.sleep)(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether an I/O error is worth retrying
+fn is_transient(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted
+    )
 }
 
 impl FileSystem for PhysicalFS {
     fn read_to_string(&self, path: &Path) -> Result<String, FilesystemError> {
-        fs::read_to_string(path).map_err(|source| FilesystemError::ReadError {
-            path: path.to_path_buf(),
-            source,
-        })
+        self.retry(|| fs::read_to_string(path))
+            .map_err(|source| FilesystemError::ReadError {
+                path: path.to_path_buf(),
+                source,
+            })
     }
 
     fn write_string(&self, path: &Path, content: &str) -> Result<(), FilesystemError> {
-        fs::write(path, content).map_err(|source| FilesystemError::WriteError {
-            path: path.to_path_buf(),
-            source,
-        })
+        self.write_bytes(path, content.as_bytes())
+    }
+
+    fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), FilesystemError> {
+        self.retry(|| fs::write(path, content))
+            .map_err(|source| FilesystemError::WriteError {
+                path: path.to_path_buf(),
+                source,
+            })
     }
 
     fn as_real_path<'a>(&self, path: &'a Path) -> Option<Cow<'a, Path>> {
@@ -49,6 +117,51 @@ impl FileSystem for PhysicalFS {
         path.is_file()
     }
 
+    fn metadata(&self, path: &Path) -> Result<FileMetadata, FilesystemError> {
+        let metadata = fs::metadata(path).map_err(|source| FilesystemError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(FileMetadata {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, FilesystemError> {
+        fs::canonicalize(path).map_err(|source| FilesystemError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn is_binary(&self, path: &Path) -> Result<bool, FilesystemError> {
+        let read_error = |source| FilesystemError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        };
+        let file = fs::File::open(path).map_err(read_error)?;
+        let mut prefix = Vec::with_capacity(BINARY_SAMPLE_LEN);
+        file.take(BINARY_SAMPLE_LEN as u64)
+            .read_to_end(&mut prefix)
+            .map_err(read_error)?;
+        Ok(prefix.contains(&0))
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FilesystemError> {
+        let read_error = |source| FilesystemError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut entries = fs::read_dir(path)
+            .map_err(read_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error)?;
+        entries.sort();
+        Ok(entries)
+    }
+
     fn read(&self, path: &Path) -> Result<Box<dyn std::io::Read>, FilesystemError> {
         let file = fs::File::open(path).map_err(|source| FilesystemError::ReadError {
             path: path.to_path_buf(),
@@ -65,6 +178,18 @@ impl FileSystem for PhysicalFS {
         Ok(Box::new(std::io::BufWriter::new(file)))
     }
 
+    fn appender(&self, path: &Path) -> Result<Box<dyn std::io::Write>, FilesystemError> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|source| FilesystemError::WriteError {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(Box::new(std::io::BufWriter::new(file)))
+    }
+
     fn rename(&self, from: &Path, to: &Path) -> Result<(), FilesystemError> {
         fs::rename(from, to).map_err(|source| FilesystemError::WriteError {
             path: to.to_path_buf(),
@@ -79,3 +204,63 @@ impl FileSystem for PhysicalFS {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+
+    /// An operation that fails with `kind` the first `failures` times it's called
+    fn flaky(failures: u32, kind: ErrorKind) -> impl FnMut() -> std::io::Result<u32> {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            if calls <= failures {
+                Err(Error::from(kind))
+            } else {
+                Ok(calls)
+            }
+        }
+    }
+
+    fn retrying_fs(retries: u32) -> PhysicalFS {
+        PhysicalFS::new()
+            .with_retry(retries, Duration::from_secs(60))
+            .with_sleep(|_| {})
+    }
+
+    #[test]
+    fn test_retry_succeeds_after_transient_failures() {
+        let fs = retrying_fs(3);
+
+        assert_eq!(fs.retry(flaky(2, ErrorKind::Interrupted)).unwrap(), 3);
+        assert_eq!(fs.retry(flaky(2, ErrorKind::WouldBlock)).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_retry_gives_up_after_limit() {
+        let fs = retrying_fs(1);
+
+        let err = fs.retry(flaky(2, ErrorKind::Interrupted)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_retry_fails_fast_on_permanent_errors() {
+        let fs = retrying_fs(3);
+
+        for kind in [ErrorKind::NotFound, ErrorKind::PermissionDenied] {
+            let mut op = flaky(1, kind);
+            assert_eq!(fs.retry(&mut op).unwrap_err().kind(), kind);
+            // Only one call was made: the next one succeeds on the second call
+            assert_eq!(op().unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_retry_disabled_by_default() {
+        let fs = PhysicalFS::new();
+
+        assert!(fs.retry(flaky(1, ErrorKind::Interrupted)).is_err());
+    }
+}
@@ -21,7 +21,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use super::{FileSystem, FilesystemError};
+use super::{FileMetadata, FileSystem, FilesystemError};
 
 /// A staged `(temp, target)` write recorded by [`StagingFs`].
 struct Staged {
@@ -107,9 +107,13 @@ impl FileSystem for StagingFs<'_> {
     }
 
     fn write_string(&self, path: &Path, content: &str) -> Result<(), FilesystemError> {
+        self.write_bytes(path, content.as_bytes())
+    }
+
+    fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), FilesystemError> {
         let mut writer = self.writer(path)?;
         writer
-            .write_all(content.as_bytes())
+            .write_all(content)
             .and_then(|()| writer.flush())
             .map_err(|source| FilesystemError::WriteError {
                 path: path.to_path_buf(),
@@ -142,6 +146,14 @@ impl FileSystem for StagingFs<'_> {
         self.inner.as_real_path(path)
     }
 
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, FilesystemError> {
+        self.inner.canonicalize(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FilesystemError> {
+        self.inner.list_dir(path)
+    }
+
     fn exists(&self, path: &Path) -> bool {
         self.inner.exists(path)
     }
@@ -149,6 +161,14 @@ impl FileSystem for StagingFs<'_> {
     fn is_file(&self, path: &Path) -> bool {
         self.inner.is_file(path)
     }
+
+    fn metadata(&self, path: &Path) -> Result<FileMetadata, FilesystemError> {
+        self.inner.metadata(path)
+    }
+
+    fn is_binary(&self, path: &Path) -> Result<bool, FilesystemError> {
+        self.inner.is_binary(path)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,350 @@
+//! Building a [`Format`] from differences: a unified diff (`git diff`,
+//! `diff -u`), or the old and new contents of a file
+//!
+//! For a unified diff, every hunk becomes one chunk: its header's old range is
+//! the lines the chunk replaces, and its context and `+` lines are the chunk's
+//! content. Applying the format then makes the same change as applying the
+//! patch, as long as the files still match the diff's old side (hunks aren't
+//! fuzzed or relocated).
+
+use std::path::{Path, PathBuf};
+
+use similar::{DiffOp, TextDiff};
+
+use thiserror::Error;
+
+use super::types::{Chunk, Format};
+
+/// Errors that can occur while reading a unified diff.
+#[derive(Debug, Error)]
+pub enum DiffError {
+    #[error("line {line}: hunk header `{header}` is not `@@ -start,count +start,count @@`")]
+    InvalidHunkHeader { line: usize, header: String },
+
+    #[error("line {line}: hunk comes before any `+++` file header")]
+    HunkWithoutFile { line: usize },
+
+    #[error("line {line}: expected a ` `, `-` or `+` line inside the hunk")]
+    MalformedHunk { line: usize },
+
+    #[error("the last hunk ends before all of the lines its header counts")]
+    TruncatedHunk,
+
+    #[error("line {line}: deleting {} is not supported", .path.display())]
+    DeletedFile { line: usize, path: PathBuf },
+
+    #[error("can't strip {strip} leading components from `{path}`")]
+    PathTooShort { path: String, strip: usize },
+}
+
+/// A hunk being read, with the old and new lines its header says are left
+struct OpenHunk {
+    chunk: Chunk,
+    old_left: usize,
+    new_left: usize,
+}
+
+impl Format {
+    /// Reads a unified diff as a format, one chunk per hunk
+    ///
+    /// `strip` removes that many leading components from each file name, like
+    /// `patch -p`: 1 turns git's `b/src/main.rs` into `src/main.rs`. Everything
+    /// outside file headers and hunks (`diff --git`, `index`, commit messages) is
+    /// ignored.
+    ///
+    /// # Errors
+    /// Returns an error if a hunk is malformed or cut short, a hunk has no file,
+    /// a file name has fewer than `strip` components, or the diff deletes a file.
+    pub fn from_unified_diff(diff: &str, strip: usize) -> Result<Self, DiffError> {
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut old_path: Option<PathBuf> = None;
+        let mut path: Option<PathBuf> = None;
+        let mut hunk: Option<OpenHunk> = None;
+        let mut last_marker = None;
+
+        for (idx, line) in diff.split_inclusive('\n').enumerate() {
+            let line_number = idx + 1;
+
+            if let Some(open) = hunk.as_mut() {
+                let (marker, text) = match line.split_at_checked(1) {
+                    Some((marker, text)) if marker != "\n" => (marker, text),
+                    // Some tools drop the lone space of an empty context line
+                    _ => (" ", "\n"),
+                };
+                match marker {
+                    " " if open.old_left > 0 && open.new_left > 0 => {
+                        open.chunk.content.push_str(text);
+                        open.old_left -= 1;
+                        open.new_left -= 1;
+                    }
+                    "-" if open.old_left > 0 => open.old_left -= 1,
+                    "+" if open.new_left > 0 => {
+                        open.chunk.content.push_str(text);
+                        open.new_left -= 1;
+                    }
+                    "\\" => {
+                        no_newline_at_eof(&mut open.chunk, last_marker);
+                        continue;
+                    }
+                    _ => return Err(DiffError::MalformedHunk { line: line_number }),
+                }
+                last_marker = marker.chars().next();
+                if open.old_left == 0 && open.new_left == 0 {
+                    chunks.push(hunk.take().expect("checked above").chunk);
+                }
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix("--- ") {
+                old_path = Some(PathBuf::from(file_name(name)));
+            } else if let Some(name) = line.strip_prefix("+++ ") {
+                let name = file_name(name);
+                if name == "/dev/null" {
+                    return Err(DiffError::DeletedFile {
+                        line: line_number,
+                        path: old_path.take().unwrap_or_default(),
+                    });
+                }
+                path = Some(strip_components(name, strip)?);
+            } else if line.starts_with("@@ ") {
+                let Some(path) = path.clone() else {
+                    return Err(DiffError::HunkWithoutFile { line: line_number });
+                };
+                let (old_start, old_len, new_len) =
+                    parse_hunk_header(line).ok_or_else(|| DiffError::InvalidHunkHeader {
+                        line: line_number,
+                        header: line.trim_end().to_string(),
+                    })?;
+                // An empty old range names the line *before* it, `-0,0` for the start
+                let start_line = if old_len == 0 {
+                    old_start + 1
+                } else {
+                    old_start
+                };
+                let open = OpenHunk {
+                    chunk: Chunk::new(path, start_line, old_len, String::new()),
+                    old_left: old_len,
+                    new_left: new_len,
+                };
+                if old_len == 0 && new_len == 0 {
+                    chunks.push(open.chunk);
+                } else {
+                    hunk = Some(open);
+                }
+            } else if line.starts_with('\\') {
+                // Marks the last line of the hunk just closed
+                if let Some(chunk) = chunks.last_mut() {
+                    no_newline_at_eof(chunk, last_marker);
+                }
+            }
+        }
+
+        if hunk.is_some() {
+            return Err(DiffError::TruncatedHunk);
+        }
+        Ok(Format(chunks))
+    }
+}
+
+impl Format {
+    /// The smallest format that turns `old` into `new` when applied to `path`
+    ///
+    /// Lines are compared with a Myers diff, and each run of changed lines
+    /// becomes one chunk covering just the `old` lines it replaces (none, for a
+    /// pure insertion), so unchanged lines never appear. This is the inverse of
+    /// applying: tools that produce a whole new file can emit a minimal format.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn from_file_diff(path: &Path, old: &str, new: &str) -> Self {
+        let diff = TextDiff::from_lines(old, new);
+        let new_lines = diff.new_slices();
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        for op in diff.ops() {
+            if let DiffOp::Equal { .. } = op {
+                continue;
+            }
+            let (old_range, new_range) = (op.old_range(), op.new_range());
+            let content: String = new_lines[new_range].concat();
+
+            // A delete next to an insert is one change
+            match chunks.last_mut() {
+                Some(chunk) if chunk.end_line() == old_range.start + 1 => {
+                    chunk.num_lines += old_range.len();
+                    chunk.content.push_str(&content);
+                }
+                _ => chunks.push(Chunk::new(
+                    path.to_path_buf(),
+                    old_range.start + 1,
+                    old_range.len(),
+                    content,
+                )),
+            }
+        }
+        for chunk in &mut chunks {
+            chunk.no_newline_eol = !chunk.content.is_empty() && !chunk.content.ends_with('\n');
+        }
+        Self(chunks)
+    }
+}
+
+/// Handle `\ No newline at end of file` after a line marked `marker`
+///
+/// After a `-` line it's about the old file only, which the chunk doesn't hold.
+fn no_newline_at_eof(chunk: &mut Chunk, marker: Option<char>) {
+    if matches!(marker, Some(' ' | '+')) && chunk.content.ends_with('\n') {
+        chunk.content.pop();
+        chunk.no_newline_eol = true;
+    }
+}
+
+/// The file name of a `---`/`+++` header, without any trailing timestamp
+fn file_name(header: &str) -> &str {
+    let name = header.trim_end_matches(['\n', '\r']);
+    name.split_once('\t').map_or(name, |(name, _)| name)
+}
+
+/// Remove `strip` leading `/`-separated components from `name`, like `patch -p`
+fn strip_components(name: &str, strip: usize) -> Result<PathBuf, DiffError> {
+    let mut rest = name;
+    for _ in 0..strip {
+        rest =
+            rest.split_once('/')
+                .map(|(_, rest)| rest)
+                .ok_or_else(|| DiffError::PathTooShort {
+                    path: name.to_string(),
+                    strip,
+                })?;
+    }
+    Ok(PathBuf::from(rest))
+}
+
+/// Parse `@@ -old_start[,old_len] +new_start[,new_len] @@` into
+/// `(old_start, old_len, new_len)`; a missing length means 1
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let ranges = line.strip_prefix("@@ ")?.split(" @@").next()?;
+    let (old, new) = ranges.split_once(' ')?;
+    let range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = range(old.strip_prefix('-')?)?;
+    let (_, new_len) = range(new.strip_prefix('+')?)?;
+    Some((old_start, old_len, new_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_FILES: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+index 1111111..2222222 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+diff --git a/b.txt b/b.txt
+--- a/b.txt
++++ b/b.txt
+@@ -4,0 +5,2 @@ fn context()
++new
++lines
+@@ -9 +11 @@
+-last
++LAST
+\\ No newline at end of file
+";
+
+    #[test]
+    fn test_from_unified_diff_two_files() {
+        let format = Format::from_unified_diff(TWO_FILES, 1).unwrap();
+
+        assert_eq!(format.len(), 3);
+        assert_eq!(
+            format.0[0],
+            Chunk::new("src/a.rs".into(), 1, 3, "one\nTWO\nthree\n".to_string())
+        );
+        assert_eq!(
+            format.0[1],
+            Chunk::new("b.txt".into(), 5, 0, "new\nlines\n".to_string())
+        );
+        assert_eq!(
+            format.0[2],
+            Chunk::new("b.txt".into(), 9, 1, "LAST".to_string()).with_no_newline_eol(true)
+        );
+    }
+
+    #[test]
+    fn test_from_unified_diff_strip() {
+        let format = Format::from_unified_diff(TWO_FILES, 0).unwrap();
+        assert_eq!(format.0[0].path, PathBuf::from("b/src/a.rs"));
+
+        assert!(matches!(
+            Format::from_unified_diff(TWO_FILES, 3),
+            Err(DiffError::PathTooShort { strip: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_file_diff_applies_to_new() {
+        let path = Path::new("a.txt");
+        let cases = [
+            ("a\nb\nc\n", "a\nB\nc\n"),
+            ("a\nb\nc\n", "a\nc\n"),
+            ("a\nc\n", "a\nb\nc\n"),
+            ("a\nb\n", "x\na\nb\ny\n"),
+            ("a\nb", "a\nb\nc"),
+            ("a\nb\n", "a\nb"),
+            ("a\nb", "a\n"),
+            ("1\n2\n3\n4\n5\n6\n", "1\ntwo\n3\n4\n6\nseven\n"),
+            ("", "new\n"),
+            ("same\n", "same\n"),
+        ];
+        for (old, new) in cases {
+            let format = Format::from_file_diff(path, old, new);
+            let applied = crate::apply::apply_format(&format.0, old).unwrap();
+            assert_eq!(applied, new, "{old:?} -> {new:?}: {format}");
+        }
+    }
+
+    #[test]
+    fn test_from_file_diff_is_minimal() {
+        let format = Format::from_file_diff(
+            Path::new("a.txt"),
+            "1\n2\n3\n4\n5\n6\n",
+            "1\ntwo\n3\n4\n6\nseven\n",
+        );
+
+        assert_eq!(
+            format.0,
+            vec![
+                Chunk::new("a.txt".into(), 2, 1, "two\n".to_string()),
+                Chunk::new("a.txt".into(), 5, 1, String::new()),
+                Chunk::new("a.txt".into(), 7, 0, "seven\n".to_string()),
+            ]
+        );
+        assert!(Format::from_file_diff(Path::new("a.txt"), "x\n", "x\n").is_empty());
+    }
+
+    #[test]
+    fn test_from_unified_diff_errors() {
+        assert!(matches!(
+            Format::from_unified_diff("@@ -1 +1 @@\n-a\n+b\n", 1),
+            Err(DiffError::HunkWithoutFile { line: 1 })
+        ));
+        assert!(matches!(
+            Format::from_unified_diff("--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n-a\n+b\n", 1),
+            Err(DiffError::TruncatedHunk)
+        ));
+        assert!(matches!(
+            Format::from_unified_diff("--- a/x\n+++ /dev/null\n@@ -1 +0,0 @@\n-a\n", 1),
+            Err(DiffError::DeletedFile { line: 2, .. })
+        ));
+    }
+}
@@ -1,9 +1,14 @@
-use aho_corasick::AhoCorasick;
-
-pub fn escape_content(content: &str) -> Display<'_, 2> {
-    let patterns = ["\\", "@"];
-    let replacements = ["\\\\", "\\@"];
-    Display::<2> {
+use aho_corasick::{AhoCorasick, MatchKind};
+
+/// Escape `\` and `@` so chunk content can't be mistaken for a delimiter
+///
+/// A run of `@@@` becomes `\@@@` rather than `\@\@\@`: escaping its first `@` is
+/// enough to keep it from ending the chunk, and it's the form a person (or a
+/// model) writing the format by hand reaches for.
+pub fn escape_content(content: &str) -> Display<'_, 3> {
+    let patterns = ["\\", "@@@", "@"];
+    let replacements = ["\\\\", "\\@@@", "\\@"];
+    Display::<3> {
         source: content,
         patterns,
         replacements,
@@ -18,7 +23,11 @@ pub struct Display<'a, const N: usize> {
 
 impl<'a, const N: usize> std::fmt::Display for Display<'a, N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let ac = AhoCorasick::new(self.patterns.as_slice())
+        // Earlier patterns win where several start at the same byte, so `@@@`
+        // is replaced whole rather than as three `@`s
+        let ac = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostFirst)
+            .build(self.patterns.as_slice())
             .expect("Failed to create Aho-Corasick matcher");
         let mut prev = 0;
         for mat in ac.find_iter(self.source) {
@@ -36,10 +45,11 @@ impl<'a, const N: usize> std::fmt::Display for Display<'a, N> {
     }
 }
 
-pub fn unescape_content(content: &str) -> Display<'_, 2> {
-    let patterns = ["\\@", "\\\\"];
-    let replacements = ["@", "\\"];
-    Display::<2> {
+/// Undo [`escape_content`]: `\@@@` is `@@@`, `\@` is `@` and `\\` is `\`
+pub fn unescape_content(content: &str) -> Display<'_, 3> {
+    let patterns = ["\\@@@", "\\@", "\\\\"];
+    let replacements = ["@@@", "@", "\\"];
+    Display::<3> {
         source: content,
         patterns,
         replacements,
@@ -94,6 +104,14 @@ mod tests {
         assert_eq!(escape_content("@@test@@").to_string(), "\\@\\@test\\@\\@");
     }
 
+    #[test]
+    fn test_escape_content_end_delimiter() {
+        assert_eq!(escape_content("@@@").to_string(), "\\@@@");
+        assert_eq!(escape_content("@@@@ @@").to_string(), "\\@@@\\@ \\@\\@");
+        assert_eq!(unescape_content("\\@@@- x").to_string(), "@@@- x");
+        assert_eq!(unescape_content("\\\\\\@@@").to_string(), "\\@@@");
+    }
+
     #[test]
     fn test_unescape_content_empty_string() {
         assert_eq!(unescape_content("").to_string(), "");
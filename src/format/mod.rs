@@ -1,6 +1,7 @@
+pub mod diff;
 pub mod escaping;
 pub mod parse;
 pub mod types;
 
 // Re-export public types
-pub use types::{Chunk, Format};
+pub use types::{Chunk, ChunkVerificationError, Format, FunctionHeaders};
@@ -1,6 +1,6 @@
 use super::escaping::unescape_content;
 use super::types::{Chunk, Format, FormatError};
-use nom::combinator::opt;
+use nom::combinator::{eof, opt};
 use nom::{
     IResult, Parser,
     branch::alt,
@@ -78,46 +78,134 @@ impl ParserError {
 
     pub(super) fn into_format_error(self, source: &str) -> FormatError {
         let src = source.to_string();
+        let offset = source.len() - self.suffix_len;
         match self.kind {
             ParserErrorKind::InvalidDelimiter => {
-                let offset = source.len() - self.suffix_len;
                 let end = source[offset..]
                     .find('\n')
                     .map_or(source.len(), |i| offset + i);
                 FormatError::InvalidDelimiter {
+                    suggestion: suggest_delimiter(&source[offset..end], &source[end..]),
                     src,
                     span: (offset, end - offset).into(),
                 }
             }
-            ParserErrorKind::InvalidLineNumber { value, len } => {
-                let offset = source.len() - self.suffix_len;
-                FormatError::InvalidLineNumber {
-                    value,
-                    src,
-                    span: (offset, len).into(),
-                }
-            }
-            ParserErrorKind::InvalidNumLines { value, len } => {
-                let offset = source.len() - self.suffix_len;
-                FormatError::InvalidNumLines {
-                    value,
-                    src,
-                    span: (offset, len).into(),
-                }
-            }
+            ParserErrorKind::InvalidLineNumber { value, len } => FormatError::InvalidLineNumber {
+                suggestion: suggest_number(line_around(source, offset), &value, "Line numbers"),
+                value,
+                src,
+                span: (offset, len).into(),
+            },
+            ParserErrorKind::InvalidNumLines { value, len } => FormatError::InvalidNumLines {
+                suggestion: suggest_number(line_around(source, offset), &value, "numlines"),
+                value,
+                src,
+                span: (offset, len).into(),
+            },
             ParserErrorKind::MissingEndDelimiter {
                 start_len,
                 start_suffix_len,
-            } => FormatError::MissingEndDelimiter {
-                src: src.clone(),
-                start_span: (source.len() - start_suffix_len, start_len).into(),
-                eof_span: (src.len().saturating_sub(1), 1).into(),
-            },
-            ParserErrorKind::Nom { .. } => FormatError::NoChunks { src },
+            } => {
+                let content_start = source.len() - start_suffix_len + start_len;
+                FormatError::MissingEndDelimiter {
+                    suggestion: suggest_end_delimiter(&source[content_start..]),
+                    src: src.clone(),
+                    start_span: (source.len() - start_suffix_len, start_len).into(),
+                    eof_span: (src.len().saturating_sub(1), 1).into(),
+                }
+            }
+            ParserErrorKind::Nom { .. } => no_chunks(source),
         }
     }
 }
 
+fn no_chunks(src: &str) -> FormatError {
+    FormatError::NoChunks {
+        src: src.to_string(),
+        suggestion: suggest_missing_at(src),
+    }
+}
+
+/// The full line of `source` containing byte `offset`, without its line ending
+fn line_around(source: &str, offset: usize) -> &str {
+    let start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    source[start..end].trim_end_matches('\r')
+}
+
+/// Whether `line` is a valid `@path:line:numlines` start delimiter
+fn is_start_delimiter(line: &str) -> bool {
+    start_delimiter(
+        &format!("{}\n", line.trim_end_matches('\r')),
+        FormatParserConfig::default(),
+    )
+    .is_ok()
+}
+
+/// `@path:10` is missing its numlines: suggest the number of lines before the next `@@@`
+fn suggest_delimiter(header: &str, rest: &str) -> Option<String> {
+    let header = header.trim_end_matches('\r');
+    let (path, line) = header.strip_prefix('@')?.rsplit_once(':')?;
+    if path.is_empty() || path.contains(':') || line.trim().parse::<usize>().is_err() {
+        return None;
+    }
+
+    let numlines = rest
+        .lines()
+        .skip(1)
+        .take_while(|l| !l.starts_with("@@@"))
+        .count()
+        .max(1);
+    Some(format!(
+        "Did you mean '@{path}:{}:{numlines}'?",
+        line.trim()
+    ))
+}
+
+/// A number with a sign or stray whitespace: suggest the header without them
+fn suggest_number(header: &str, value: &str, what: &str) -> Option<String> {
+    let fixed = value.trim().trim_start_matches(['-', '+']).trim();
+    if fixed.parse::<usize>().is_err() {
+        return None;
+    }
+
+    let fixed_header = header.replacen(&format!(":{value}"), &format!(":{fixed}"), 1);
+    if value.trim_start().starts_with('-') {
+        Some(format!(
+            "{what} can't be negative. Did you mean '{fixed_header}'?"
+        ))
+    } else {
+        Some(format!("Did you mean '{fixed_header}'?"))
+    }
+}
+
+/// A chunk that runs to EOF usually swallowed the next chunk's header, or has a
+/// mistyped end delimiter
+fn suggest_end_delimiter(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        if is_start_delimiter(line) {
+            Some(format!(
+                "Did you forget '@@@' before '{}'?",
+                line.trim_end_matches('\r')
+            ))
+        } else if matches!(line.trim(), "@" | "@@") {
+            Some("Did you mean '@@@'?".to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Headers written without the leading `@` (e.g. `src/main.rs:10:5`)
+fn suggest_missing_at(src: &str) -> Option<String> {
+    src.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .find(|line| !line.starts_with('@') && is_start_delimiter(&format!("@{line}")))
+        .map(|line| format!("Chunk headers start with '@'. Did you mean '@{line}'?"))
+}
+
 type ParseResult<'a, T> = IResult<&'a str, T, ParserError>;
 
 fn invalid_delimiter_error(input: &str) -> ParserError {
@@ -140,66 +228,134 @@ where
     })
 }
 
+/// Options for [`parse_format_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatParserConfig {
+    /// Read a leading drive letter as part of the path, so `@C:\Users\file.rs:10:5`
+    /// names `C:\Users\file.rs` (default: only on Windows)
+    ///
+    /// Only a single letter followed by `:\` or `:/` counts as a drive, so
+    /// headers like `@a:10:5` still parse as the relative path `a`.
+    pub allow_windows_paths: bool,
+
+    /// Let the end of input close the last chunk when its `@@@` is missing
+    /// (default: false)
+    ///
+    /// Chunks before it still need their `@@@`: if the unterminated content holds
+    /// another chunk header, it's still a missing delimiter error.
+    pub lenient_eof: bool,
+}
+
+// Only derivable where `cfg!(windows)` is false
+#[allow(clippy::derivable_impls)]
+impl Default for FormatParserConfig {
+    fn default() -> Self {
+        Self {
+            allow_windows_paths: cfg!(windows),
+            lenient_eof: false,
+        }
+    }
+}
+
+impl FormatParserConfig {
+    /// Read `@C:\...` headers as Windows paths, see
+    /// [`FormatParserConfig::allow_windows_paths`]
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_allow_windows_paths(mut self, allow: bool) -> Self {
+        self.allow_windows_paths = allow;
+        self
+    }
+
+    /// Accept a last chunk without `@@@`, see [`FormatParserConfig::lenient_eof`]
+    #[must_use]
+    pub fn with_lenient_eof(mut self, lenient_eof: bool) -> Self {
+        self.lenient_eof = lenient_eof;
+        self
+    }
+}
+
 /// Main entry point - parses the entire format
 pub fn parse_format(src: &str) -> Result<Format, FormatError> {
+    parse_format_with(src, FormatParserConfig::default())
+}
+
+/// Parses the entire format with the given options
+pub fn parse_format_with(src: &str, config: FormatParserConfig) -> Result<Format, FormatError> {
     // Skip leading whitespace/comments
     let (input, ()) = skip_whitespace_and_comments(src).map_err(|e| match e {
         nom::Err::Error(e) | nom::Err::Failure(e) => e.into_format_error(src),
-        nom::Err::Incomplete(_) => FormatError::NoChunks {
-            src: src.to_string(),
-        },
+        nom::Err::Incomplete(_) => no_chunks(src),
     })?;
 
     // Parse all chunks
-    let (_, chunks) = many0(preceded(skip_whitespace_and_comments, chunk_parser))
+    let (_, chunks) = many0(preceded(skip_whitespace_and_comments, chunk_parser(config)))
         .parse(input)
         .map_err(|e| match e {
             nom::Err::Error(e) | nom::Err::Failure(e) => e.into_format_error(src),
-            nom::Err::Incomplete(_) => FormatError::NoChunks {
-                src: src.to_string(),
-            },
+            nom::Err::Incomplete(_) => no_chunks(src),
         })?;
 
     if chunks.is_empty() {
-        return Err(FormatError::NoChunks {
-            src: src.to_string(),
-        });
+        return Err(no_chunks(src));
     }
 
     Ok(Format(chunks))
 }
 
 /// Returns a parser that consumes a chunk with context for better diagnostics.
-fn chunk_parser(input: &str) -> ParseResult<'_, Chunk> {
-    let chunk_start_suffix_len = input.len();
-    let header_len = input.split_inclusive('\n').next().map_or(0, str::len);
+fn chunk_parser<'a>(config: FormatParserConfig) -> impl Fn(&'a str) -> ParseResult<'a, Chunk> {
+    move |input| {
+        let chunk_start_suffix_len = input.len();
+        let header_len = input.split_inclusive('\n').next().map_or(0, str::len);
 
-    let (input, (path, line_number, numlines)) = start_delimiter(input)?;
+        let (input, (path, line_number, numlines)) = start_delimiter(input, config)?;
 
-    let (input, mut content) = chunk_content(chunk_start_suffix_len, header_len)(input)?;
+        let (input, mut content) =
+            chunk_content(chunk_start_suffix_len, header_len, config)(input)?;
 
-    let (input, no_newline_eol) = parse_end_delimiter_nom(input)?;
-    if no_newline_eol && content.ends_with('\n') {
-        content.pop();
-    }
+        let (input, no_newline_eol) = if input.is_empty() && config.lenient_eof {
+            // The chunk ran to the end of input; close it like a plain `@@@`
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            (input, false)
+        } else {
+            parse_end_delimiter_nom(input)?
+        };
+        if no_newline_eol && content.ends_with('\n') {
+            content.pop();
+        }
 
-    let unescaped_content = unescape_content(&content);
-    Ok((
-        input,
-        Chunk::new(path, line_number, numlines, unescaped_content.to_string())
-            .with_no_newline_eol(no_newline_eol),
-    ))
+        let unescaped_content = unescape_content(&content);
+        Ok((
+            input,
+            Chunk::new(path, line_number, numlines, unescaped_content.to_string())
+                .with_no_newline_eol(no_newline_eol),
+        ))
+    }
 }
 
 /// Parser for the start delimiter: @path:line:numlines
-fn start_delimiter(input: &str) -> ParseResult<'_, (PathBuf, usize, usize)> {
+fn start_delimiter(
+    input: &str,
+    config: FormatParserConfig,
+) -> ParseResult<'_, (PathBuf, usize, usize)> {
     // Use closures to lazily construct errors with the correct suffix length
     let invalid_failure = || nom::Err::Failure(invalid_delimiter_error(input));
     let invalid_error = || nom::Err::Error(invalid_delimiter_error(input));
 
     let (input, _) = char('@')(input).map_err(|_: nom::Err<ParserError>| invalid_error())?;
-    let (input, path_str) = take_till1(|c| c == ':' || c == '\n')(input)
+    // A drive letter's colon is part of the path, not a separator
+    let drive_len = if config.allow_windows_paths && has_drive_prefix(input) {
+        2
+    } else {
+        0
+    };
+    let (rest, tail) = take_till1(|c| c == ':' || c == '\n')(&input[drive_len..])
         .map_err(|_: nom::Err<ParserError>| invalid_failure())?;
+    let path_str = &input[..drive_len + tail.len()];
+    let input = rest;
     let (input, _) = char(':')(input).map_err(|_: nom::Err<ParserError>| invalid_failure())?;
 
     let (input, line_str) = take_till1(|c| c == ':' || c == '\n')(input)
@@ -225,20 +381,33 @@ fn start_delimiter(input: &str) -> ParseResult<'_, (PathBuf, usize, usize)> {
     Ok((input, (PathBuf::from(path_str), line_number, numlines)))
 }
 
+/// Whether `path` starts with a Windows drive, like `C:\` or `C:/`
+fn has_drive_prefix(path: &str) -> bool {
+    matches!(
+        path.as_bytes(),
+        [drive, b':', b'\\' | b'/', ..] if drive.is_ascii_alphabetic()
+    )
+}
+
 /// Parser factory for chunk content until the @@@ end delimiter.
 fn chunk_content<'a>(
     chunk_start_suffix_len: usize,
     header_len: usize,
+    config: FormatParserConfig,
 ) -> impl Fn(&'a str) -> ParseResult<'a, String> {
     move |mut current| {
         let mut content = String::new();
 
         loop {
+            // Content that is itself `@@@` is written `\@@@`, which doesn't end it
             if current.starts_with("@@@") {
                 return Ok((current, content));
             }
 
             if current.is_empty() {
+                if config.lenient_eof && !content.lines().any(is_start_delimiter) {
+                    return Ok((current, content));
+                }
                 return Err(nom::Err::Failure(ParserError::new(
                     current,
                     ParserErrorKind::MissingEndDelimiter {
@@ -267,15 +436,17 @@ fn chunk_content<'a>(
 
 /// Parse end delimiter: @@@ or @@@- (no newline at end of file)
 /// Allows any text after @@@ until the end of the line (which is ignored).
+/// The line may end in `\n`, `\r\n`, or the end of input, so a final `@@@` or
+/// `@@@\r` without a trailing newline is still a valid delimiter.
 fn parse_end_delimiter_nom(input: &str) -> ParseResult<'_, bool> {
     let (input, _) = tag("@@@").parse(input)?;
 
     let (input, opt_tag) = opt(tag("-")).parse(input)?;
     let is_no_newline_eol = opt_tag.is_some();
 
-    // Allow optional text after @@@ until end of line
+    // Allow optional text after @@@ until end of line (this also eats a `\r`)
     let (input, _) = opt(not_newline).parse(input)?;
-    let (input, _) = alt((recognize(newline), recognize(nom::combinator::eof))).parse(input)?;
+    let (input, _) = alt((recognize(newline), eof)).parse(input)?;
     Ok((input, is_no_newline_eol))
 }
 
@@ -296,7 +467,7 @@ fn skip_whitespace_and_comments(input: &str) -> ParseResult<'_, ()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::format::parse::start_delimiter;
+    use crate::format::parse::{FormatParserConfig, parse_format_with, start_delimiter};
 
     use super::super::types::{Format, FormatError};
     use std::path::PathBuf;
@@ -369,6 +540,17 @@ more content
         assert_eq!(format.0[0].content, "user@domain.com\\path\n");
     }
 
+    #[test]
+    fn test_format_from_str_escaped_end_delimiter_round_trips() {
+        let input = "@notes.md:1:3\n\\@@@\n\\@@@- not the end\ntext \\@@@\n@@@\n";
+
+        let format = Format::from_str(input).unwrap();
+        assert_eq!(format.0.len(), 1);
+        assert_eq!(format.0[0].content, "@@@\n@@@- not the end\ntext @@@\n");
+        assert_eq!(format.to_string(), input);
+        assert_eq!(Format::from_str(&format.to_string()).unwrap().0, format.0);
+    }
+
     #[test]
     fn test_format_from_str_invalid_delimiter() {
         let input = "@test.txt:invalid\ncontent\n@@@\n";
@@ -412,6 +594,58 @@ more content
         }
     }
 
+    fn help(input: &str) -> Option<String> {
+        use miette::Diagnostic;
+        Format::from_str(input)
+            .unwrap_err()
+            .help()
+            .map(|h| h.to_string())
+    }
+
+    #[test]
+    fn test_format_error_suggestions() {
+        // Missing numlines: counted from the chunk's content
+        assert_eq!(
+            help("@src/a.rs:10\none\ntwo\n@@@\n").as_deref(),
+            Some("Did you mean '@src/a.rs:10:2'?")
+        );
+        // Negative line number / numlines
+        assert_eq!(
+            help("@src/a.rs:-10:5\ncontent\n@@@\n").as_deref(),
+            Some("Line numbers can't be negative. Did you mean '@src/a.rs:10:5'?")
+        );
+        assert_eq!(
+            help("@src/a.rs:10:-1\ncontent\n@@@\n").as_deref(),
+            Some("numlines can't be negative. Did you mean '@src/a.rs:10:1'?")
+        );
+        // Forgotten `@@@` before the next chunk
+        assert_eq!(
+            help("@a.txt:1:1\na\n@b.txt:1:1\nb\n").as_deref(),
+            Some("Did you forget '@@@' before '@b.txt:1:1'?")
+        );
+        // Header without the leading `@`
+        assert_eq!(
+            help("src/a.rs:10:1\ncontent\n").as_deref(),
+            Some("Chunk headers start with '@'. Did you mean '@src/a.rs:10:1'?")
+        );
+    }
+
+    #[test]
+    fn test_format_error_without_suggestion_keeps_generic_help() {
+        let err = Format::from_str("@test.txt:not_a_number:5\ncontent\n@@@\n").unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::InvalidLineNumber {
+                suggestion: None,
+                ..
+            }
+        ));
+        assert_eq!(
+            help("@test.txt:not_a_number:5\ncontent\n@@@\n").as_deref(),
+            Some("Line number must be a positive integer")
+        );
+    }
+
     #[test]
     fn test_format_error_invalid_numlines() {
         let input = "@test.txt:10:invalid\ncontent\n@@@\n";
@@ -452,6 +686,44 @@ more content
         }
     }
 
+    #[test]
+    fn test_format_windows_paths() {
+        let input = "@C:\\Users\\file.rs:10:1\nfn main() {}\n@@@\n";
+        let windows = FormatParserConfig::default().with_allow_windows_paths(true);
+
+        let format = parse_format_with(input, windows).unwrap();
+        assert_eq!(format.0[0].path, PathBuf::from("C:\\Users\\file.rs"));
+        assert_eq!(format.0[0].start_line, 10);
+        assert_eq!(format.0[0].num_lines, 1);
+
+        let format = parse_format_with("@d:/src/a.rs:2:1\nx\n@@@\n", windows).unwrap();
+        assert_eq!(format.0[0].path, PathBuf::from("d:/src/a.rs"));
+
+        // A one-letter relative path isn't mistaken for a drive
+        let format = parse_format_with("@a:10:1\nx\n@@@\n", windows).unwrap();
+        assert_eq!(format.0[0].path, PathBuf::from("a"));
+
+        let posix = FormatParserConfig::default().with_allow_windows_paths(false);
+        assert!(parse_format_with(input, posix).is_err());
+    }
+
+    #[test]
+    fn test_format_lenient_eof() {
+        let lenient = FormatParserConfig::default().with_lenient_eof(true);
+
+        let input = "@src/main.rs:10:2\nfn main() {\n}";
+        assert!(parse_format_with(input, FormatParserConfig::default()).is_err());
+        let format = parse_format_with(input, lenient).unwrap();
+        assert_eq!(format.0.len(), 1);
+        assert_eq!(format.0[0].content, "fn main() {\n}\n");
+        assert!(!format.0[0].no_newline_eol);
+
+        // Only the last chunk may skip its `@@@`
+        let input = "@a.rs:1:1\nx\n@b.rs:1:1\ny\n";
+        let err = parse_format_with(input, lenient).unwrap_err();
+        assert!(matches!(err, FormatError::MissingEndDelimiter { .. }));
+    }
+
     #[test]
     fn test_format_from_str_with_trailing_text_after_delimiter() {
         // End delimiter can have trailing text/comments after @@@
@@ -498,10 +770,38 @@ line 10
         // Test that Windows line endings (\r\n) are preserved in content
         let input = "@test.txt:1:2\r\n";
 
-        let res = start_delimiter(input).unwrap();
+        let res = start_delimiter(input, FormatParserConfig::default()).unwrap();
         assert_eq!(res.0, "");
     }
 
+    #[test]
+    fn test_format_end_delimiter_at_eof() {
+        // No newline after the final @@@ (e.g. pasted from a terminal)
+        let input = "@f.txt:1:1\ncontent\n@@@";
+
+        let format = Format::from_str(input).unwrap();
+        assert_eq!(format.0.len(), 1);
+        assert_eq!(format.0[0].path, PathBuf::from("f.txt"));
+        assert_eq!(format.0[0].content, "content\n");
+        assert!(!format.0[0].no_newline_eol);
+    }
+
+    #[test]
+    fn test_format_crlf_end_delimiter_at_eof() {
+        // Windows file whose last line is `@@@\r` with no trailing `\n`
+        let input = "@f.txt:1:1\r\ncontent\r\n@@@\r";
+
+        let format = Format::from_str(input).unwrap();
+        assert_eq!(format.0.len(), 1);
+        assert_eq!(format.0[0].content, "content\r\n");
+        assert!(!format.0[0].no_newline_eol);
+
+        let input = "@a.txt:1:1\r\na\r\n@@@\r\n@b.txt:2:1\r\nb\r\n@@@-\r";
+        let format = Format::from_str(input).unwrap();
+        assert_eq!(format.0.len(), 2);
+        assert!(format.0[1].no_newline_eol);
+    }
+
     #[test]
     fn test_format_mixed_line_endings() {
         // Test mixed line endings - some CRLF, some LF
@@ -4,25 +4,35 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use thiserror::Error;
 
+use crate::filesystem::{FileSystem, FilesystemError};
+use crate::types::ContextLine;
+
+/// Lines of the file shown around each chunk by [`Format::display_annotated`]
+pub const ANNOTATED_CONTEXT: usize = 2;
+
 /// Errors that can occur while parsing the format.
+///
+/// A `suggestion`, when present, is a likely fix and replaces the default help
+/// text.
 #[derive(Debug, Error, Diagnostic)]
 pub enum FormatError {
     #[error("Invalid start delimiter")]
     #[diagnostic(
         code(format::invalid_delimiter),
-        help("Expected format: @<path>:<line>:<numlines>")
+        help("{}", suggestion.as_deref().unwrap_or("Expected format: @<path>:<line>:<numlines>"))
     )]
     InvalidDelimiter {
         #[source_code]
         src: String,
         #[label("Invalid delimiter here")]
         span: SourceSpan,
+        suggestion: Option<String>,
     },
 
     #[error("Invalid line number: {value}")]
     #[diagnostic(
         code(format::invalid_line_number),
-        help("Line number must be a positive integer")
+        help("{}", suggestion.as_deref().unwrap_or("Line number must be a positive integer"))
     )]
     InvalidLineNumber {
         value: String,
@@ -30,12 +40,13 @@ pub enum FormatError {
         src: String,
         #[label("Expected a number here")]
         span: SourceSpan,
+        suggestion: Option<String>,
     },
 
     #[error("Invalid numlines: {value}")]
     #[diagnostic(
         code(format::invalid_numlines),
-        help("numlines must be a positive integer")
+        help("{}", suggestion.as_deref().unwrap_or("numlines must be a positive integer"))
     )]
     InvalidNumLines {
         value: String,
@@ -43,12 +54,13 @@ pub enum FormatError {
         src: String,
         #[label("Expected a number here")]
         span: SourceSpan,
+        suggestion: Option<String>,
     },
 
     #[error("Missing end delimiter @@@")]
     #[diagnostic(
         code(format::missing_end_delimiter),
-        help("Each chunk must be terminated with @@@")
+        help("{}", suggestion.as_deref().unwrap_or("Each chunk must be terminated with @@@"))
     )]
     MissingEndDelimiter {
         #[source_code]
@@ -57,17 +69,28 @@ pub enum FormatError {
         start_span: SourceSpan,
         #[label("Expected @@@ before end of file")]
         eof_span: SourceSpan,
+        suggestion: Option<String>,
     },
 
     #[error("No chunks found in input")]
     #[diagnostic(
         code(format::no_chunks),
-        help("File must contain at least one chunk starting with @<path>:<line>:<numlines>")
+        help(
+            "{}",
+            suggestion.as_deref().unwrap_or(
+                "File must contain at least one chunk starting with @<path>:<line>:<numlines>"
+            )
+        )
     )]
     NoChunks {
         #[source_code]
         src: String,
+        suggestion: Option<String>,
     },
+
+    #[error("Failed to read chunk: {0}")]
+    #[diagnostic(code(format::io))]
+    Io(#[from] std::io::Error),
 }
 
 /// Format represents a structured file format for storing code chunks with metadata.
@@ -129,6 +152,31 @@ impl Format {
         self.0.len()
     }
 
+    /// Returns true if the format has no chunks.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the chunks by reference.
+    #[allow(dead_code)]
+    pub fn iter(&self) -> std::slice::Iter<'_, Chunk> {
+        self.0.iter()
+    }
+
+    /// Parses like [`FromStr`], but lets the end of input stand in for the last
+    /// chunk's missing `@@@`, which is easy to forget when editing by hand
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`FromStr`], except a missing final `@@@`.
+    #[allow(dead_code)]
+    pub fn from_str_lenient(s: &str) -> Result<Self, FormatError> {
+        use crate::format::parse::{FormatParserConfig, parse_format_with};
+        parse_format_with(s, FormatParserConfig::default().with_lenient_eof(true))
+    }
+
     /// Converts a slice of match results into a Format.
     /// Each match result is converted to a chunk containing the match line
     /// along with its before and after context lines.
@@ -164,8 +212,9 @@ impl Format {
                     content.push_str(&ctx.content);
                 }
 
-                let num_lines =
-                    match_result.context_before.len() + 1 + match_result.context_after.len();
+                let num_lines = match_result.context_before.len()
+                    + match_result.line_count()
+                    + match_result.context_after.len();
 
                 let no_newline_eol = !content.ends_with('\n');
 
@@ -185,6 +234,111 @@ impl Format {
         res
     }
 
+    /// Converts match results into a Format with one chunk per run of lines
+    ///
+    /// Unlike [`Format::from_matches`], which builds a chunk per match so that
+    /// matches with overlapping context yield overlapping chunks, this groups the
+    /// matches by file and covers the union of all match and context lines with
+    /// non-overlapping chunks. Where one match's context is another's match line,
+    /// the match line wins, so edits made to match lines (such as
+    /// `search --replace`) survive. Each chunk highlights the first match in it.
+    pub fn from_matches_grouped(matches: &[crate::types::MatchResult]) -> Self {
+        use std::collections::BTreeMap;
+
+        // (path, line number) -> line content, plus the match on that line
+        let mut lines: BTreeMap<(&Path, usize), (&str, Option<&crate::types::MatchResult>)> =
+            BTreeMap::new();
+        for m in matches {
+            for ctx in m.context_before.iter().chain(&m.context_after) {
+                lines
+                    .entry((&m.file_path, ctx.line_number))
+                    .or_insert((&ctx.content, None));
+            }
+        }
+        for m in matches {
+            // A multiline match's whole content is keyed under its first line,
+            // and the other lines it spanned are covered with no content, so a
+            // replacement with a different number of lines still replaces them
+            let line = lines
+                .entry((&m.file_path, m.line_number))
+                .or_insert((&m.line_content, None));
+            // The first match on a line is the one shown
+            if line.1.is_none() {
+                *line = (&m.line_content, Some(m));
+            }
+            for idx in 1..m.line_count() {
+                let line = lines
+                    .entry((&m.file_path, m.line_number + idx))
+                    .or_insert(("", None));
+                if line.1.is_none() {
+                    line.0 = "";
+                }
+            }
+        }
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut current: Option<(Chunk, bool)> = None;
+        for ((path, line_number), (content, m)) in lines {
+            let continues = current
+                .as_ref()
+                .is_some_and(|(chunk, _)| chunk.path == path && chunk.end_line() == line_number);
+            if !continues {
+                chunks.extend(current.take().map(|(chunk, _)| chunk));
+                current = Some((
+                    Chunk::new(path.to_path_buf(), line_number, 0, String::new()),
+                    false,
+                ));
+            }
+            let (chunk, has_match) = current.as_mut().expect("a chunk was just started");
+
+            if let Some(m) = m
+                && !*has_match
+            {
+                *has_match = true;
+                let offset = chunk.content.len();
+                chunk.match_range = m
+                    .line_match
+                    .as_ref()
+                    .map(|range| range.start + offset..range.end + offset);
+            }
+            chunk.content.push_str(content);
+            chunk.num_lines += 1;
+            if !content.is_empty() {
+                chunk.no_newline_eol = !content.ends_with('\n');
+            }
+        }
+        chunks.extend(current.map(|(chunk, _)| chunk));
+
+        Self(chunks)
+    }
+
+    /// Builds a format that inserts `content` after every matched line
+    ///
+    /// Each chunk replaces no lines (`num_lines == 0`) and starts on the line
+    /// after its match, so applying the format adds `content` below each match
+    /// and leaves every other line as it was. Several matches on one line insert
+    /// once. A missing final newline is added to `content`.
+    #[allow(dead_code)]
+    pub fn insert_after_matches(matches: &[crate::types::MatchResult], content: &str) -> Self {
+        let mut content = content.to_string();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+
+        let lines: std::collections::BTreeSet<(&Path, usize)> = matches
+            .iter()
+            .map(|m| (m.file_path.as_path(), m.line_number))
+            .collect();
+        let chunks = lines
+            .into_iter()
+            .map(|(path, line_number)| {
+                Chunk::new(path.to_path_buf(), line_number + 1, 0, content.clone())
+            })
+            .collect();
+
+        Self(chunks)
+    }
+
     fn sort(&mut self) {
         self.0.sort_by(|c1, c2| c1.as_ref().cmp(&c2.as_ref()));
     }
@@ -195,6 +349,36 @@ impl Format {
     #[allow(dead_code)]
     pub fn merge(&mut self) {
         self.sort();
+        self.merge_sorted();
+    }
+
+    /// Sorts, drops exact duplicate chunks, and merges overlapping or adjacent
+    /// ones, leaving the format ready to apply.
+    ///
+    /// Returns the number of chunks removed.
+    pub fn normalize(&mut self) -> usize {
+        let before = self.0.len();
+        self.sort();
+        self.0.dedup();
+        self.merge_sorted();
+        before - self.0.len()
+    }
+
+    /// Replaces every chunk's content with `mutation(content)`, recounting
+    /// `num_lines` to match (e.g. to strip trailing whitespace everywhere).
+    ///
+    /// Since `num_lines` is how many file lines a chunk replaces on apply, a
+    /// mutation that adds or drops lines also changes the span each chunk covers.
+    #[allow(dead_code)]
+    pub fn apply_content_mutation<F: Fn(&str) -> String>(&mut self, mutation: F) {
+        for chunk in &mut self.0 {
+            chunk.content = mutation(&chunk.content);
+            chunk.num_lines = chunk.content.split_inclusive('\n').count();
+        }
+    }
+
+    /// Combines consecutive mergeable chunks; expects the chunks to be sorted.
+    fn merge_sorted(&mut self) {
         if self.0.len() < 2 {
             return;
         }
@@ -221,6 +405,251 @@ impl Format {
         self.0 = result;
     }
 
+    /// Merges chunks like [`Format::merge`], but also merges chunks separated by
+    /// at most `gap_tolerance` lines.
+    ///
+    /// The lines in each gap are read from `fs`, so the merged chunk still
+    /// mirrors the file exactly and applies cleanly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file with a gap to fill can't be read.
+    #[allow(dead_code)]
+    pub fn merge_with_tolerance(
+        &mut self,
+        gap_tolerance: usize,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FilesystemError> {
+        self.sort();
+        if self.0.len() < 2 {
+            return Ok(());
+        }
+
+        let mut result = Vec::new();
+        let mut chunks = std::mem::take(&mut self.0).into_iter();
+        let mut current = chunks.next().expect("at least two chunks");
+        // Lines of the file `current` belongs to, read on the first gap in it
+        let mut lines: Option<(PathBuf, Vec<String>)> = None;
+
+        for chunk in chunks {
+            let chunk = match current.merge(chunk) {
+                Ok(()) => continue,
+                Err(chunk) => chunk,
+            };
+
+            let end = current.end_line();
+            if !current.can_merge_with_gap(&chunk, gap_tolerance) || current.no_newline_eol {
+                result.push(std::mem::replace(&mut current, chunk));
+                continue;
+            }
+
+            if lines.as_ref().is_none_or(|(path, _)| *path != current.path) {
+                let content = fs.read_to_string(&current.path)?;
+                let file_lines = content.split_inclusive('\n').map(String::from).collect();
+                lines = Some((current.path.clone(), file_lines));
+            }
+            let file_lines = lines
+                .as_ref()
+                .map(|(_, l)| l.as_slice())
+                .unwrap_or_default();
+
+            // Lines are 1-indexed: the gap is lines `end..chunk.start_line`
+            let Some(gap) = file_lines.get(end - 1..chunk.start_line - 1) else {
+                result.push(std::mem::replace(&mut current, chunk));
+                continue;
+            };
+            let gap = Chunk::new(current.path.clone(), end, gap.len(), gap.concat());
+            current
+                .merge(gap)
+                .expect("gap chunk starts where current ends");
+            if let Err(chunk) = current.merge(chunk) {
+                result.push(std::mem::replace(&mut current, chunk));
+            }
+        }
+
+        result.push(current);
+        self.0 = result;
+        Ok(())
+    }
+
+    /// Whether applying the format would change any file by more than whitespace,
+    /// see [`Chunk::content_eq_ignoring_whitespace`]
+    ///
+    /// A chunk whose lines can't be read (a missing file, or lines past its end)
+    /// counts as a change, so this errs on the side of "something to apply".
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn has_effective_changes(&self, fs: &dyn FileSystem) -> bool {
+        let mut cached: Option<(&Path, String)> = None;
+        self.0.iter().any(|chunk| {
+            if cached.as_ref().is_none_or(|(path, _)| *path != chunk.path) {
+                let Ok(content) = fs.read_to_string(&chunk.path) else {
+                    return true;
+                };
+                cached = Some((&chunk.path, content));
+            }
+            let (_, content) = cached.as_ref().expect("cached above");
+            let original: Vec<&str> = content
+                .split_inclusive('\n')
+                .skip(chunk.start_line.saturating_sub(1))
+                .take(chunk.num_lines)
+                .collect();
+            original.len() < chunk.num_lines
+                || !eq_ignoring_whitespace(&original.concat(), &chunk.content)
+        })
+    }
+
+    /// Checks every chunk against the lines it covers in its file
+    ///
+    /// Returns one error per chunk whose content isn't exactly those lines, e.g.
+    /// because the file changed since the chunks were generated. An empty result
+    /// means the format is still an accurate snapshot of the files.
+    #[must_use]
+    pub fn verify_against_fs(&self, fs: &dyn FileSystem) -> Vec<ChunkVerificationError> {
+        let mut cached: Option<(&Path, Option<String>)> = None;
+        let mut errors = Vec::new();
+        for chunk in &self.0 {
+            if cached.as_ref().is_none_or(|(path, _)| *path != chunk.path) {
+                cached = Some((&chunk.path, fs.read_to_string(&chunk.path).ok()));
+            }
+            let (_, content) = cached.as_ref().expect("cached above");
+            let actual = content.as_ref().map(|content| {
+                content
+                    .split_inclusive('\n')
+                    .skip(chunk.start_line.saturating_sub(1))
+                    .take(chunk.num_lines)
+                    .collect::<String>()
+            });
+            if actual.as_deref() != Some(chunk.content.as_str()) {
+                errors.push(ChunkVerificationError {
+                    chunk: chunk.clone(),
+                    expected: chunk.content.clone(),
+                    actual,
+                });
+            }
+        }
+        errors
+    }
+
+    /// Writes the format to `writer`, exactly as its `Display` does, and returns
+    /// where each chunk starts in the output
+    ///
+    /// With the index, [`FormatIndex::read_chunk`] can read one chunk of a large
+    /// format file without parsing the rest.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    #[allow(dead_code)]
+    pub fn write_with_index<W: std::io::Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<FormatIndex, std::io::Error> {
+        let mut index = FormatIndex::default();
+        let mut offset = 0;
+        for (idx, chunk) in self.0.iter().enumerate() {
+            let separator = if idx == 0 { "" } else { "\n" };
+            let comment = format!(
+                "{separator}{}",
+                Rendered(|f| display_function_comment(f, chunk, None))
+            );
+            let body = Rendered(|f| display_chunk(f, chunk, false)).to_string();
+
+            writer.write_all(comment.as_bytes())?;
+            offset += comment.len();
+            index
+                .offsets
+                .entry((chunk.path.clone(), chunk.start_line))
+                .or_insert(offset);
+            writer.write_all(body.as_bytes())?;
+            offset += body.len();
+        }
+        Ok(index)
+    }
+
+    /// Renders each chunk in place in its file, for review
+    ///
+    /// Every chunk gets a `path:first-last` heading naming the lines it covers,
+    /// then its lines marked `>`, between up to [`ANNOTATED_CONTEXT`] lines of
+    /// the file on either side, marked `|`. All numbers are the file's own: a
+    /// chunk's lines are numbered through the lines it replaces, and any lines
+    /// it adds beyond those are marked `+` with no number.
+    ///
+    /// # Errors
+    /// Returns an error if a chunk's file can't be read.
+    #[allow(dead_code)]
+    pub fn display_annotated(&self, fs: &dyn FileSystem) -> Result<String, FilesystemError> {
+        use std::fmt::Write as _;
+
+        let mut out = String::new();
+        let mut cached: Option<(&Path, String)> = None;
+        for (idx, chunk) in self.0.iter().enumerate() {
+            if cached.as_ref().is_none_or(|(path, _)| *path != chunk.path) {
+                cached = Some((&chunk.path, fs.read_to_string(&chunk.path)?));
+            }
+            let (_, content) = cached.as_ref().expect("cached above");
+            let file_lines: Vec<&str> = content.split_inclusive('\n').collect();
+
+            if idx != 0 {
+                out.push('\n');
+            }
+            let last = chunk.end_line().saturating_sub(1).max(chunk.start_line);
+            let _ = writeln!(
+                out,
+                "{}:{}-{}",
+                chunk.path.display(),
+                chunk.start_line,
+                last
+            );
+
+            let mut line = |line_no: Option<usize>, marker: char, text: &str| {
+                let _ = match line_no {
+                    Some(line_no) => write!(out, "  {line_no:4} {marker} {text}"),
+                    None => write!(out, "       {marker} {text}"),
+                };
+                if !text.ends_with('\n') {
+                    out.push('\n');
+                }
+            };
+            let before = chunk.start_line.saturating_sub(ANNOTATED_CONTEXT).max(1);
+            for line_no in before..chunk.start_line {
+                if let Some(text) = file_lines.get(line_no - 1) {
+                    line(Some(line_no), '|', text);
+                }
+            }
+            for (line_no, text) in (chunk.start_line..).zip(chunk.content.split_inclusive('\n')) {
+                if line_no < chunk.end_line() {
+                    line(Some(line_no), '>', text);
+                } else {
+                    line(None, '+', text);
+                }
+            }
+            for line_no in chunk.end_line()..chunk.end_line() + ANNOTATED_CONTEXT {
+                if let Some(text) = file_lines.get(line_no - 1) {
+                    line(Some(line_no), '|', text);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Shifts the chunks in `path` that start at or after `applied_chunk`'s end
+    /// by `line_delta` lines, so they keep pointing at the same text once
+    /// `applied_chunk` has been written and grown or shrunk the file by that much
+    ///
+    /// `applied_chunk` itself, and chunks in other files, are left alone. A chunk
+    /// is never moved before line 1.
+    pub fn rebase_after_apply(&mut self, path: &Path, applied_chunk: &Chunk, line_delta: i64) {
+        let end = applied_chunk.end_line();
+        for chunk in &mut self.0 {
+            if chunk.path != path || chunk.start_line < end || chunk == applied_chunk {
+                continue;
+            }
+            let start = i64::try_from(chunk.start_line).unwrap_or(i64::MAX);
+            chunk.start_line =
+                usize::try_from(start.saturating_add(line_delta).max(1)).unwrap_or(1);
+        }
+    }
+
     pub fn file_chunks(&mut self) -> Vec<(&Path, &[Chunk])> {
         self.sort();
 
@@ -251,11 +680,138 @@ impl Format {
         Display {
             format: self,
             plain,
+            heading: false,
+            highlight,
+            show_line_numbers: false,
+            function_headers: None,
+        }
+    }
+
+    /// Human-readable text grouped by file, like `grep --heading`: each file's
+    /// path once (bold if `highlight`), then its chunks indented and separated
+    /// by `--`, then a blank line. Like the plain display, it can't be applied.
+    #[must_use]
+    pub fn display_headings(&self, highlight: bool) -> Display<'_> {
+        Display {
+            format: self,
+            plain: true,
+            heading: true,
             highlight,
+            show_line_numbers: false,
+            function_headers: None,
         }
     }
 }
 
+impl IntoIterator for Format {
+    type Item = Chunk;
+    type IntoIter = std::vec::IntoIter<Chunk>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Format {
+    type Item = &'a Chunk;
+    type IntoIter = std::slice::Iter<'a, Chunk>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<Chunk> for Format {
+    /// Collects chunks into a Format as-is, without sorting or merging.
+    fn from_iter<I: IntoIterator<Item = Chunk>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Where each chunk starts in a serialized format, from [`Format::write_with_index`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatIndex {
+    /// `(path, start_line)` -> byte offset of the chunk's `@` header. Of several
+    /// chunks with the same key, the first one's.
+    offsets: std::collections::BTreeMap<(PathBuf, usize), usize>,
+}
+
+#[allow(dead_code)]
+impl FormatIndex {
+    /// The byte offset of the chunk for `path` starting at `start_line`
+    #[must_use]
+    pub fn offset(&self, path: &Path, start_line: usize) -> Option<usize> {
+        self.offsets.get(&(path.to_path_buf(), start_line)).copied()
+    }
+
+    /// How many chunks are indexed
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Parse the single chunk starting at byte `offset` of `reader`
+    ///
+    /// Only the chunk's own lines are read: from its `@` header through its
+    /// `@@@` (or `@@@-`) end delimiter.
+    ///
+    /// # Errors
+    /// Returns an error if reading fails or the text at `offset` isn't a chunk.
+    pub fn read_chunk<R: std::io::Read + std::io::Seek>(
+        mut reader: R,
+        offset: usize,
+    ) -> Result<Chunk, FormatError> {
+        use std::io::BufRead;
+
+        reader.seek(std::io::SeekFrom::Start(offset as u64))?;
+        let mut reader = std::io::BufReader::new(reader);
+        let mut text = String::new();
+        loop {
+            let start = text.len();
+            if reader.read_line(&mut text)? == 0 {
+                break;
+            }
+            // Content escapes `@`, so only a delimiter line can look like this
+            if matches!(text[start..].trim_end_matches('\n'), "@@@" | "@@@-") {
+                break;
+            }
+        }
+
+        let format = crate::format::parse::parse_format(&text)?;
+        Ok(format.0.into_iter().next().expect("parsing found a chunk"))
+    }
+}
+
+/// Adapts a rendering function such as [`display_chunk`] to `Display`
+struct Rendered<F: Fn(&mut fmt::Formatter) -> fmt::Result>(F);
+
+impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> fmt::Display for Rendered<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.0)(f)
+    }
+}
+
+/// A chunk that no longer matches its file, see [`Format::verify_against_fs`]
+#[derive(Debug, Clone, Error)]
+#[error(
+    "{}:{}: chunk doesn't match the file{}",
+    .chunk.path.display(),
+    .chunk.start_line,
+    if .actual.is_none() { " (file can't be read)" } else { "" }
+)]
+pub struct ChunkVerificationError {
+    pub chunk: Chunk,
+    /// The chunk's content
+    pub expected: String,
+    /// The lines the chunk covers in the file, or `None` if it can't be read
+    pub actual: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ChunkRef<'a> {
     pub path: &'a Path,
@@ -264,7 +820,7 @@ pub struct ChunkRef<'a> {
 }
 
 /// Chunk represents a single code snippet with its metadata and content.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Chunk {
     pub path: PathBuf,
     pub start_line: usize,
@@ -306,23 +862,103 @@ impl Chunk {
         }
     }
 
+    /// One past the last line covered by this chunk (1-indexed, exclusive)
+    pub fn end_line(&self) -> usize {
+        self.start_line + self.num_lines
+    }
+
+    /// How many lines applying this chunk adds to its file (negative if it
+    /// removes lines)
+    pub fn line_delta(&self) -> i64 {
+        let lines = self.content.split_inclusive('\n').count();
+        i64::try_from(lines).unwrap_or(i64::MAX) - i64::try_from(self.num_lines).unwrap_or(i64::MAX)
+    }
+
+    /// Whether `line` (1-indexed) falls within this chunk
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn contains_line(&self, line: usize) -> bool {
+        (self.start_line..self.end_line()).contains(&line)
+    }
+
+    /// Whether this chunk shares at least one line with the `num_lines` lines
+    /// starting at `start` (1-indexed). An empty range overlaps nothing.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn overlaps(&self, start: usize, num_lines: usize) -> bool {
+        num_lines > 0 && start < self.end_line() && self.start_line < start + num_lines
+    }
+
+    /// The content's lines, without their `\n`, each paired with its line
+    /// number (numbered from `start_line`)
+    ///
+    /// Collected into a `Vec` for random access. Numbers follow the content, so
+    /// after an edit that adds or removes lines they no longer match the file.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn content_lines(&self) -> Vec<(usize, &str)> {
+        (self.start_line..)
+            .zip(self.content.split_inclusive('\n'))
+            .map(|(number, line)| (number, line.strip_suffix('\n').unwrap_or(line)))
+            .collect()
+    }
+
+    /// Like [`Chunk::content_lines`], but lazy, and a `\r\n` ending is
+    /// stripped whole
+    ///
+    /// Content that doesn't end in a newline still yields its last line, and
+    /// content that does yields no empty line after it.
+    #[allow(dead_code)]
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        (self.start_line..).zip(self.content.lines())
+    }
+
+    /// The content line numbered `line_number` (see [`Chunk::content_lines`]),
+    /// without its `\n`, or `None` outside the content
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn line_at(&self, line_number: usize) -> Option<&str> {
+        let line = self
+            .content
+            .split_inclusive('\n')
+            .nth(line_number.checked_sub(self.start_line)?)?;
+        Some(line.strip_suffix('\n').unwrap_or(line))
+    }
+
+    /// Whether the two chunks' contents are equal once whitespace is ignored
+    ///
+    /// Each line is trimmed and its inner runs of whitespace collapse to one
+    /// space, so re-indenting (tabs vs spaces) or re-spacing a line doesn't count
+    /// as a difference. Lines are still compared one to one: adding or removing a
+    /// line, even a blank one, does. Paths and line numbers aren't compared.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn content_eq_ignoring_whitespace(&self, other: &Chunk) -> bool {
+        eq_ignoring_whitespace(&self.content, &other.content)
+    }
+
     /// Determines if this chunk can be merged with another chunk.
     /// Two chunks can be merged if they have the same path and are either:
     /// - Sequential (no gaps between them)
     /// - Overlapping
     #[allow(dead_code)]
     pub fn can_merge(&self, other: &Chunk) -> bool {
+        self.can_merge_with_gap(other, 0)
+    }
+
+    /// Like [`Chunk::can_merge`], but also allows up to `tolerance` lines
+    /// between the two chunks.
+    #[allow(dead_code)]
+    pub fn can_merge_with_gap(&self, other: &Chunk, tolerance: usize) -> bool {
         if self.path != other.path {
             return false;
         }
 
-        let self_end = self.start_line + self.num_lines;
-        let other_end = other.start_line + other.num_lines;
-
         // Check if chunks are sequential or overlapping
         // Sequential: one chunk ends where the other begins
         // Overlapping: chunks share some lines
-        self_end >= other.start_line && other_end >= self.start_line
+        self.end_line() + tolerance >= other.start_line
+            && other.end_line() + tolerance >= self.start_line
     }
 
     /// Merges another chunk into this chunk, updating this chunk in place.
@@ -341,8 +977,8 @@ impl Chunk {
             return Err(other);
         }
 
-        let self_end = self.start_line + self.num_lines;
-        let other_end = other.start_line + other.num_lines;
+        let self_end = self.end_line();
+        let other_end = other.end_line();
 
         // Calculate the merged boundaries
         let merged_start = self.start_line.min(other.start_line);
@@ -411,6 +1047,11 @@ impl Chunk {
             }
         };
 
+        // Whichever chunk reaches further decides how the merged chunk ends
+        if other_end > self_end {
+            self.no_newline_eol = other.no_newline_eol;
+        }
+
         // Update self with merged values
         self.start_line = merged_start;
         self.num_lines = merged_num_lines;
@@ -420,99 +1061,305 @@ impl Chunk {
     }
 }
 
+/// The enclosing function header of each match (`--show-function`), shown
+/// beside a [`Format`] with [`Display::with_function_headers`]
+///
+/// Headers are display-only, so they're kept out of the chunks: each chunk
+/// shows the header of the first match it covers.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionHeaders(std::collections::BTreeMap<(PathBuf, usize), ContextLine>);
+
+impl FunctionHeaders {
+    /// Collect the headers found for `matches`, keyed by their path and line
+    #[must_use]
+    pub fn from_matches(matches: &[crate::types::MatchResult]) -> Self {
+        Self(
+            matches
+                .iter()
+                .filter_map(|m| {
+                    let header = m.function_header.clone()?;
+                    Some(((m.file_path.clone(), m.line_number), header))
+                })
+                .collect(),
+        )
+    }
+
+    /// The header of the first match within `chunk`'s lines
+    fn for_chunk(&self, chunk: &Chunk) -> Option<&ContextLine> {
+        let end = chunk.start_line + chunk.num_lines.max(1);
+        self.0
+            .range((chunk.path.clone(), chunk.start_line)..(chunk.path.clone(), end))
+            .next()
+            .map(|(_, header)| header)
+    }
+}
+
 pub struct Display<'a> {
     pub format: &'a Format,
     pub plain: bool,
+    /// Group plain output under one heading per file, see [`Format::display_headings`]
+    pub heading: bool,
     pub highlight: bool,
+    /// Prefix each content line of the chunk format with its line number and
+    /// `|` (dimmed if `highlight`), see [`Display::with_line_numbers`]
+    pub show_line_numbers: bool,
+    /// Function headers shown with the chunks, see [`Display::with_function_headers`]
+    pub function_headers: Option<&'a FunctionHeaders>,
+}
+
+impl<'a> Display<'a> {
+    /// Show each chunk's enclosing function header (`--show-function`): as a
+    /// `#` comment above it in the chunk format, which parsing skips, and as an
+    /// `=` line in the plain displays
+    #[must_use]
+    pub fn with_function_headers(mut self, headers: &'a FunctionHeaders) -> Self {
+        self.function_headers = Some(headers);
+        self
+    }
+
+    /// Number the content lines of the chunk format, like `nl`: `1 | first`
+    ///
+    /// Handy to check a chunk's `@path:line:num` header against its content,
+    /// but the numbered output can't be applied. The numbers are padded to the
+    /// widest one in the format. The plain displays are always numbered.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_line_numbers(mut self, show: bool) -> Self {
+        self.show_line_numbers = show;
+        self
+    }
 }
 
-fn display_format(f: &mut fmt::Formatter, format: &Format, highlight: bool) -> std::fmt::Result {
+fn display_format(
+    f: &mut fmt::Formatter,
+    format: &Format,
+    highlight: bool,
+    show_line_numbers: bool,
+    headers: Option<&FunctionHeaders>,
+) -> std::fmt::Result {
+    let width = show_line_numbers.then(|| {
+        let last = format
+            .0
+            .iter()
+            .map(|chunk| chunk.start_line + chunk.content.lines().count().saturating_sub(1))
+            .max()
+            .unwrap_or(1);
+        last.to_string().len()
+    });
     for (idx, chunk) in format.0.iter().enumerate() {
         if idx != 0 {
             f.write_str("\n")?;
         };
+        display_function_comment(f, chunk, headers)?;
+        match width {
+            Some(width) => display_numbered_chunk(f, chunk, highlight, width)?,
+            None => display_chunk(f, chunk, highlight)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// [`display_chunk`] with each content line prefixed by its number, padded to
+/// `width`
+fn display_numbered_chunk(
+    f: &mut fmt::Formatter,
+    chunk: &Chunk,
+    highlight: bool,
+    width: usize,
+) -> fmt::Result {
+    let (dim, reset) = if highlight {
+        ("\x1b[2m", "\x1b[0m")
+    } else {
+        ("", "")
+    };
+    let rendered =
+        Rendered(|f: &mut fmt::Formatter| display_chunk(f, chunk, highlight)).to_string();
+    let mut lines = rendered.split_inclusive('\n');
+    if let Some(header) = lines.next() {
+        f.write_str(header)?;
+    }
+    // The content lines are those before the end delimiter
+    let body: Vec<&str> = lines.collect();
+    let (content, end) = body.split_at(body.len().saturating_sub(1));
+    for (line_no, line) in (chunk.start_line..).zip(content) {
+        write!(f, "{dim}{line_no:>width$} |{reset} {line}")?;
+    }
+    for line in end {
+        f.write_str(line)?;
+    }
 
-        // Start delimiter: @path:line:numlines
+    Ok(())
+}
+
+/// A chunk's function header as a comment line, if it has one (text between
+/// chunks is ignored on parse)
+fn display_function_comment(
+    f: &mut fmt::Formatter,
+    chunk: &Chunk,
+    headers: Option<&FunctionHeaders>,
+) -> fmt::Result {
+    if let Some(header) = headers.and_then(|headers| headers.for_chunk(chunk)) {
         writeln!(
             f,
-            "@{}:{}:{}",
+            "# {}:{}: {}",
             chunk.path.display(),
-            chunk.start_line,
-            chunk.num_lines
+            header.line_number,
+            header.content.trim_end()
         )?;
+    }
+
+    Ok(())
+}
 
-        match &chunk.match_range {
-            Some(range) if highlight => {
-                let start_red = "\x1b[31m";
-                let end_red = "\x1b[0m";
-                write!(
-                    f,
-                    "{}{}{}{}{}",
-                    crate::format::escaping::escape_content(&chunk.content[..range.start]),
-                    start_red,
-                    crate::format::escaping::escape_content(&chunk.content[range.clone()]),
-                    end_red,
-                    crate::format::escaping::escape_content(&chunk.content[range.end..])
-                )?;
+/// One chunk of [`display_format`], from its `@` header to its end delimiter
+fn display_chunk(f: &mut fmt::Formatter, chunk: &Chunk, highlight: bool) -> fmt::Result {
+    // Start delimiter: @path:line:numlines
+    writeln!(
+        f,
+        "@{}:{}:{}",
+        chunk.path.display(),
+        chunk.start_line,
+        chunk.num_lines
+    )?;
+
+    match &chunk.match_range {
+        Some(range) if highlight => {
+            let start_red = "\x1b[31m";
+            let end_red = "\x1b[0m";
+            write!(
+                f,
+                "{}{}{}{}{}",
+                crate::format::escaping::escape_content(&chunk.content[..range.start]),
+                start_red,
+                crate::format::escaping::escape_content(&chunk.content[range.clone()]),
+                end_red,
+                crate::format::escaping::escape_content(&chunk.content[range.end..])
+            )?;
+        }
+        _ => {
+            // Escaped content (content already has trailing newline, don't add another)
+            write!(
+                f,
+                "{}",
+                crate::format::escaping::escape_content(&chunk.content)
+            )?;
+        }
+    }
+
+    if chunk.no_newline_eol {
+        writeln!(f, "\n@@@-")?;
+    } else {
+        // End delimiter
+        writeln!(f, "@@@")?;
+    }
+
+    Ok(())
+}
+
+fn display_plain(
+    f: &mut fmt::Formatter,
+    format: &Format,
+    highlight: bool,
+    headers: Option<&FunctionHeaders>,
+) -> std::fmt::Result {
+    for chunk in format.0.iter() {
+        writeln!(f, "\n{}:{}", chunk.path.display(), chunk.start_line)?;
+        display_plain_header(f, chunk, headers)?;
+        display_plain_lines(f, chunk, highlight)?;
+    }
+
+    Ok(())
+}
+
+fn display_headings(
+    f: &mut fmt::Formatter,
+    format: &Format,
+    highlight: bool,
+    headers: Option<&FunctionHeaders>,
+) -> fmt::Result {
+    let mut previous: Option<&Path> = None;
+    for chunk in &format.0 {
+        if previous == Some(chunk.path.as_path()) {
+            writeln!(f, "  --")?;
+        } else {
+            if previous.is_some() {
+                writeln!(f)?;
             }
-            _ => {
-                // Escaped content (content already has trailing newline, don't add another)
-                write!(
-                    f,
-                    "{}",
-                    crate::format::escaping::escape_content(&chunk.content)
-                )?;
+            if highlight {
+                writeln!(f, "\x1b[1m{}\x1b[0m", chunk.path.display())?;
+            } else {
+                writeln!(f, "{}", chunk.path.display())?;
             }
+            previous = Some(&chunk.path);
         }
 
+        display_plain_header(f, chunk, headers)?;
+        display_plain_lines(f, chunk, highlight)?;
         if chunk.no_newline_eol {
-            writeln!(f, "\n@@@-")?;
-        } else {
-            // End delimiter
-            writeln!(f, "@@@")?;
+            writeln!(f)?;
         }
     }
+    if previous.is_some() {
+        writeln!(f)?;
+    }
 
     Ok(())
 }
 
-fn display_plain(f: &mut fmt::Formatter, format: &Format, highlight: bool) -> std::fmt::Result {
-    for chunk in format.0.iter() {
-        writeln!(f, "\n{}:{}", chunk.path.display(), chunk.start_line)?;
-        let mut bytes = 0;
-        for (line_no, line) in (chunk.start_line..).zip(chunk.content.split_inclusive('\n')) {
-            let start = bytes;
-            let end = bytes + line.len();
-            match chunk.match_range.as_ref() {
-                Some(range) if start <= range.start && end > range.end => {
-                    if highlight {
-                        let start_red = "\x1b[31m";
-                        let end_red = "\x1b[0m";
-
-                        let line_start = range.start - start;
-                        let line_end = range.end - start;
-
-                        write!(
-                            f,
-                            "  {:4} > {}{}{}{}{}",
-                            line_no,
-                            line.get(..line_start).unwrap_or_default(),
-                            start_red,
-                            &line[line_start..line_end],
-                            end_red,
-                            line.get(line_end..).unwrap_or_default()
-                        )?;
-                    } else {
-                        write!(f, "  {:4} > {}", line_no, line)?;
-                    }
-                }
-                _ => {
-                    write!(f, "  {:4} | {}", line_no, line)?;
+/// A chunk's function header as a numbered `=` line, if it has one
+fn display_plain_header(
+    f: &mut fmt::Formatter,
+    chunk: &Chunk,
+    headers: Option<&FunctionHeaders>,
+) -> fmt::Result {
+    if let Some(header) = headers.and_then(|headers| headers.for_chunk(chunk)) {
+        writeln!(
+            f,
+            "  {:4} = {}",
+            header.line_number,
+            header.content.trim_end()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The numbered lines of one chunk, marking the matched line with `>`
+fn display_plain_lines(f: &mut fmt::Formatter, chunk: &Chunk, highlight: bool) -> fmt::Result {
+    let mut bytes = 0;
+    for (line_no, line) in (chunk.start_line..).zip(chunk.content.split_inclusive('\n')) {
+        let start = bytes;
+        let end = bytes + line.len();
+        match chunk.match_range.as_ref() {
+            Some(range) if start <= range.start && end > range.end => {
+                let line_start = range.start - start;
+                let line_end = range.end - start;
+                // A range that splits a character is shown without highlighting
+                if let (true, Some(before), Some(matched), Some(after)) = (
+                    highlight,
+                    line.get(..line_start),
+                    line.get(line_start..line_end),
+                    line.get(line_end..),
+                ) {
+                    let start_red = "\x1b[31m";
+                    let end_red = "\x1b[0m";
+
+                    write!(
+                        f,
+                        "  {:4} > {}{}{}{}{}",
+                        line_no, before, start_red, matched, end_red, after
+                    )?;
+                } else {
+                    write!(f, "  {:4} > {}", line_no, line)?;
                 }
             }
-
-            bytes += line.len();
+            _ => {
+                write!(f, "  {:4} | {}", line_no, line)?;
+            }
         }
+
+        bytes += line.len();
     }
 
     Ok(())
@@ -521,10 +1368,18 @@ fn display_plain(f: &mut fmt::Formatter, format: &Format, highlight: bool) -> st
 impl fmt::Display for Display<'_> {
     /// Serializes the Format to the file format string.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.plain {
-            display_plain(f, self.format, self.highlight)
+        if self.heading {
+            display_headings(f, self.format, self.highlight, self.function_headers)
+        } else if self.plain {
+            display_plain(f, self.format, self.highlight, self.function_headers)
         } else {
-            display_format(f, self.format, self.highlight)
+            display_format(
+                f,
+                self.format,
+                self.highlight,
+                self.show_line_numbers,
+                self.function_headers,
+            )
         }
     }
 }
@@ -532,7 +1387,7 @@ impl fmt::Display for Display<'_> {
 impl fmt::Display for Format {
     /// Serializes the Format to the file format string.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        display_format(f, self, false)
+        display_format(f, self, false, false, None)
     }
 }
 
@@ -549,11 +1404,36 @@ impl FromStr for Format {
     }
 }
 
+/// See [`Chunk::content_eq_ignoring_whitespace`]
+fn eq_ignoring_whitespace(a: &str, b: &str) -> bool {
+    let words =
+        |content| str::lines(content).map(|line| line.split_whitespace().collect::<Vec<_>>());
+    words(a).eq(words(b))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::MatchResult;
     use std::str::FromStr;
 
+    #[test]
+    fn test_format_from_str_lenient_without_final_delimiter() {
+        let input = "@src/main.rs:10:1\nfn main() {}\n";
+        assert!(Format::from_str(input).is_err());
+
+        let format = Format::from_str_lenient(input).unwrap();
+        assert_eq!(
+            format.0,
+            vec![Chunk::new(
+                PathBuf::from("src/main.rs"),
+                10,
+                1,
+                "fn main() {}\n".to_string()
+            )]
+        );
+    }
+
     #[test]
     fn test_chunk_new() {
         let chunk = Chunk::new(PathBuf::from("test.txt"), 42, 1, "test content".to_string());
@@ -652,6 +1532,388 @@ mod tests {
         assert_eq!(deserialized.0[0].content, original.0[0].content);
     }
 
+    #[test]
+    fn test_format_function_header_is_a_comment() {
+        let path = PathBuf::from("src/lib.rs");
+        let headers = FunctionHeaders(
+            [(
+                (path.clone(), 6),
+                ContextLine {
+                    line_number: 3,
+                    content: "fn compute() {\n".to_string(),
+                },
+            )]
+            .into(),
+        );
+        let original = Format(vec![Chunk::new(
+            path,
+            5,
+            2,
+            "    let x = 1;\n    TARGET\n".to_string(),
+        )]);
+
+        let output = original
+            .display(false, false)
+            .with_function_headers(&headers)
+            .to_string();
+        assert!(output.starts_with("# src/lib.rs:3: fn compute() {\n@src/lib.rs:5:2\n"));
+        let plain = original
+            .display(true, false)
+            .with_function_headers(&headers)
+            .to_string();
+        assert!(plain.starts_with("\nsrc/lib.rs:5\n     3 = fn compute() {\n"));
+
+        // The header is display-only: parsing skips it as a comment
+        let parsed = Format::from_str(&output).unwrap();
+        assert_eq!(parsed.0, original.0);
+    }
+
+    #[test]
+    fn test_format_iter_collect_roundtrip() {
+        let original = Format(vec![
+            Chunk::new(PathBuf::from("a.txt"), 1, 1, "a\n".to_string()),
+            Chunk::new(PathBuf::from("b.txt"), 2, 1, "b\n".to_string()),
+        ]);
+        let expected = original.to_string();
+
+        let borrowed: Vec<&Chunk> = original.iter().collect();
+        assert_eq!(borrowed.len(), 2);
+        assert_eq!((&original).into_iter().count(), 2);
+
+        let collected: Format = original.into_iter().collect();
+        assert_eq!(collected.to_string(), expected);
+    }
+
+    #[test]
+    fn test_format_filter_collect_subset() {
+        let format = Format(vec![
+            Chunk::new(PathBuf::from("a.txt"), 1, 1, "a\n".to_string()),
+            Chunk::new(PathBuf::from("b.txt"), 2, 1, "b\n".to_string()),
+            Chunk::new(PathBuf::from("a.txt"), 5, 1, "c\n".to_string()),
+        ]);
+
+        let subset: Format = format
+            .into_iter()
+            .filter(|c| c.path == Path::new("a.txt"))
+            .collect();
+        assert_eq!(subset.len(), 2);
+        assert!(subset.iter().all(|c| c.path == Path::new("a.txt")));
+    }
+
+    #[test]
+    fn test_format_collect_empty() {
+        let format: Format = std::iter::empty().collect();
+        assert!(format.is_empty());
+        assert_eq!(format.len(), 0);
+    }
+
+    #[test]
+    fn test_format_apply_content_mutation_recounts_lines() {
+        let mut format = Format(vec![
+            Chunk::new(PathBuf::from("a.txt"), 1, 2, "a\nb\n".to_string()),
+            Chunk::new(PathBuf::from("b.txt"), 4, 3, "c\nd\ne\n".to_string()),
+        ]);
+
+        format.apply_content_mutation(|content| {
+            content
+                .split_inclusive('\n')
+                .flat_map(|line| [line, line])
+                .collect()
+        });
+
+        let num_lines: Vec<usize> = format.iter().map(|c| c.num_lines).collect();
+        assert_eq!(num_lines, vec![4, 6]);
+        assert_eq!(format.0[0].content, "a\na\nb\nb\n");
+        assert_eq!(format.0[0].start_line, 1);
+
+        // Dropping every other line halves the total
+        format.apply_content_mutation(|content| content.split_inclusive('\n').step_by(2).collect());
+
+        let total_lines: usize = format.iter().map(|c| c.num_lines).sum();
+        assert_eq!(total_lines, 5);
+        assert_eq!(format.0[1].content, "c\nd\ne\n");
+    }
+
+    /// A match on `line_number` of `lines`, with `context` lines either side
+    fn match_in(lines: &[&str], line_number: usize, context: usize) -> MatchResult {
+        let ctx = |range: std::ops::Range<usize>| {
+            range
+                .filter(|n| (1..=lines.len()).contains(n))
+                .map(|n| ContextLine {
+                    line_number: n,
+                    content: lines[n - 1].to_string(),
+                })
+                .collect()
+        };
+        let line_content = lines[line_number - 1].to_string();
+        MatchResult {
+            file_path: PathBuf::from("a.rs"),
+            line_number,
+            line_match: Some(0..line_content.len() - 1),
+            line_content,
+            byte_offset: 0,
+            context_before: ctx(line_number.saturating_sub(context)..line_number),
+            context_after: ctx(line_number + 1..line_number + context + 1),
+            function_header: None,
+            match_lines: 1,
+            file_metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_insert_after_matches_applies_below_each_match() {
+        use crate::filesystem::FileSystem;
+        use crate::filesystem::memory::MemoryFS;
+
+        let fs = MemoryFS::new();
+        let lines = ["fn a() {\n", "}\n", "fn b() {\n", "}\n"];
+        fs.add_file(Path::new("a.rs"), &lines.concat()).unwrap();
+
+        let matches = [match_in(&lines, 2, 0), match_in(&lines, 4, 1)];
+        let mut format = Format::insert_after_matches(&matches, "// end");
+
+        assert_eq!(format.0[0].start_line, 3);
+        assert_eq!(format.0[0].num_lines, 0);
+        crate::apply::apply_format_to_fs(&mut format, &fs).unwrap();
+        assert_eq!(
+            fs.read_to_string(Path::new("a.rs")).unwrap(),
+            "fn a() {\n}\n// end\nfn b() {\n}\n// end\n"
+        );
+    }
+
+    #[test]
+    fn test_display_with_line_numbers() {
+        let format = Format(vec![Chunk::new(
+            PathBuf::from("a.txt"),
+            1,
+            2,
+            "first\nsecond\n".into(),
+        )]);
+
+        assert_eq!(
+            format
+                .display(false, false)
+                .with_line_numbers(true)
+                .to_string(),
+            "@a.txt:1:2\n1 | first\n2 | second\n@@@\n"
+        );
+        assert_eq!(
+            format
+                .display(false, true)
+                .with_line_numbers(true)
+                .to_string(),
+            "@a.txt:1:2\n\x1b[2m1 |\x1b[0m first\n\x1b[2m2 |\x1b[0m second\n@@@\n"
+        );
+    }
+
+    #[test]
+    fn test_display_line_numbers_widen_past_999_lines() {
+        let mut last = Chunk::new(PathBuf::from("a.txt"), 999, 2, "999\n1000".into());
+        last.no_newline_eol = true;
+        let format = Format(vec![
+            Chunk::new(PathBuf::from("a.txt"), 1, 1, "one\n".into()),
+            last,
+        ]);
+
+        assert_eq!(
+            format
+                .display(false, false)
+                .with_line_numbers(true)
+                .to_string(),
+            "\
+@a.txt:1:1
+   1 | one
+@@@
+
+@a.txt:999:2
+ 999 | 999
+1000 | 1000
+@@@-
+"
+        );
+    }
+
+    #[test]
+    fn test_display_annotated_numbers_lines_from_the_file() {
+        use crate::filesystem::memory::MemoryFS;
+
+        let fs = MemoryFS::new();
+        let content: String = (1..=9).map(|n| format!("line {n}\n")).collect();
+        fs.add_file(Path::new("a.txt"), &content).unwrap();
+
+        // Lines 4-6 become four lines; the one added has no number in the file
+        let format = Format(vec![
+            Chunk::new(
+                PathBuf::from("a.txt"),
+                4,
+                3,
+                "four\nfive\nfive b\nsix\n".into(),
+            ),
+            Chunk::new(PathBuf::from("a.txt"), 1, 1, "one\n".into()),
+        ]);
+
+        assert_eq!(
+            format.display_annotated(&fs).unwrap(),
+            "\
+a.txt:4-6
+     2 | line 2
+     3 | line 3
+     4 > four
+     5 > five
+     6 > five b
+       + six
+     7 | line 7
+     8 | line 8
+
+a.txt:1-1
+     1 > one
+     2 | line 2
+     3 | line 3
+"
+        );
+    }
+
+    #[test]
+    fn test_format_from_matches_overlapping_context() {
+        let lines = ["1\n", "2\n", "3\n", "4\n", "5\n", "6\n"];
+        let matches = vec![match_in(&lines, 2, 1), match_in(&lines, 4, 1)];
+
+        // One chunk per match, so the shared line 3 shows up twice
+        let format = Format::from_matches(&matches);
+        let spans: Vec<_> = format.iter().map(|c| (c.start_line, c.num_lines)).collect();
+        assert_eq!(spans, vec![(1, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn test_format_from_matches_grouped_overlapping_context() {
+        let lines = ["1\n", "2\n", "3\n", "4\n", "5\n", "6\n", "7\n", "8\n"];
+        let mut edited = match_in(&lines, 3, 1);
+        edited.line_content = "three\n".to_string();
+        let matches = vec![match_in(&lines, 2, 1), edited, match_in(&lines, 7, 0)];
+
+        let format = Format::from_matches_grouped(&matches);
+        let chunks: Vec<_> = format
+            .iter()
+            .map(|c| {
+                (
+                    c.start_line,
+                    c.num_lines,
+                    c.content.as_str(),
+                    c.match_range.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            chunks,
+            vec![
+                // Line 3 is match 2's line and in match 1's context: the match wins
+                (1, 4, "1\n2\nthree\n4\n", Some(2..3)),
+                (7, 1, "7\n", Some(0..1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunk_content_eq_ignoring_whitespace() {
+        let chunk = |content: &str| Chunk::new(PathBuf::from("a.rs"), 1, 2, content.to_string());
+        let spaces = chunk("fn main() {\n    let x  = 1;\n");
+
+        assert!(spaces.content_eq_ignoring_whitespace(&chunk("fn main() {\n\tlet x = 1;  \n")));
+        assert!(!spaces.content_eq_ignoring_whitespace(&chunk("fn main() {\n    let x = 2;\n")));
+        assert!(!spaces.content_eq_ignoring_whitespace(&chunk("fn main() {\n\n    let x = 1;\n")));
+        // Whitespace inside a word still matters
+        assert!(!spaces.content_eq_ignoring_whitespace(&chunk("fn ma in() {\n    let x = 1;\n")));
+    }
+
+    #[test]
+    fn test_format_has_effective_changes() {
+        use crate::filesystem::memory::MemoryFS;
+
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("a.rs");
+        fs.add_file(&path, "fn main() {\n    run();\n}\n").unwrap();
+
+        let reindented = Format(vec![Chunk::new(
+            path.clone(),
+            2,
+            1,
+            "\trun();\n".to_string(),
+        )]);
+        assert!(!reindented.has_effective_changes(&fs));
+
+        let edited = Format(vec![
+            Chunk::new(path.clone(), 1, 1, "fn main()  {\n".to_string()),
+            Chunk::new(path.clone(), 2, 1, "    stop();\n".to_string()),
+        ]);
+        assert!(edited.has_effective_changes(&fs));
+
+        let missing = Format(vec![Chunk::new(
+            PathBuf::from("b.rs"),
+            1,
+            1,
+            "x\n".to_string(),
+        )]);
+        assert!(missing.has_effective_changes(&fs));
+    }
+
+    #[test]
+    fn test_format_verify_against_fs() {
+        use crate::filesystem::memory::MemoryFS;
+
+        let fs = MemoryFS::new();
+        let path = PathBuf::from("a.rs");
+        fs.add_file(&path, "fn main() {\n    run();\n}\n").unwrap();
+
+        let fresh = Format(vec![Chunk::new(
+            path.clone(),
+            1,
+            2,
+            "fn main() {\n    run();\n".to_string(),
+        )]);
+        assert!(fresh.verify_against_fs(&fs).is_empty());
+
+        let stale = Format(vec![
+            Chunk::new(path.clone(), 1, 1, "fn main() {\n".to_string()),
+            Chunk::new(path.clone(), 2, 1, "    stop();\n".to_string()),
+            Chunk::new(PathBuf::from("b.rs"), 1, 1, "x\n".to_string()),
+        ]);
+        let errors = stale.verify_against_fs(&fs);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].chunk.start_line, 2);
+        assert_eq!(errors[0].expected, "    stop();\n");
+        assert_eq!(errors[0].actual.as_deref(), Some("    run();\n"));
+        assert_eq!(errors[1].chunk.path, PathBuf::from("b.rs"));
+        assert_eq!(errors[1].actual, None);
+    }
+
+    #[test]
+    fn test_format_write_with_index_offsets_reparse() {
+        use std::io::Cursor;
+
+        let format = Format(vec![
+            Chunk::new(PathBuf::from("a.rs"), 1, 2, "fn a() {\n}\n".to_string()),
+            Chunk::new(PathBuf::from("a.rs"), 9, 1, "user@host\n".to_string()),
+            Chunk::new(PathBuf::from("b.rs"), 4, 1, "end".to_string()).with_no_newline_eol(true),
+        ]);
+
+        let mut out = Vec::new();
+        let index = format.write_with_index(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), format.to_string());
+        assert_eq!(index.len(), 3);
+
+        for chunk in &format {
+            let offset = index.offset(&chunk.path, chunk.start_line).unwrap();
+            assert_eq!(out[offset], b'@');
+            let read = FormatIndex::read_chunk(Cursor::new(&out), offset).unwrap();
+            assert_eq!(read.path, chunk.path);
+            assert_eq!(read.start_line, chunk.start_line);
+            assert_eq!(read.num_lines, chunk.num_lines);
+            assert_eq!(read.content, chunk.content);
+            assert_eq!(read.no_newline_eol, chunk.no_newline_eol);
+        }
+        assert_eq!(index.offset(Path::new("c.rs"), 1), None);
+    }
+
     #[test]
     fn test_chunk_can_merge_same_path_adjacent() {
         let chunk1 = Chunk::new(
@@ -695,6 +1957,102 @@ mod tests {
         assert!(!chunk1.can_merge(&chunk2));
     }
 
+    #[test]
+    fn test_chunk_contains_line_boundaries() {
+        // Covers lines 5, 6 and 7
+        let chunk = Chunk::new(PathBuf::from("test.txt"), 5, 3, "a\nb\nc\n".to_string());
+
+        assert!(!chunk.contains_line(4));
+        assert!(chunk.contains_line(5));
+        assert!(chunk.contains_line(7));
+        assert!(!chunk.contains_line(8));
+    }
+
+    #[test]
+    fn test_rebase_after_apply_shifts_later_chunks_in_the_file() {
+        let path = PathBuf::from("a.txt");
+        let applied = Chunk::new(path.clone(), 2, 1, "b\nb1\nb2\nb3\n".to_string());
+        let mut format = Format(vec![
+            Chunk::new(path.clone(), 1, 1, "a\n".to_string()),
+            applied.clone(),
+            Chunk::new(path.clone(), 3, 2, "c\nd\n".to_string()),
+            Chunk::new(path.clone(), 9, 1, "i\n".to_string()),
+            Chunk::new(PathBuf::from("b.txt"), 5, 1, "e\n".to_string()),
+        ]);
+
+        assert_eq!(applied.line_delta(), 3);
+        format.rebase_after_apply(&path, &applied, applied.line_delta());
+
+        let starts: Vec<_> = format.iter().map(|c| c.start_line).collect();
+        assert_eq!(starts, vec![1, 2, 6, 12, 5]);
+
+        // Removing lines moves them back, but never before line 1
+        format.rebase_after_apply(&path, &applied, -20);
+        let starts: Vec<_> = format.iter().map(|c| c.start_line).collect();
+        assert_eq!(starts, vec![1, 2, 1, 1, 5]);
+    }
+
+    #[test]
+    fn test_display_plain_highlight_of_split_character() {
+        // A range ending inside `é`, as a pattern without Unicode could report
+        let chunk = Chunk::new(PathBuf::from("a.txt"), 1, 1, "café latte\n".to_string())
+            .with_match_range(Some(0..4));
+        let format = Format(vec![chunk]);
+
+        let shown = format.display(true, true).to_string();
+        assert!(shown.contains("café latte"));
+    }
+
+    #[test]
+    fn test_chunk_numbered_lines() {
+        let chunk = Chunk::new(
+            PathBuf::from("test.txt"),
+            10,
+            3,
+            "first\r\nsecond\nthird".to_string(),
+        )
+        .with_no_newline_eol(true);
+        assert_eq!(
+            chunk.numbered_lines().collect::<Vec<_>>(),
+            vec![(10, "first"), (11, "second"), (12, "third")]
+        );
+
+        let chunk = Chunk::new(PathBuf::from("test.txt"), 10, 3, "a\nb\nc\n".to_string());
+        assert_eq!(
+            chunk.numbered_lines().collect::<Vec<_>>(),
+            vec![(10, "a"), (11, "b"), (12, "c")]
+        );
+    }
+
+    #[test]
+    fn test_chunk_content_lines_and_line_at() {
+        let chunk = Chunk::new(PathBuf::from("test.txt"), 5, 3, "a\nb\nc".to_string());
+
+        assert_eq!(chunk.content_lines(), vec![(5, "a"), (6, "b"), (7, "c")]);
+        assert_eq!(chunk.line_at(5), Some("a"));
+        assert_eq!(chunk.line_at(7), Some("c"));
+        assert_eq!(chunk.line_at(4), None);
+        assert_eq!(chunk.line_at(8), None);
+    }
+
+    #[test]
+    fn test_chunk_overlaps_boundaries() {
+        // Covers lines 5, 6 and 7
+        let chunk = Chunk::new(PathBuf::from("test.txt"), 5, 3, "a\nb\nc\n".to_string());
+
+        // Ranges ending just before the start / starting just after the end
+        assert!(!chunk.overlaps(3, 2));
+        assert!(!chunk.overlaps(8, 2));
+        // Ranges touching the first / last line
+        assert!(chunk.overlaps(3, 3));
+        assert!(chunk.overlaps(7, 5));
+        // Ranges inside and around the chunk
+        assert!(chunk.overlaps(6, 1));
+        assert!(chunk.overlaps(1, 20));
+        // Empty ranges overlap nothing
+        assert!(!chunk.overlaps(6, 0));
+    }
+
     #[test]
     fn test_chunk_merge_adjacent() {
         let mut chunk1 = Chunk::new(
@@ -884,6 +2242,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::cmp_owned)]
     fn test_format_merge_multiple_files() {
         let mut format = Format(vec![
             Chunk::new(
@@ -948,6 +2307,89 @@ mod tests {
         assert_eq!(format.0[0].num_lines, 8);
     }
 
+    #[test]
+    fn test_format_normalize_dedupes_and_merges() {
+        let chunk = |start, content: &str| {
+            Chunk::new(PathBuf::from("test.txt"), start, 2, content.to_string())
+        };
+        let mut format = Format(vec![
+            chunk(10, "line10\nline11\n"),
+            chunk(3, "line3\nline4\n"),
+            chunk(1, "line1\nline2\n"),
+            chunk(1, "line1\nline2\n"),
+        ]);
+
+        // The duplicate is dropped and the adjacent chunk merged into line 1's
+        assert_eq!(format.normalize(), 2);
+
+        assert_eq!(format.len(), 2);
+        assert_eq!(format.0[0].start_line, 1);
+        assert_eq!(format.0[0].num_lines, 4);
+        assert_eq!(format.0[0].content, "line1\nline2\nline3\nline4\n");
+        assert_eq!(format.0[1], chunk(10, "line10\nline11\n"));
+    }
+
+    #[test]
+    fn test_chunk_merge_keeps_no_newline_eol_of_later_chunk() {
+        let mut chunk1 = Chunk::new(PathBuf::from("test.txt"), 1, 2, "a\nb\n".to_string());
+        let chunk2 = Chunk::new(PathBuf::from("test.txt"), 2, 2, "b\nc".to_string())
+            .with_no_newline_eol(true);
+
+        chunk1.merge(chunk2).unwrap();
+        assert_eq!(chunk1.content, "a\nb\nc");
+        assert!(chunk1.no_newline_eol);
+    }
+
+    #[test]
+    fn test_chunk_can_merge_with_gap() {
+        let chunk1 = Chunk::new(PathBuf::from("test.txt"), 1, 2, "a\nb\n".to_string());
+        let two_line_gap = Chunk::new(PathBuf::from("test.txt"), 5, 1, "e\n".to_string());
+        let three_line_gap = Chunk::new(PathBuf::from("test.txt"), 6, 1, "f\n".to_string());
+
+        assert!(!chunk1.can_merge(&two_line_gap));
+        assert!(chunk1.can_merge_with_gap(&two_line_gap, 2));
+        assert!(two_line_gap.can_merge_with_gap(&chunk1, 2));
+        assert!(!chunk1.can_merge_with_gap(&three_line_gap, 2));
+    }
+
+    #[test]
+    fn test_format_merge_with_tolerance_fills_gap_from_fs() {
+        use crate::filesystem::memory::MemoryFS;
+
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("test.txt"), "a\nb\nc\nd\ne\nf\n")
+            .unwrap();
+
+        let mut format = Format(vec![
+            Chunk::new(PathBuf::from("test.txt"), 5, 1, "E\n".to_string()),
+            Chunk::new(PathBuf::from("test.txt"), 1, 2, "A\nB\n".to_string()),
+        ]);
+        format.merge_with_tolerance(2, &fs).unwrap();
+
+        assert_eq!(format.len(), 1);
+        assert_eq!(format.0[0].start_line, 1);
+        assert_eq!(format.0[0].num_lines, 5);
+        // Gap lines come from the file; chunk lines keep their (edited) content
+        assert_eq!(format.0[0].content, "A\nB\nc\nd\nE\n");
+    }
+
+    #[test]
+    fn test_format_merge_with_tolerance_respects_limit() {
+        use crate::filesystem::memory::MemoryFS;
+
+        let fs = MemoryFS::new();
+        fs.add_file(Path::new("test.txt"), "a\nb\nc\nd\ne\nf\n")
+            .unwrap();
+
+        let mut format = Format(vec![
+            Chunk::new(PathBuf::from("test.txt"), 1, 2, "a\nb\n".to_string()),
+            Chunk::new(PathBuf::from("test.txt"), 6, 1, "f\n".to_string()),
+        ]);
+        format.merge_with_tolerance(2, &fs).unwrap();
+
+        assert_eq!(format.len(), 2);
+    }
+
     #[test]
     fn test_file_chunks_multiple_files() {
         // This test verifies the fix for the bug where file_chunks would panic
@@ -1022,3 +2464,58 @@ mod tests {
         }
     }
 }
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A line of printable text, biased towards the characters the format escapes
+    fn line() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop_oneof![
+                3 => "\\PC",
+                1 => Just("@".to_string()),
+                1 => Just("\\".to_string()),
+                1 => Just("@@@".to_string()),
+            ],
+            0..12,
+        )
+        .prop_map(|parts| parts.concat())
+    }
+
+    impl Arbitrary for Chunk {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with((): ()) -> Self::Strategy {
+            (
+                "[a-zA-Z0-9_.-]{1,12}(/[a-zA-Z0-9_.-]{1,12}){0,3}",
+                1..=10_000usize,
+                1..=500usize,
+                prop::collection::vec(line(), 1..8),
+                any::<bool>(),
+            )
+                .prop_map(|(path, start_line, num_lines, lines, no_newline_eol)| {
+                    let mut content: String = lines.iter().map(|l| format!("{l}\n")).collect();
+                    if no_newline_eol {
+                        content.pop();
+                    }
+                    Chunk::new(PathBuf::from(path), start_line, num_lines, content)
+                        .with_no_newline_eol(no_newline_eol)
+                })
+                .boxed()
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1000))]
+
+        #[test]
+        fn format_round_trips(chunks in prop::collection::vec(any::<Chunk>(), 1..6)) {
+            let format = Format(chunks);
+            let parsed = Format::from_str(&format.to_string()).unwrap();
+            prop_assert_eq!(parsed.0, format.0);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io::Read,
     path::{Path, PathBuf},
 };
 
+use crate::matcher::Matcher;
 use crate::types::{ContextLine, IngestInput, MatchResult};
 
 #[derive(thiserror::Error, Debug)]
@@ -13,16 +15,40 @@ pub enum IngestError {
         source: crate::filesystem::FilesystemError,
     },
 
-    #[error("Failed to read line {line_num} in {path}: {source}")]
+    #[error("Failed to read line {line_num} in {path}: {source}{}", display_context(.context))]
     LineReadError {
         path: PathBuf,
         line_num: usize,
+        /// The last few lines read before the failure, if any
+        context: Option<String>,
         #[source]
         source: std::io::Error,
     },
 
-    #[error("unexpected EOF in {path} at line {line_num}")]
-    UnexpectedEOF { line_num: usize, path: PathBuf },
+    #[error("byte offset {offset} is past the end of {} ({len} bytes)", path.display())]
+    ByteOffsetOutOfRange {
+        path: PathBuf,
+        offset: u64,
+        len: u64,
+    },
+
+    #[error("unexpected EOF in {path} at line {line_num}{}", display_context(.context))]
+    UnexpectedEOF {
+        line_num: usize,
+        path: PathBuf,
+        /// The last few lines read before the end of the file, if any
+        context: Option<String>,
+    },
+}
+
+/// How many of the most recently read lines an [`IngestError`] carries
+const CONTEXT_LINES: usize = 3;
+
+fn display_context(context: &Option<String>) -> String {
+    match context {
+        Some(context) => format!("\nlast lines read:\n{}", context.trim_end_matches('\n')),
+        None => String::new(),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -61,6 +87,10 @@ pub fn ingest(
     inputs: Vec<IngestInput>,
     context: usize,
 ) -> Result<Vec<MatchResult>, IngestError> {
+    let inputs = inputs
+        .into_iter()
+        .map(|input| resolve_byte_offset(fs, input))
+        .collect::<Result<Vec<_>, _>>()?;
     let paths_with_ranges = group_inputs_by_path_and_create_ranges(inputs, context);
     let mut matches = Vec::new();
 
@@ -73,6 +103,128 @@ pub fn ingest(
     Ok(matches)
 }
 
+/// Search for `matcher` only inside the given line ranges, the reverse of
+/// [`ingest`]: re-check a pattern within previously flagged regions
+///
+/// Each range is 1-indexed and end-exclusive (`3..5` is lines 3 and 4), and is
+/// cut short at the end of its file. Overlapping ranges report a line once.
+/// Matches are per line, without context, ordered by path and then line.
+///
+/// # Errors
+/// Returns an error if a file can't be read.
+#[allow(dead_code)]
+pub fn search_within(
+    fs: &dyn crate::filesystem::FileSystem,
+    matcher: &impl Matcher,
+    inputs: Vec<(PathBuf, std::ops::Range<usize>)>,
+) -> Result<Vec<MatchResult>, IngestError> {
+    let mut ranges_by_path: BTreeMap<PathBuf, Vec<std::ops::Range<usize>>> = BTreeMap::new();
+    for (path, range) in inputs {
+        let range = range.start.max(1)..range.end;
+        if !range.is_empty() {
+            ranges_by_path.entry(path).or_default().push(range);
+        }
+    }
+
+    let mut matches = Vec::new();
+    for (path, ranges) in ranges_by_path {
+        let intervals = merge_ranges(ranges);
+        let Some(last) = intervals.last().map(|range| range.end - 1) else {
+            continue;
+        };
+        let mut intervals = intervals.iter().peekable();
+        let mut reader = std::io::BufReader::new(fs.read(&path)?);
+        let mut buf = String::new();
+        let mut positions = Positions {
+            path: &path,
+            line: 1,
+            byte: 0,
+            recent: VecDeque::with_capacity(CONTEXT_LINES),
+        };
+        while positions.line <= last {
+            let (line_number, byte_offset) = (positions.line, positions.byte);
+            match read_line(&mut reader, &mut buf, &mut positions) {
+                Err(IngestError::UnexpectedEOF { .. }) => break,
+                result => result?,
+            }
+            // Intervals are sorted and disjoint, so drop the ones already passed
+            while intervals
+                .next_if(|range| range.end <= line_number)
+                .is_some()
+            {}
+            let wanted = intervals
+                .peek()
+                .is_some_and(|range| range.contains(&line_number));
+            if wanted && matcher.is_match(buf.trim_end_matches(['\r', '\n'])) {
+                matches.push(MatchResult {
+                    file_path: path.clone(),
+                    line_number,
+                    line_content: buf.clone(),
+                    line_match: None,
+                    byte_offset,
+                    context_before: Vec::new(),
+                    context_after: Vec::new(),
+                    function_header: None,
+                    match_lines: 1,
+                    file_metadata: None,
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+/// Sort `ranges` and merge the overlapping or touching ones into disjoint intervals
+fn merge_ranges(mut ranges: Vec<std::ops::Range<usize>>) -> Vec<std::ops::Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<std::ops::Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Fill in the line number of an input located only by its byte offset
+fn resolve_byte_offset(
+    fs: &dyn crate::filesystem::FileSystem,
+    mut input: IngestInput,
+) -> Result<IngestInput, IngestError> {
+    if let (0, Some(offset)) = (input.line_number, input.byte_offset) {
+        input.line_number = from_byte_offset(fs, &input.file_path, offset)?;
+    }
+    Ok(input)
+}
+
+/// The 1-indexed line containing byte `offset` of the file at `path`
+fn from_byte_offset(
+    fs: &dyn crate::filesystem::FileSystem,
+    path: &Path,
+    offset: u64,
+) -> Result<usize, IngestError> {
+    let mut bytes = Vec::new();
+    fs.read(path)?
+        .take(offset)
+        .read_to_end(&mut bytes)
+        .map_err(|source| crate::filesystem::FilesystemError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    let len = bytes.len() as u64;
+    if len < offset {
+        return Err(IngestError::ByteOffsetOutOfRange {
+            path: path.to_path_buf(),
+            offset,
+            len,
+        });
+    }
+    Ok(bytes.iter().filter(|&&b| b == b'\n').count() + 1)
+}
+
 fn group_inputs_by_path_and_create_ranges(
     inputs: Vec<IngestInput>,
     context: usize,
@@ -108,6 +260,7 @@ fn process_file(
         path,
         line: 1,
         byte: 0,
+        recent: VecDeque::with_capacity(CONTEXT_LINES),
     };
 
     let iter = ranges.into_iter().filter_map(move |range| {
@@ -184,6 +337,9 @@ fn process_range(
         byte_offset: line_offset,
         context_before,
         context_after,
+        function_header: None,
+        match_lines: 1,
+        file_metadata: None,
     }))
 }
 
@@ -191,6 +347,26 @@ struct Positions<'a> {
     pub path: &'a Path,
     pub line: usize,
     pub byte: usize,
+    /// Ring buffer of the last [`CONTEXT_LINES`] lines read, for error reports
+    pub recent: VecDeque<String>,
+}
+
+impl Positions<'_> {
+    fn remember(&mut self, line: &str) {
+        // Reuse the evicted line's allocation once the buffer is full
+        let mut slot = if self.recent.len() == CONTEXT_LINES {
+            self.recent.pop_front().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        slot.clear();
+        slot.push_str(line);
+        self.recent.push_back(slot);
+    }
+
+    fn context(&self) -> Option<String> {
+        (!self.recent.is_empty()).then(|| self.recent.iter().map(String::as_str).collect())
+    }
 }
 
 fn read_line(
@@ -203,13 +379,16 @@ fn read_line(
         Ok(0) => Err(IngestError::UnexpectedEOF {
             line_num: positions.line,
             path: positions.path.to_path_buf(),
+            context: positions.context(),
         })?,
         Err(e) => Err(IngestError::LineReadError {
             line_num: positions.line,
             path: positions.path.to_path_buf(),
+            context: positions.context(),
             source: e,
         })?,
         Ok(v) => {
+            positions.remember(buf);
             positions.line += 1;
             positions.byte += v;
             Ok(())
@@ -319,6 +498,63 @@ mod tests {
         assert_eq!(ranges[1].end, 11);
     }
 
+    #[test]
+    fn test_search_within_only_searches_the_ranges() {
+        use crate::matcher::regex::GrepMatcher;
+
+        let fs = crate::filesystem::memory::MemoryFS::new();
+        let a = PathBuf::from("a.rs");
+        let b = PathBuf::from("b.rs");
+        fs.write_string(&a, "TODO 1\nok\nTODO 3\nok\nTODO 5\nok\nTODO 7\n")
+            .unwrap();
+        fs.write_string(&b, "TODO 1\nTODO 2").unwrap();
+
+        let matcher = GrepMatcher::compile("TODO").unwrap();
+        let inputs = vec![
+            (b.clone(), 2..10),
+            (a.clone(), 2..4),
+            (a.clone(), 3..5),
+            (a.clone(), 6..8),
+        ];
+        let found: Vec<_> = search_within(&fs, &matcher, inputs)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.file_path, m.line_number, m.line_content))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (a.clone(), 3, "TODO 3\n".to_string()),
+                (a, 7, "TODO 7\n".to_string()),
+                (b, 2, "TODO 2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_within_merges_ranges_instead_of_listing_lines() {
+        use crate::matcher::regex::GrepMatcher;
+
+        assert_eq!(merge_ranges(vec![6..8, 2..4, 3..5, 5..6]), vec![2..8]);
+
+        // A range reaching far past the end of the file costs nothing extra
+        let fs = crate::filesystem::memory::MemoryFS::new();
+        let a = PathBuf::from("a.rs");
+        fs.write_string(
+            &a,
+            "TODO 1
+ok
+TODO 3
+",
+        )
+        .unwrap();
+        let matcher = GrepMatcher::compile("TODO").unwrap();
+        let found = search_within(&fs, &matcher, vec![(a, 2..usize::MAX)]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].line_number, 3);
+    }
+
     #[test]
     fn test_process_range_position_less_than_start() {
         // Test when position < range.start
@@ -341,6 +577,7 @@ mod tests {
             path: &path,
             line: 1,
             byte: 0,
+            recent: VecDeque::new(),
         };
 
         let result = process_range(&mut reader, &mut buf, &mut positions, range, &path)
@@ -376,6 +613,7 @@ mod tests {
             path: &path,
             line: 5, // Already past the range
             byte: 24,
+            recent: VecDeque::new(),
         };
 
         let result = process_range(&mut reader, &mut buf, &mut positions, range, &path).unwrap();
@@ -404,6 +642,7 @@ mod tests {
             path: &path,
             line: 1,
             byte: 0,
+            recent: VecDeque::new(),
         };
 
         // Skip to line 3 to position the reader correctly
@@ -445,6 +684,7 @@ mod tests {
             path: &path,
             line: 4, // At the end
             byte: 18,
+            recent: VecDeque::new(),
         };
 
         let result = process_range(&mut reader, &mut buf, &mut positions, range, &path).unwrap();
@@ -472,6 +712,7 @@ mod tests {
             path: &path,
             line: 1,
             byte: 0,
+            recent: VecDeque::new(),
         };
 
         let result = process_range(&mut reader, &mut buf, &mut positions, range, &path);
@@ -506,6 +747,7 @@ mod tests {
             path: &path,
             line: 1,
             byte: 0,
+            recent: VecDeque::new(),
         };
 
         let result = process_range(&mut reader, &mut buf, &mut positions, range, &path)
@@ -521,4 +763,67 @@ mod tests {
         assert_eq!(result.context_after[0].line_number, 4);
         assert_eq!(result.context_after[0].content, "line4\n");
     }
+
+    #[test]
+    fn test_from_byte_offset_counts_preceding_newlines() {
+        let fs = crate::filesystem::memory::MemoryFS::new();
+        let path = PathBuf::from("offsets.txt");
+        fs.write_string(&path, "ab\ncd\nef").unwrap();
+
+        let line_at = |offset| from_byte_offset(&fs, &path, offset).unwrap();
+        assert_eq!(line_at(0), 1);
+        // The newline itself still belongs to the line it ends
+        assert_eq!(line_at(2), 1);
+        assert_eq!(line_at(3), 2);
+        assert_eq!(line_at(8), 3);
+
+        let err = from_byte_offset(&fs, &path, 9).unwrap_err();
+        assert!(matches!(
+            err,
+            IngestError::ByteOffsetOutOfRange {
+                offset: 9,
+                len: 8,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unexpected_eof_includes_last_lines_read() {
+        let fs = crate::filesystem::memory::MemoryFS::new();
+        let path = PathBuf::from("short.txt");
+        fs.write_string(&path, "alpha\nbeta\ngamma\n").unwrap();
+
+        // Line 5 doesn't exist, so reading up to it runs off the end
+        let range = Range {
+            start: 2,
+            line: 5,
+            end: 6,
+        };
+
+        let reader = fs.read(&path).unwrap();
+        let mut reader = std::io::BufReader::new(reader);
+        let mut buf = String::new();
+        let mut positions = Positions {
+            path: &path,
+            line: 1,
+            byte: 0,
+            recent: VecDeque::new(),
+        };
+
+        let err = process_range(&mut reader, &mut buf, &mut positions, range, &path).unwrap_err();
+        match &err {
+            IngestError::UnexpectedEOF {
+                line_num, context, ..
+            } => {
+                assert_eq!(*line_num, 4);
+                assert_eq!(context.as_deref(), Some("alpha\nbeta\ngamma\n"));
+            }
+            _ => panic!("Expected UnexpectedEOF error"),
+        }
+        assert!(
+            err.to_string()
+                .ends_with("last lines read:\nalpha\nbeta\ngamma")
+        );
+    }
 }
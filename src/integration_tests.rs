@@ -233,3 +233,46 @@ fn test_search_format_apply_roundtrip_preserves_content() {
         original_content, final_content
     );
 }
+
+/// Test that merged search output applies back as a no-op
+///
+/// Matches close enough for their context to overlap, and matches in several
+/// files, must come out as one sorted, non-overlapping format that `apply`
+/// accepts and that leaves every file unchanged.
+#[test]
+fn test_merged_search_output_apply_roundtrip() {
+    use crate::apply::apply_format_to_fs;
+    use crate::execute::write_merged_format;
+    use crate::filesystem::FileSystem;
+    use crate::format::Format;
+
+    let fs = MemoryFS::new();
+    let first = PathBuf::from("/test/a.txt");
+    let second = PathBuf::from("/test/b.txt");
+    let first_content = "1\nfunc a\n3\n4\nfunc b\n6\n7\n8\n9\nfunc c";
+    let second_content = "func d\n2\n3\n";
+    fs.add_file(&first, first_content).unwrap();
+    fs.add_file(&second, second_content).unwrap();
+
+    let matcher = GrepMatcher::compile("func").unwrap().with_context(2);
+    let walker = SimpleWalker::new(vec![second.clone(), first.clone()]);
+    let searcher = Searcher::new(fs.clone(), matcher, walker);
+
+    let mut output = Vec::new();
+    let chunks = write_merged_format(&mut output, searcher.search_all(), false).unwrap();
+    let mut format: Format = String::from_utf8(output).unwrap().parse().unwrap();
+    assert_eq!(chunks, 2);
+    assert_eq!(format.len(), 2);
+    assert_eq!(format.0[0].path, first);
+
+    apply_format_to_fs(&mut format, &fs).unwrap();
+    assert_eq!(fs.read_to_string(&first).unwrap(), first_content);
+    assert_eq!(fs.read_to_string(&second).unwrap(), second_content);
+    // A file walked twice still comes out once
+    let matcher = GrepMatcher::compile("func").unwrap().with_context(2);
+    let walker = SimpleWalker::new(vec![first.clone(), second.clone(), first.clone()]);
+    let searcher = Searcher::new(fs.clone(), matcher, walker);
+    let mut output = Vec::new();
+    let chunks = write_merged_format(&mut output, searcher.search_all(), false).unwrap();
+    assert_eq!(chunks, 2);
+}
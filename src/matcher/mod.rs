@@ -24,6 +24,20 @@ pub enum MatcherError {
         source: GrepRegexError,
     },
 
+    /// Bytes passed to `GrepMatcher::from_bytes` weren't produced by `to_bytes`
+    #[error("Invalid serialized matcher: {reason}")]
+    InvalidSerialized { reason: &'static str },
+
+    /// `GrepMatcher::replace_all` would have split a multibyte character
+    #[error(
+        "Replacing matches of '{pattern}' would leave invalid UTF-8: a pattern without Unicode (--no-unicode) matched part of a character"
+    )]
+    InvalidReplacement { pattern: String },
+
+    /// `GrepMatcher::with_multiline_dotall` was turned on outside multiline mode
+    #[error("`.` can only match newlines in multiline mode (--multiline)")]
+    DotallWithoutMultiline,
+
     /// Search operation failed
     #[error("Search error: {source}")]
     SearchError {
@@ -32,21 +46,70 @@ pub enum MatcherError {
     },
 }
 
+/// How a file that isn't valid UTF-8 is searched, see
+/// [`GrepMatcher::with_non_utf8_strategy`](regex::GrepMatcher::with_non_utf8_strategy)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonUtf8Strategy {
+    /// Skip the file with a warning
+    #[default]
+    Skip,
+
+    /// Decode it with each invalid sequence replaced by U+FFFD
+    Lossy,
+
+    /// Search its raw bytes, reporting invalid bytes in lines as `\xNN`
+    Binary,
+}
+
 /// Information about a single match within file content
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MatchInfo {
     /// Line number where the match occurred (1-indexed)
     pub line_num: usize,
-    /// Byte offset within the file
+    /// Byte offset within the file of the start of the matched line
     pub byte_offset: usize,
-    /// Content of the line containing the match
+    /// Content of the line containing the match (in multiline mode, every line
+    /// the match spans, so it may hold several lines)
     pub line_content: String,
 
     pub line_match: Option<std::ops::Range<usize>>,
 
+    /// Column of the first match on the line (1-indexed, in characters; 0 if unknown)
+    pub column_start: usize,
+    /// Column just past the end of the first match (1-indexed, in characters; 0 if unknown)
+    pub column_end: usize,
+
     pub previous_lines: String,
 
     pub next_lines: String,
+
+    /// Byte range of every match in `line_content`, in order, when the matcher
+    /// collects them (see `GrepMatcher::all_match_spans`); empty otherwise
+    pub spans: Vec<std::ops::Range<usize>>,
+}
+
+/// Parse a pattern file (`grep -f`): one pattern per line, skipping blank
+/// lines and `#` comments
+#[must_use]
+pub fn parse_pattern_file(content: &str) -> Vec<&str> {
+    content
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect()
+}
+
+/// Combine several patterns into one regex that matches any of them
+#[must_use]
+pub fn combine_patterns<S: AsRef<str>>(patterns: &[S]) -> String {
+    match patterns {
+        [single] => single.as_ref().to_string(),
+        _ => patterns
+            .iter()
+            .map(|p| format!("(?:{})", p.as_ref()))
+            .collect::<Vec<_>>()
+            .join("|"),
+    }
 }
 
 /// Abstract pattern matching interface
@@ -67,6 +130,19 @@ pub trait Matcher: Send + Sync {
     /// Returns all matches found in the content, with line numbers and positions.
     fn search_in_content(&self, content: &str) -> Vec<MatchInfo>;
 
+    /// Search raw file contents that aren't valid UTF-8, see
+    /// [`NonUtf8Strategy::Binary`]
+    ///
+    /// The default searches them decoded lossily instead.
+    fn search_in_bytes(&self, bytes: &[u8]) -> Vec<MatchInfo> {
+        self.search_in_content(&String::from_utf8_lossy(bytes))
+    }
+
+    /// How files that aren't valid UTF-8 should be searched
+    fn non_utf8_strategy(&self) -> NonUtf8Strategy {
+        NonUtf8Strategy::Skip
+    }
+
     /// Check if a single line matches the pattern
     ///
     /// This is a helper method for simpler matching scenarios.
@@ -112,6 +188,20 @@ mod tests {
         assert!(matches[1].line_content.contains("say hello"));
     }
 
+    #[test]
+    fn test_pattern_file_combines_patterns() {
+        let content = "# things to audit\nunwrap\\(\\)\n\nTODO\n";
+
+        let patterns = parse_pattern_file(content);
+        assert_eq!(patterns, vec!["unwrap\\(\\)", "TODO"]);
+
+        let matcher = GrepMatcher::compile(&combine_patterns(&patterns)).unwrap();
+        assert!(matcher.is_match("x.unwrap()"));
+        assert!(matcher.is_match("// TODO: fix"));
+        assert!(!matcher.is_match("# things to audit"));
+        assert!(!matcher.is_match("unwrap_or"));
+    }
+
     #[test]
     fn test_stub_matcher_returns_predefined_matches() {
         let mut matcher = StubMatcher::new();
@@ -120,8 +210,11 @@ mod tests {
             byte_offset: 100,
             line_content: "test line\n".to_string(),
             line_match: None,
+            column_start: 0,
+            column_end: 0,
             previous_lines: String::new(),
             next_lines: String::new(),
+            spans: Vec::new(),
         });
 
         let matches = matcher.search_in_content("any content");
@@ -6,32 +6,158 @@
 
 use std::path::Path;
 
-use super::{MatchInfo, Matcher, MatcherError};
-use grep::matcher::Matcher as GrepMatcherTrait;
-use grep::regex::RegexMatcher as GrepRegexMatcher;
+use super::{MatchInfo, Matcher, MatcherError, NonUtf8Strategy};
+use grep::matcher::LineTerminator;
+use grep::matcher::{Captures, Matcher as GrepMatcherTrait};
+use grep::regex::{RegexMatcher as GrepRegexMatcher, RegexMatcherBuilder};
 use grep::searcher::{BinaryDetection, Searcher, SearcherBuilder};
 
 /// Production matcher using grep-regex
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GrepMatcher {
     matcher: GrepRegexMatcher,
+    pattern: String,
+    options: RegexOptions,
     context: usize,
+    all_spans: bool,
+    non_utf8: NonUtf8Strategy,
 }
 
+/// Everything besides the pattern that a [`GrepMatcher`]'s regex is compiled
+/// with, see the setter of each field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RegexOptions {
+    unicode: bool,
+    anchor_line: bool,
+    crlf: bool,
+    multiline: bool,
+    dotall: bool,
+    line_terminator: u8,
+}
+
+impl RegexOptions {
+    /// The default options, Unicode-aware or not
+    fn new(unicode: bool) -> Self {
+        Self {
+            unicode,
+            anchor_line: false,
+            crlf: false,
+            multiline: false,
+            dotall: false,
+            line_terminator: b'\n',
+        }
+    }
+
+    /// The flags byte of the [`GrepMatcher::to_bytes`] encoding, without
+    /// [`FLAG_ALL_SPANS`], which isn't a compile option
+    fn flags(self) -> u8 {
+        [
+            (self.unicode, FLAG_UNICODE),
+            (self.anchor_line, FLAG_ANCHOR_LINE),
+            (self.crlf, FLAG_CRLF),
+            (self.multiline, FLAG_MULTILINE),
+            (self.dotall, FLAG_DOTALL),
+            (self.line_terminator != b'\n', FLAG_LINE_TERMINATOR),
+        ]
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+
+    /// The options encoded by a flags byte and the line terminator stored with it
+    fn from_flags(flags: u8, line_terminator: u8) -> Self {
+        let multiline = flags & FLAG_MULTILINE != 0;
+        Self {
+            unicode: flags & FLAG_UNICODE != 0,
+            anchor_line: flags & FLAG_ANCHOR_LINE != 0,
+            crlf: flags & FLAG_CRLF != 0,
+            multiline,
+            dotall: flags & FLAG_DOTALL != 0 && multiline,
+            line_terminator,
+        }
+    }
+}
+
+/// Leading bytes of [`GrepMatcher::to_bytes`] output; the trailing digit is the
+/// encoding version
+const SERIALIZED_MAGIC: &[u8; 5] = b"BKRE2";
+
+/// Flags byte bit for [`GrepMatcher::compile_with_unicode`]
+const FLAG_UNICODE: u8 = 1;
+
+/// Flags byte bit for [`GrepMatcher::anchor_line`]
+const FLAG_ANCHOR_LINE: u8 = 2;
+
+/// Flags byte bit for [`GrepMatcher::crlf`]
+const FLAG_CRLF: u8 = 4;
+
+/// Flags byte bit for [`GrepMatcher::with_multiline`]
+const FLAG_MULTILINE: u8 = 8;
+
+/// Flags byte bit for [`GrepMatcher::all_match_spans`]
+const FLAG_ALL_SPANS: u8 = 16;
+
+/// Flags byte bit for [`GrepMatcher::with_multiline_dotall`]
+const FLAG_DOTALL: u8 = 32;
+
+/// Flags byte bit set when a [`GrepMatcher::with_line_terminator`] byte other
+/// than `\n` follows the flags byte
+const FLAG_LINE_TERMINATOR: u8 = 64;
+
 mod sink {
+    use std::borrow::Cow;
+    use std::fmt::Write;
     use std::io;
 
     use grep::searcher::{Searcher, Sink, SinkError, SinkMatch};
 
     use crate::matcher::MatchInfo;
 
+    /// Collects matches, decoding each line as UTF-8
+    ///
+    /// The last field is whether bytes that aren't valid UTF-8 are escaped as
+    /// `\xNN` rather than failing the search.
     #[derive(Debug)]
-    pub struct UTF8<'a>(&'a mut Vec<MatchInfo>, String);
+    pub struct UTF8<'a>(&'a mut Vec<MatchInfo>, String, bool);
 
     impl<'a> UTF8<'a> {
         pub fn new(matches: &'a mut Vec<MatchInfo>) -> Self {
-            Self(matches, String::new())
+            Self(matches, String::new(), false)
         }
+
+        /// A sink that escapes invalid UTF-8 as `\xNN` instead of failing
+        pub fn escaping(matches: &'a mut Vec<MatchInfo>) -> Self {
+            Self(matches, String::new(), true)
+        }
+
+        fn decode<'b>(&self, bytes: &'b [u8]) -> Result<Cow<'b, str>, io::Error> {
+            if self.2 {
+                Ok(escape_invalid(bytes))
+            } else {
+                decode(bytes).map(Cow::Borrowed)
+            }
+        }
+    }
+
+    /// Decode a line, reporting invalid UTF-8 as an `InvalidData` error so
+    /// callers can tell it apart from other I/O failures.
+    fn decode(bytes: &[u8]) -> Result<&str, io::Error> {
+        std::str::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Decode `bytes`, writing each byte that isn't valid UTF-8 as `\xNN`
+    pub fn escape_invalid(bytes: &[u8]) -> Cow<'_, str> {
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return Cow::Borrowed(text);
+        }
+        let mut text = String::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            text.push_str(chunk.valid());
+            for byte in chunk.invalid() {
+                let _ = write!(text, "\\x{byte:02X}");
+            }
+        }
+        Cow::Owned(text)
     }
 
     impl Sink for UTF8<'_> {
@@ -42,10 +168,7 @@ mod sink {
             _searcher: &Searcher,
             mat: &SinkMatch<'_>,
         ) -> Result<bool, io::Error> {
-            let matched = match std::str::from_utf8(mat.bytes()) {
-                Ok(matched) => matched,
-                Err(err) => return Err(io::Error::error_message(err)),
-            };
+            let matched = self.decode(mat.bytes())?;
 
             let Some(line_number) = mat.line_number() else {
                 let msg = "line numbers not enabled";
@@ -59,10 +182,13 @@ mod sink {
             self.0.push(MatchInfo {
                 line_num: line_number as usize,
                 byte_offset: byte_offset as usize,
-                line_content: matched.to_string(),
+                line_content: matched.into_owned(),
                 line_match: None,
+                column_start: 0,
+                column_end: 0,
                 previous_lines: prev,
                 next_lines: String::new(),
+                spans: Vec::new(),
             });
             Ok(true)
         }
@@ -72,19 +198,16 @@ mod sink {
             _searcher: &Searcher,
             mat: &grep::searcher::SinkContext<'_>,
         ) -> Result<bool, Self::Error> {
-            let matched = match std::str::from_utf8(mat.bytes()) {
-                Ok(matched) => matched,
-                Err(err) => return Err(io::Error::error_message(err)),
-            };
+            let matched = self.decode(mat.bytes())?;
 
             match mat.kind() {
                 grep::searcher::SinkContextKind::Before => {
-                    self.1.push_str(matched);
+                    self.1.push_str(&matched);
                 }
 
                 grep::searcher::SinkContextKind::After => {
                     if let Some(last) = self.0.last_mut() {
-                        last.next_lines.push_str(matched);
+                        last.next_lines.push_str(&matched);
                     }
                 }
 
@@ -97,11 +220,29 @@ mod sink {
 }
 
 impl GrepMatcher {
+    /// Set the lines of context captured before and after each match
+    ///
+    /// Any value is accepted: context stops at the start and end of the file, so
+    /// a context larger than the file simply captures the whole file.
     pub fn with_context(self, context: usize) -> Self {
-        Self {
-            matcher: self.matcher.clone(),
-            context,
-        }
+        Self { context, ..self }
+    }
+
+    /// Record the byte range of every match on each matching line in
+    /// [`MatchInfo::spans`], not just the first (default: off)
+    ///
+    /// A line is still reported once however many times it matches; the spans
+    /// are what tell the occurrences apart.
+    #[must_use]
+    pub fn all_match_spans(self, all_spans: bool) -> Self {
+        Self { all_spans, ..self }
+    }
+
+    /// Choose how files that aren't valid UTF-8 are searched (default:
+    /// [`NonUtf8Strategy::Skip`])
+    #[must_use]
+    pub fn with_non_utf8_strategy(self, non_utf8: NonUtf8Strategy) -> Self {
+        Self { non_utf8, ..self }
     }
 
     fn build_searcher(&self) -> Searcher {
@@ -111,36 +252,342 @@ impl GrepMatcher {
         // This matches the behavior of ripgrep and other grep tools.
         let mut searcher = SearcherBuilder::new();
 
+        // NUL-separated records are full of NULs, so they can't mean binary there
+        let binary_detection = if self.options.line_terminator == b'\0' {
+            BinaryDetection::none()
+        } else {
+            BinaryDetection::quit(b'\x00')
+        };
         searcher
-            .binary_detection(BinaryDetection::quit(b'\x00'))
-            .line_number(true);
+            .binary_detection(binary_detection)
+            .line_number(true)
+            .multi_line(self.options.multiline);
+        if self.options.crlf {
+            searcher.line_terminator(LineTerminator::crlf());
+        } else if self.options.line_terminator != b'\n' {
+            searcher.line_terminator(LineTerminator::byte(self.options.line_terminator));
+        }
 
-        if self.context > 0 {
+        // Context is split into `\n` lines downstream, so records don't get any
+        if self.context > 0 && (self.options.crlf || self.options.line_terminator == b'\n') {
             searcher.before_context(self.context);
             searcher.after_context(self.context);
         }
 
         searcher.build()
     }
-}
 
-impl Matcher for GrepMatcher {
-    fn compile(pattern: &str) -> Result<Self, MatcherError>
-    where
-        Self: Sized,
-    {
-        let matcher =
-            GrepRegexMatcher::new(pattern).map_err(|source| MatcherError::InvalidPattern {
+    /// Compile `pattern`, choosing whether it's Unicode-aware
+    ///
+    /// [`Matcher::compile`] is Unicode-aware. With `unicode` off, `\w`, `\d`,
+    /// `\s` and `\b` only know ASCII and `.` matches any single byte, which is
+    /// faster on large ASCII inputs such as logs.
+    ///
+    /// # Errors
+    /// Returns an error if `pattern` is not a valid regex.
+    pub fn compile_with_unicode(pattern: &str, unicode: bool) -> Result<Self, MatcherError> {
+        Self::build(pattern, RegexOptions::new(unicode))
+    }
+
+    /// Only match lines that are entirely the pattern, like `grep -x`
+    ///
+    /// This anchors the pattern as `^(?:pattern)$`, so `foo` matches the line
+    /// `foo` but not `   foo   `. Unlike word matching, any surrounding text at
+    /// all rules the line out.
+    ///
+    /// # Errors
+    /// Returns an error if the anchored pattern fails to compile.
+    pub fn anchor_line(self, anchor_line: bool) -> Result<Self, MatcherError> {
+        let options = RegexOptions {
+            anchor_line,
+            ..self.options
+        };
+        self.rebuild(options)
+    }
+
+    /// Treat `\r\n` as the line terminator, like `rg --crlf`
+    ///
+    /// Lines then end at `\r\n` and `$` matches before the `\r`. The `\r` stays
+    /// in the reported line content and context, so chunks applied back keep the
+    /// file's line endings. By default a `\r` is ordinary text, and `foo$`
+    /// doesn't match `foo\r\n`.
+    ///
+    /// # Errors
+    /// Returns an error if the pattern fails to compile in CRLF mode.
+    pub fn crlf(self, crlf: bool) -> Result<Self, MatcherError> {
+        let options = RegexOptions {
+            crlf,
+            ..self.options
+        };
+        self.rebuild(options)
+    }
+
+    /// Let matches span lines, like `rg -U`
+    ///
+    /// The pattern is matched against the whole file instead of one line at a
+    /// time, so `\n` in the pattern can match a line break (`foo\nbar` matches
+    /// `foo` at the end of one line and `bar` at the start of the next), while
+    /// `^` and `$` still match at the start and end of each line. A match is
+    /// reported once, on the line it starts on: its `line_content` holds every
+    /// line the match touches, so it may contain newlines, and the context after
+    /// it starts below its last line. `.` still doesn't match `\n`, unless
+    /// [`GrepMatcher::with_multiline_dotall`] is on; turning multiline off turns
+    /// that off too.
+    ///
+    /// # Errors
+    /// Returns an error if the pattern fails to compile in multiline mode.
+    pub fn with_multiline(self, multiline: bool) -> Result<Self, MatcherError> {
+        let options = RegexOptions {
+            multiline,
+            dotall: self.options.dotall && multiline,
+            ..self.options
+        };
+        self.rebuild(options)
+    }
+
+    /// Let `.` match `\n` too, so `begin.*end` can span lines (default: off)
+    ///
+    /// Only meaningful in multiline mode: line-by-line search never shows `.` a
+    /// newline. Beware that `.*` then runs to the last possible match in the
+    /// file; `.*?` stops at the first.
+    ///
+    /// # Errors
+    /// Returns `MatcherError::DotallWithoutMultiline` if turned on without
+    /// [`GrepMatcher::with_multiline`], or an error if the pattern fails to
+    /// compile.
+    pub fn with_multiline_dotall(self, dotall: bool) -> Result<Self, MatcherError> {
+        if dotall && !self.options.multiline {
+            return Err(MatcherError::DotallWithoutMultiline);
+        }
+        let options = RegexOptions {
+            dotall,
+            ..self.options
+        };
+        self.rebuild(options)
+    }
+
+    /// Split the input into "lines" at `terminator` instead of `\n`, like
+    /// `rg --null-data` with `b'\0'`
+    ///
+    /// Each match is then a whole terminator-delimited record (including its
+    /// terminator), line numbers count records, and no context is collected,
+    /// since context is handled as `\n` lines. The pattern never matches the
+    /// terminator itself, so a match can't run into the next record. With
+    /// [`GrepMatcher::crlf`] on, CRLF wins.
+    ///
+    /// # Errors
+    /// Returns an error if the pattern fails to compile with `terminator`.
+    pub fn with_line_terminator(self, terminator: u8) -> Result<Self, MatcherError> {
+        let options = RegexOptions {
+            line_terminator: terminator,
+            ..self.options
+        };
+        self.rebuild(options)
+    }
+
+    /// Recompile the pattern with `options`, keeping the settings that don't
+    /// affect compilation
+    fn rebuild(self, options: RegexOptions) -> Result<Self, MatcherError> {
+        if options == self.options {
+            return Ok(self);
+        }
+        Ok(Self {
+            context: self.context,
+            all_spans: self.all_spans,
+            non_utf8: self.non_utf8,
+            ..Self::build(&self.pattern, options)?
+        })
+    }
+
+    fn build(pattern: &str, options: RegexOptions) -> Result<Self, MatcherError> {
+        let mut builder = RegexMatcherBuilder::new();
+        builder
+            .unicode(options.unicode)
+            .whole_line(options.anchor_line)
+            .crlf(options.crlf)
+            // The whole file is one haystack, so `^`/`$` must know about lines
+            .multi_line(options.multiline)
+            .dot_matches_new_line(options.dotall);
+        if options.line_terminator != b'\n' && !options.crlf && !options.multiline {
+            builder.line_terminator(Some(options.line_terminator));
+        }
+        let matcher = builder
+            .build(pattern)
+            .map_err(|source| MatcherError::InvalidPattern {
                 pattern: pattern.to_string(),
                 source,
             })?;
 
         Ok(Self {
             matcher,
+            pattern: pattern.to_string(),
+            options,
             context: 0,
+            all_spans: false,
+            non_utf8: NonUtf8Strategy::default(),
         })
     }
 
+    /// Serialize this matcher so another process can rebuild it with
+    /// [`GrepMatcher::from_bytes`]
+    ///
+    /// The compiled automaton itself isn't portable, so this stores what it was
+    /// built from: a magic header, the context as a little-endian `u64`, a flags
+    /// byte, the line terminator byte if it isn't `\n`, then the UTF-8 pattern.
+    /// Rebuilding recompiles the pattern.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SERIALIZED_MAGIC.len() + 9 + self.pattern.len());
+        bytes.extend_from_slice(SERIALIZED_MAGIC);
+        bytes.extend_from_slice(&(self.context as u64).to_le_bytes());
+        let mut flags = self.options.flags();
+        if self.all_spans {
+            flags |= FLAG_ALL_SPANS;
+        }
+        bytes.push(flags);
+        if self.options.line_terminator != b'\n' {
+            bytes.push(self.options.line_terminator);
+        }
+        bytes.extend_from_slice(self.pattern.as_bytes());
+        bytes
+    }
+
+    /// Rebuild a matcher serialized with [`GrepMatcher::to_bytes`]
+    ///
+    /// # Errors
+    /// Returns `MatcherError::InvalidSerialized` if `bytes` aren't in the
+    /// `to_bytes` encoding, or `MatcherError::InvalidPattern` if the stored
+    /// pattern no longer compiles.
+    #[allow(dead_code)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MatcherError> {
+        let invalid = |reason| MatcherError::InvalidSerialized { reason };
+        let rest = bytes
+            .strip_prefix(SERIALIZED_MAGIC)
+            .ok_or(invalid("missing header or unsupported version"))?;
+        let (context, rest) = rest
+            .split_first_chunk::<8>()
+            .ok_or(invalid("truncated context"))?;
+        let (flags, pattern) = rest.split_first().ok_or(invalid("truncated flags"))?;
+        let flags = *flags;
+        let (line_terminator, pattern) = if flags & FLAG_LINE_TERMINATOR == 0 {
+            (b'\n', pattern)
+        } else {
+            let (terminator, pattern) = pattern
+                .split_first()
+                .ok_or(invalid("truncated line terminator"))?;
+            (*terminator, pattern)
+        };
+        let context = usize::try_from(u64::from_le_bytes(*context)).map_err(|_| {
+            MatcherError::InvalidSerialized {
+                reason: "context out of range",
+            }
+        })?;
+        let pattern =
+            std::str::from_utf8(pattern).map_err(|_| MatcherError::InvalidSerialized {
+                reason: "pattern is not valid UTF-8",
+            })?;
+
+        let options = RegexOptions::from_flags(flags, line_terminator);
+        Ok(Self::build(pattern, options)?
+            .with_context(context)
+            .all_match_spans(flags & FLAG_ALL_SPANS != 0))
+    }
+
+    /// Replace every match in `line` with `replacement`
+    ///
+    /// `replacement` may refer to capture groups as `$1` or `${name}`, like
+    /// ripgrep's `--replace`.
+    ///
+    /// # Errors
+    /// Returns an error if the replaced line isn't valid UTF-8, which happens when
+    /// a pattern compiled without Unicode matches part of a multibyte character.
+    pub fn replace_all(&self, line: &str, replacement: &str) -> Result<String, MatcherError> {
+        let invalid = || MatcherError::InvalidReplacement {
+            pattern: self.pattern.clone(),
+        };
+        let mut caps = self.matcher.new_captures().map_err(|_| invalid())?;
+        let mut dst = Vec::with_capacity(line.len());
+        let result = self.matcher.replace_with_captures(
+            line.as_bytes(),
+            &mut caps,
+            &mut dst,
+            |caps, dst| {
+                caps.interpolate(
+                    |name| self.matcher.capture_index(name),
+                    line.as_bytes(),
+                    replacement.as_bytes(),
+                    dst,
+                );
+                true
+            },
+        );
+        result.map_err(|_| invalid())?;
+        String::from_utf8(dst).map_err(|_| invalid())
+    }
+
+    /// The byte range of every match in `line`, in order
+    ///
+    /// Each range is widened to whole characters, since a pattern compiled
+    /// without Unicode can match part of a multibyte character.
+    pub fn find_spans(&self, line: &str) -> Vec<std::ops::Range<usize>> {
+        let mut spans = Vec::new();
+        let _ = self.matcher.find_iter(line.as_bytes(), |m| {
+            spans.push(char_span(line, m.start()..m.end()));
+            true
+        });
+        spans
+    }
+
+    /// Locate the first match within each match's line, filling in the byte
+    /// range and the 1-based character columns, and every match's range if
+    /// [`GrepMatcher::all_match_spans`] is on.
+    fn locate_matches(&self, matches: &mut [MatchInfo]) {
+        for cur_match in matches {
+            if self.all_spans {
+                cur_match.spans = self.find_spans(&cur_match.line_content);
+            }
+            let line = cur_match.line_content.as_bytes();
+            let mut first = None;
+            let _ = self.matcher.find_iter(line, |m| {
+                first = Some(m);
+                false
+            });
+            let Some(m) = first else {
+                continue;
+            };
+
+            let line = &cur_match.line_content;
+            let span = char_span(line, m.start()..m.end());
+            let column = |byte: usize| line.get(..byte).map_or(0, |s| s.chars().count() + 1);
+            cur_match.column_start = column(span.start);
+            cur_match.column_end = column(span.end);
+            cur_match.line_match = Some(span);
+        }
+    }
+}
+
+/// Widen `span` outward to the nearest character boundaries of `line`
+fn char_span(line: &str, span: std::ops::Range<usize>) -> std::ops::Range<usize> {
+    let mut start = span.start.min(line.len());
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = span.end.min(line.len());
+    while !line.is_char_boundary(end) {
+        end += 1;
+    }
+    start..end
+}
+
+impl Matcher for GrepMatcher {
+    fn compile(pattern: &str) -> Result<Self, MatcherError>
+    where
+        Self: Sized,
+    {
+        Self::compile_with_unicode(pattern, true)
+    }
+
     fn search_in_content(&self, content: &str) -> Vec<MatchInfo> {
         let mut matches = Vec::new();
 
@@ -157,9 +604,31 @@ impl Matcher for GrepMatcher {
             tracing::warn!("Search error: {}", e);
         }
 
+        self.locate_matches(&mut matches);
+        matches
+    }
+
+    /// Search the raw bytes, NULs and all, reporting bytes that aren't valid
+    /// UTF-8 as `\xNN`
+    fn search_in_bytes(&self, bytes: &[u8]) -> Vec<MatchInfo> {
+        let mut matches = Vec::new();
+
+        let mut searcher = self.build_searcher();
+        searcher.set_binary_detection(BinaryDetection::none());
+        let result =
+            searcher.search_slice(&self.matcher, bytes, sink::UTF8::escaping(&mut matches));
+        if let Err(e) = result {
+            tracing::warn!("Search error: {}", e);
+        }
+
+        self.locate_matches(&mut matches);
         matches
     }
 
+    fn non_utf8_strategy(&self) -> NonUtf8Strategy {
+        self.non_utf8
+    }
+
     fn is_match(&self, text: &str) -> bool {
         self.matcher.is_match(text.as_bytes()).unwrap_or(false)
     }
@@ -173,14 +642,7 @@ impl Matcher for GrepMatcher {
                 .search_path(&self.matcher, path, sink::UTF8::new(&mut matches))
                 .map_err(|source| MatcherError::SearchError { source })?;
 
-            for cur_match in &mut matches {
-                let Ok(Some(m)) = self.matcher.find_at(cur_match.line_content.as_bytes(), 0) else {
-                    continue;
-                };
-
-                cur_match.line_match = Some(m.start()..m.end());
-            }
-
+            self.locate_matches(&mut matches);
             Ok(matches)
         })
     }
@@ -294,4 +756,324 @@ mod tests {
         assert_eq!(after_lines[1], "line 7\n");
         assert_eq!(after_lines[2], "line 8\n");
     }
+
+    #[test]
+    fn test_grep_matcher_multiline() {
+        let content = "one\nfoo\nbar\nfoo\nbaz\n";
+        let single = GrepMatcher::compile(r"foo\nbar").unwrap();
+        assert!(single.search_in_content(content).is_empty());
+
+        let multiline = single.with_context(1).with_multiline(true).unwrap();
+        let matches = multiline.search_in_content(content);
+
+        assert_eq!(matches.len(), 1);
+        let m = &matches[0];
+        assert_eq!(m.line_num, 2);
+        assert_eq!(m.line_content, "foo\nbar\n");
+        assert_eq!(m.line_match, Some(0..7));
+        assert_eq!(m.previous_lines, "one\n");
+        assert_eq!(m.next_lines, "foo\n");
+
+        let restored = GrepMatcher::from_bytes(&multiline.to_bytes()).unwrap();
+        assert!(restored.options.multiline);
+    }
+
+    #[test]
+    fn test_grep_matcher_multiline_dotall() {
+        let content = "before\nbegin\nmiddle\nend\nafter\n";
+        let multiline = GrepMatcher::compile("begin.*end")
+            .unwrap()
+            .with_multiline(true)
+            .unwrap();
+        assert!(multiline.search_in_content(content).is_empty());
+
+        let dotall = multiline.with_multiline_dotall(true).unwrap();
+        let matches = dotall.search_in_content(content);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_num, 2);
+        assert_eq!(matches[0].line_content, "begin\nmiddle\nend\n");
+
+        let err = GrepMatcher::compile("begin.*end")
+            .unwrap()
+            .with_multiline_dotall(true)
+            .unwrap_err();
+        assert!(matches!(err, MatcherError::DotallWithoutMultiline));
+    }
+
+    #[test]
+    fn test_grep_matcher_nul_terminated_records() {
+        let content = "alpha\0beta foo\0gamma\nfoo\0foo end";
+        let matcher = GrepMatcher::compile("foo")
+            .unwrap()
+            .with_context(2)
+            .with_line_terminator(b'\0')
+            .unwrap();
+
+        let matches = matcher.search_in_content(content);
+        let found: Vec<(usize, &str)> = matches
+            .iter()
+            .map(|m| (m.line_num, m.line_content.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(2, "beta foo\0"), (3, "gamma\nfoo\0"), (4, "foo end")]
+        );
+        assert!(matches[0].previous_lines.is_empty());
+
+        let restored = GrepMatcher::from_bytes(&matcher.to_bytes()).unwrap();
+        assert_eq!(restored.options.line_terminator, b'\0');
+        assert_eq!(restored.search_in_content(content), matches);
+    }
+
+    #[test]
+    fn test_grep_matcher_all_match_spans() {
+        let first_only = GrepMatcher::compile("foo").unwrap();
+        let matches = first_only.search_in_content("foo foo foo\n");
+        assert_eq!(matches[0].line_match, Some(0..3));
+        assert!(matches[0].spans.is_empty());
+
+        let all = first_only.all_match_spans(true);
+        let matches = all.search_in_content("foo foo foo\nbar\n");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].spans, vec![0..3, 4..7, 8..11]);
+
+        let restored = GrepMatcher::from_bytes(&all.to_bytes()).unwrap();
+        assert!(restored.all_spans);
+    }
+
+    #[test]
+    fn test_grep_matcher_bytes_round_trip() {
+        let content = "fn alpha() {}\nlet x = 1;\nfn beta_2() {}\nend\n";
+        let original = GrepMatcher::compile(r"fn (\w+)\(").unwrap().with_context(1);
+
+        let restored = GrepMatcher::from_bytes(&original.to_bytes()).unwrap();
+
+        assert_eq!(restored.to_bytes(), original.to_bytes());
+        assert_eq!(
+            restored.search_in_content(content),
+            original.search_in_content(content)
+        );
+    }
+
+    #[test]
+    fn test_grep_matcher_rebuild_keeps_every_setting() {
+        let matcher = GrepMatcher::compile_with_unicode("foo", false)
+            .unwrap()
+            .with_context(2)
+            .all_match_spans(true)
+            .with_non_utf8_strategy(NonUtf8Strategy::Lossy)
+            .with_multiline(true)
+            .unwrap()
+            .with_multiline_dotall(true)
+            .unwrap()
+            .crlf(true)
+            .unwrap()
+            .anchor_line(true)
+            .unwrap();
+
+        let expected = RegexOptions {
+            unicode: false,
+            anchor_line: true,
+            crlf: true,
+            multiline: true,
+            dotall: true,
+            line_terminator: b'\n',
+        };
+        assert_eq!(matcher.options, expected);
+        assert_eq!(matcher.context, 2);
+        assert!(matcher.all_spans);
+        assert_eq!(matcher.non_utf8, NonUtf8Strategy::Lossy);
+        assert_eq!(RegexOptions::from_flags(expected.flags(), b'\n'), expected);
+
+        // Turning multiline off turns dotall off with it
+        let single = matcher.with_multiline(false).unwrap();
+        assert!(!single.options.dotall);
+    }
+
+    #[test]
+    fn test_grep_matcher_bytes_keep_unicode_flag() {
+        let ascii = GrepMatcher::compile_with_unicode(r"\w+", false).unwrap();
+        let restored = GrepMatcher::from_bytes(&ascii.to_bytes()).unwrap();
+        assert!(!restored.options.unicode);
+    }
+
+    #[test]
+    fn test_grep_matcher_anchor_line() {
+        let anchored = GrepMatcher::compile("foo|bar")
+            .unwrap()
+            .with_context(1)
+            .anchor_line(true)
+            .unwrap();
+
+        assert!(anchored.is_match("foo"));
+        assert!(anchored.is_match("bar"));
+        assert!(!anchored.is_match("   foo   "));
+        assert!(!anchored.is_match("foobar"));
+
+        let matches = anchored.search_in_content("foo\n  foo\nfood\nbar\n");
+        let lines: Vec<_> = matches.iter().map(|m| m.line_num).collect();
+        assert_eq!(lines, vec![1, 4]);
+        assert_eq!(matches[0].next_lines, "  foo\n");
+
+        let restored = GrepMatcher::from_bytes(&anchored.to_bytes()).unwrap();
+        assert!(!restored.is_match("   foo   "));
+
+        let unanchored = anchored.anchor_line(false).unwrap();
+        assert!(unanchored.is_match("   foo   "));
+        assert_eq!(unanchored.context, 1);
+    }
+
+    #[test]
+    fn test_grep_matcher_crlf() {
+        let content = "one\r\nfoo\r\nthree\r\n";
+
+        let plain = GrepMatcher::compile("foo").unwrap().with_context(1);
+        let matches = plain.search_in_content(content);
+        assert_eq!(matches[0].line_content, "foo\r\n");
+        assert_eq!(matches[0].previous_lines, "one\r\n");
+        assert!(
+            GrepMatcher::compile("foo$")
+                .unwrap()
+                .search_in_content(content)
+                .is_empty()
+        );
+
+        let crlf = plain.crlf(true).unwrap();
+        let matches = crlf.search_in_content(content);
+        assert_eq!(matches[0].line_num, 2);
+        assert_eq!(matches[0].line_content, "foo\r\n");
+        assert_eq!(matches[0].line_match, Some(0..3));
+        assert_eq!(matches[0].previous_lines, "one\r\n");
+        assert_eq!(matches[0].next_lines, "three\r\n");
+
+        let anchored = GrepMatcher::compile("foo$").unwrap().crlf(true).unwrap();
+        assert_eq!(anchored.search_in_content(content).len(), 1);
+        let restored = GrepMatcher::from_bytes(&anchored.to_bytes()).unwrap();
+        assert_eq!(
+            restored.search_in_content(content)[0].line_content,
+            "foo\r\n"
+        );
+    }
+
+    #[test]
+    fn test_grep_matcher_unicode_word_characters() {
+        let line = "café naïve\n";
+
+        let unicode = GrepMatcher::compile(r"\w+").unwrap();
+        let words: Vec<_> = unicode.search_in_content(line);
+        assert_eq!(words[0].line_match, Some(0.."café".len()));
+        assert_eq!(unicode.replace_all(line, "_").unwrap(), "_ _\n");
+
+        // ASCII-only: accented letters aren't word characters
+        let ascii = GrepMatcher::compile_with_unicode(r"\w+", false).unwrap();
+        let words: Vec<_> = ascii.search_in_content(line);
+        assert_eq!(words[0].line_match, Some(0..3));
+        assert_eq!(ascii.replace_all(line, "_").unwrap(), "_é _ï_\n");
+    }
+
+    #[test]
+    fn test_grep_matcher_no_unicode_match_inside_multibyte_character() {
+        // Without Unicode, `.` matches the first byte of the two-byte `é`
+        let line = "café latte\n";
+        let ascii = GrepMatcher::compile_with_unicode("caf.", false).unwrap();
+
+        let found = ascii.search_in_content(line);
+        assert_eq!(found[0].line_match, Some(0.."café".len()));
+        assert_eq!((found[0].column_start, found[0].column_end), (1, 5));
+        assert_eq!(ascii.find_spans(line), vec![0.."café".len()]);
+        assert!(matches!(
+            ascii.replace_all(line, "tea"),
+            Err(MatcherError::InvalidReplacement { .. })
+        ));
+    }
+
+    #[test]
+    fn test_grep_matcher_from_bad_bytes() {
+        assert!(matches!(
+            GrepMatcher::from_bytes(b"not a matcher"),
+            Err(MatcherError::InvalidSerialized { .. })
+        ));
+        assert!(matches!(
+            GrepMatcher::from_bytes(b"BKRE2\x01\x00"),
+            Err(MatcherError::InvalidSerialized { .. })
+        ));
+
+        let mut bytes = SERIALIZED_MAGIC.to_vec();
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.push(FLAG_UNICODE);
+        bytes.extend_from_slice(b"[unclosed");
+        assert!(matches!(
+            GrepMatcher::from_bytes(&bytes),
+            Err(MatcherError::InvalidPattern { .. })
+        ));
+    }
+
+    #[test]
+    fn test_grep_matcher_replace_all() {
+        let matcher = GrepMatcher::compile(r"(\w+)_(?P<n>\d)").unwrap();
+
+        assert_eq!(
+            matcher.replace_all("let a_1 = b_2;\n", "${n}_$1").unwrap(),
+            "let 1_a = 2_b;\n"
+        );
+        assert_eq!(
+            matcher.replace_all("no match\n", "x").unwrap(),
+            "no match\n"
+        );
+    }
+
+    #[test]
+    fn test_grep_matcher_find_spans() {
+        let matcher = GrepMatcher::compile("foo").unwrap();
+
+        assert_eq!(matcher.find_spans("foo bar foo\n"), vec![0..3, 8..11]);
+        assert!(matcher.find_spans("bar\n").is_empty());
+    }
+
+    #[test]
+    fn test_grep_matcher_column_range() {
+        let matcher = GrepMatcher::compile("foo").unwrap();
+
+        let matches = matcher.search_in_content("   foo bar\n");
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].column_start, 4);
+        assert_eq!(matches[0].column_end, 7);
+        assert_eq!(matches[0].line_match, Some(3..6));
+    }
+
+    #[test]
+    fn test_grep_matcher_column_reports_first_match() {
+        let matcher = GrepMatcher::compile("ab").unwrap();
+
+        let matches = matcher.search_in_content("xab ab ab\n");
+
+        assert_eq!(matches[0].column_start, 2);
+        assert_eq!(matches[0].column_end, 4);
+    }
+
+    #[test]
+    fn test_grep_matcher_column_counts_characters_not_bytes() {
+        let matcher = GrepMatcher::compile("foo").unwrap();
+
+        // "héllo" is 5 characters but 6 bytes ('é' is two bytes in UTF-8)
+        let matches = matcher.search_in_content("héllo foo\n");
+
+        assert_eq!(matches[0].line_match, Some(7..10));
+        assert_eq!(matches[0].column_start, 7);
+        assert_eq!(matches[0].column_end, 10);
+    }
+
+    #[test]
+    fn test_grep_matcher_huge_context_is_bounded_by_file() {
+        let matcher = GrepMatcher::compile("MATCH")
+            .unwrap()
+            .with_context(usize::MAX);
+
+        let matches = matcher.search_in_content("line 1\nMATCH\nline 3\n");
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].previous_lines, "line 1\n");
+        assert_eq!(matches[0].next_lines, "line 3\n");
+    }
 }
@@ -121,8 +121,11 @@ mod tests {
             byte_offset: 42,
             line_content: "test line\n".to_string(),
             line_match: None,
+            column_start: 0,
+            column_end: 0,
             previous_lines: String::new(),
             next_lines: String::new(),
+            spans: Vec::new(),
         });
 
         let matches = matcher.search_in_content("ignored");
@@ -141,16 +144,22 @@ mod tests {
             byte_offset: 0,
             line_content: "first\n".to_string(),
             line_match: None,
+            column_start: 0,
+            column_end: 0,
             previous_lines: String::new(),
             next_lines: String::new(),
+            spans: Vec::new(),
         });
         matcher.add_match(MatchInfo {
             line_num: 2,
             byte_offset: 10,
             line_content: "second\n".to_string(),
             line_match: None,
+            column_start: 0,
+            column_end: 0,
             previous_lines: String::new(),
             next_lines: String::new(),
+            spans: Vec::new(),
         });
 
         let matches = matcher.search_in_content("ignored");
@@ -4,11 +4,21 @@
 //! using abstract dependencies (`FileSystem`, Matcher, Walker traits). This
 //! implements the functional core of the hexagonal architecture.
 
-use crate::filesystem::FileSystem;
-use crate::matcher::{MatchInfo, Matcher};
-use crate::types::{MatchResult, SearchError, SearchResult};
+use crate::filesystem::{FileSystem, FilesystemError};
+use crate::matcher::{MatchInfo, Matcher, MatcherError, NonUtf8Strategy};
+use crate::types::{ContextLine, MatchResult, SearchError, SearchResult, SearchStats};
 use crate::walker::Walker;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A searched file and its matches, or the error searching it
+type FileResult = (PathBuf, Result<Vec<MatchResult>, SearchError>);
+
+/// Rewrites or discards (by returning `None`) each match, or fails the file's
+/// search, see [`Searcher::with_try_match_transformer`]
+type MatchTransformer =
+    Box<dyn Fn(MatchResult) -> Result<Option<MatchResult>, MatcherError> + Send + Sync>;
 
 /// Core search orchestrator
 ///
@@ -23,6 +33,15 @@ where
     fs: FS,
     matcher: M,
     walker: W,
+    newest: Option<usize>,
+    max_total_bytes: Option<u64>,
+    function_header: Option<M>,
+    block_context: bool,
+    passthru: bool,
+    dedup_files: bool,
+    collect_stats: bool,
+    transformer: Option<MatchTransformer>,
+    stats: Mutex<SearchStats>,
 }
 
 impl<FS, M, W> Searcher<FS, M, W>
@@ -37,6 +56,377 @@ where
             fs,
             matcher,
             walker,
+            newest: None,
+            max_total_bytes: None,
+            function_header: None,
+            block_context: false,
+            passthru: false,
+            dedup_files: false,
+            collect_stats: false,
+            transformer: None,
+            stats: Mutex::default(),
+        }
+    }
+
+    /// The matcher files are searched with
+    pub fn matcher(&self) -> &M {
+        &self.matcher
+    }
+
+    /// Only search the `n` most recently modified files the walker yields
+    ///
+    /// This turns the walk phase from streaming into collect-first: every walked
+    /// path is gathered and stat'ed before any file is searched. Files without a
+    /// known modification time sort as the oldest.
+    #[must_use]
+    pub fn with_newest(mut self, n: usize) -> Self {
+        self.newest = Some(n);
+        self
+    }
+
+    /// Stop searching once the files searched add up to more than `bytes`
+    ///
+    /// A guardrail for untrusted trees: the walk ends early and the results found
+    /// so far are returned. The budget is approximate. Sizes come from file
+    /// metadata before each file is read, and the file that crosses the budget is
+    /// still searched in full, so slightly more than `bytes` may be read.
+    #[must_use]
+    pub fn with_max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    /// Attach the nearest enclosing function header to each match
+    ///
+    /// `header` matches lines that start a function (or other block). For every
+    /// match, the file is scanned backward from the match for the closest such
+    /// line, like `git grep --show-function`. Headers already visible in the
+    /// match's context are not repeated.
+    #[must_use]
+    pub fn with_show_function(mut self, header: M) -> Self {
+        self.function_header = Some(header);
+        self
+    }
+
+    /// Replace each match's fixed context with its whole enclosing `{ ... }` block
+    ///
+    /// The block is found by counting braces outward from the match, which suits
+    /// C-like languages but is only a heuristic: braces inside strings, chars and
+    /// comments are counted too, a header split across lines before its `{` is
+    /// left out, and blocks that aren't brace-delimited (Python, Ruby `end`) aren't
+    /// recognized. Matches outside any block, or in unbalanced code, keep their
+    /// ordinary context.
+    #[must_use]
+    pub fn with_block_context(mut self, block_context: bool) -> Self {
+        self.block_context = block_context;
+        self
+    }
+
+    /// Emit every line of each file that has a match, like `rg --passthru`
+    ///
+    /// The lines around the matches become their context: the first match takes
+    /// everything above it, and each match takes everything up to the next one, so
+    /// the chunks tile the whole file. This overrides the matcher's context and
+    /// [`Searcher::with_block_context`]. Files without a match emit nothing.
+    #[must_use]
+    pub fn with_passthru(mut self, passthru: bool) -> Self {
+        self.passthru = passthru;
+        self
+    }
+
+    /// Search each file once, however many walked paths lead to it
+    ///
+    /// Paths are compared by [`FileSystem::canonicalize`], so overlapping roots
+    /// or symlinks don't report a file twice; the first path walked is kept.
+    /// Paths that can't be canonicalized are always searched.
+    #[must_use]
+    pub fn with_dedup_files(mut self, dedup_files: bool) -> Self {
+        self.dedup_files = dedup_files;
+        self
+    }
+
+    /// Also count the files [`Searcher::stats`] reports as skipped for being
+    /// binary or over the byte budget (default: false)
+    ///
+    /// Those counts cost extra work: a read of each file without matches to
+    /// check whether it's binary, and walking on past the byte budget.
+    #[must_use]
+    pub fn with_stats(mut self, collect_stats: bool) -> Self {
+        self.collect_stats = collect_stats;
+        self
+    }
+
+    /// Pass every match through `transformer` before it's returned
+    ///
+    /// The transformer runs last, after context and function headers are filled
+    /// in. Returning `None` discards the match, so this can filter matches, enrich
+    /// them, or remap their paths.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn with_match_transformer<F>(self, transformer: F) -> Self
+    where
+        F: Fn(MatchResult) -> Option<MatchResult> + Send + Sync + 'static,
+    {
+        self.with_try_match_transformer(move |m| Ok(transformer(m)))
+    }
+
+    /// Like [`Searcher::with_match_transformer`], but the transformer can fail,
+    /// which fails the search of the match's file
+    #[must_use]
+    pub fn with_try_match_transformer<F>(mut self, transformer: F) -> Self
+    where
+        F: Fn(MatchResult) -> Result<Option<MatchResult>, MatcherError> + Send + Sync + 'static,
+    {
+        self.transformer = Some(Box::new(transformer));
+        self
+    }
+
+    /// Pass every match through `map`, after any transformer set so far
+    ///
+    /// Like [`Searcher::with_match_transformer`], but `map` may keep state
+    /// between matches (it's called under a lock), and it composes: `map` sees
+    /// what the earlier transformer returned. Returning `None` drops the match.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn with_map<F>(mut self, map: F) -> Self
+    where
+        F: FnMut(MatchResult) -> Option<MatchResult> + Send + 'static,
+    {
+        let map = Mutex::new(map);
+        let previous = self.transformer.take();
+        self.transformer = Some(Box::new(move |m| {
+            let m = match &previous {
+                Some(previous) => match previous(m)? {
+                    Some(m) => m,
+                    None => return Ok(None),
+                },
+                None => m,
+            };
+            Ok((map
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner))(
+                m
+            ))
+        }));
+        self
+    }
+
+    /// Fill in `function_header` on each match by scanning the file backward
+    fn attach_function_headers(&self, header: &M, path: &Path, matches: &mut [MatchResult]) {
+        let content = match self.fs.read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to read {} for --show-function: {}",
+                    path.display(),
+                    err
+                );
+                return;
+            }
+        };
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+
+        for m in matches {
+            let first_visible = m
+                .context_before
+                .first()
+                .map_or(m.line_number, |ctx| ctx.line_number);
+            // Lines are 1-indexed, so line `n` is `lines[n - 1]`.
+            let above = lines
+                .get(..m.line_number.saturating_sub(1))
+                .unwrap_or_default();
+            m.function_header = above
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(_, line)| header.is_match(line))
+                .map(|(idx, line)| ContextLine {
+                    line_number: idx + 1,
+                    content: (*line).to_string(),
+                })
+                .filter(|ctx| ctx.line_number < first_visible);
+        }
+    }
+
+    /// Widen each match's context to its enclosing block, see
+    /// [`Searcher::with_block_context`]
+    fn attach_block_context(&self, path: &Path, matches: &mut [MatchResult]) {
+        let content = match self.fs.read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to read {} for --block-context: {}",
+                    path.display(),
+                    err
+                );
+                return;
+            }
+        };
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let context = |range: std::ops::Range<usize>| {
+            range
+                .map(|idx| ContextLine {
+                    line_number: idx + 1,
+                    content: lines[idx].to_string(),
+                })
+                .collect()
+        };
+
+        for m in matches {
+            let idx = m.line_number.saturating_sub(1);
+            if let Some((start, end)) = enclosing_block(&lines, idx) {
+                m.context_before = context(start..idx);
+                m.context_after = context(idx + 1..end + 1);
+            }
+        }
+    }
+
+    /// Spread the file's lines across the matches' context, see
+    /// [`Searcher::with_passthru`]
+    fn attach_passthru(&self, path: &Path, matches: &mut [MatchResult]) {
+        let content = match self.fs.read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                tracing::warn!("Failed to read {} for --passthru: {}", path.display(), err);
+                return;
+            }
+        };
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let context = |range: std::ops::Range<usize>| {
+            range
+                .filter_map(|idx| {
+                    Some(ContextLine {
+                        line_number: idx + 1,
+                        content: (*lines.get(idx)?).to_string(),
+                    })
+                })
+                .collect()
+        };
+
+        let mut start = 0;
+        let mut iter = matches.iter_mut().peekable();
+        while let Some(m) = iter.next() {
+            let idx = m.line_number.saturating_sub(1);
+            // Context runs up to the next match's line, or to the end of the file
+            let end = iter
+                .peek()
+                .map_or(lines.len(), |next| next.line_number.saturating_sub(1))
+                .max(idx + 1);
+            m.context_before = context(start..idx);
+            m.context_after = context(idx + 1..end);
+            start = end;
+        }
+    }
+
+    /// Collect the walked files, newest first, truncated to `n`
+    fn newest_files(&self, n: usize) -> Vec<PathBuf> {
+        let mut files: Vec<_> = self
+            .walker
+            .files()
+            .map(|path| {
+                let modified = self.fs.metadata(&path).ok().and_then(|m| m.modified);
+                (modified, path)
+            })
+            .collect();
+
+        // `None` compares less than any `Some`, so a descending sort puts
+        // files with unknown mtimes last.
+        files.sort_by(|(a, _), (b, _)| b.cmp(a));
+        files.truncate(n);
+        files.into_iter().map(|(_, path)| path).collect()
+    }
+
+    /// Yield files until their sizes add up to more than `budget` bytes
+    ///
+    /// The walk stops there, unless [`Searcher::with_stats`] is on: then the
+    /// files after that are still walked, but only to count them as
+    /// [`SearchStats::size_skipped`].
+    fn within_budget<'a>(
+        &'a self,
+        files: impl Iterator<Item = PathBuf> + 'a,
+        budget: u64,
+    ) -> Box<dyn Iterator<Item = PathBuf> + 'a> {
+        let mut spent = 0u64;
+        let mut warned = false;
+        let mut within = move |path: &PathBuf| {
+            if spent > budget {
+                if !warned {
+                    warned = true;
+                    tracing::warn!(
+                        "Stopped searching after {} bytes: the budget of {} bytes is spent",
+                        spent,
+                        budget
+                    );
+                }
+                return false;
+            }
+            let len = self.fs.metadata(path).map_or(0, |m| m.len);
+            spent = spent.saturating_add(len);
+            true
+        };
+        if !self.collect_stats {
+            return Box::new(files.take_while(move |path| within(path)));
+        }
+        Box::new(files.filter(move |path| {
+            let keep = within(path);
+            if !keep {
+                self.record(|stats| stats.size_skipped += 1);
+            }
+            keep
+        }))
+    }
+
+    /// Update the running [`SearchStats`]
+    fn record(&self, update: impl FnOnce(&mut SearchStats)) {
+        update(
+            &mut self
+                .stats
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+    }
+
+    /// What the searches run so far looked at, and why files were skipped
+    ///
+    /// Counts accumulate over every search this searcher runs, and a lazy search
+    /// only counts the files it has reached so far. Binary and over-budget files
+    /// are only told apart with [`Searcher::with_stats`] on.
+    pub fn stats(&self) -> SearchStats {
+        *self
+            .stats
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn skip_invalid_utf8(&self, path: &Path) {
+        self.record(|stats| stats.invalid_utf8_skipped += 1);
+        tracing::warn!(
+            "Skipping {}: not valid UTF-8 (use --encoding lossy or binary to search it anyway)",
+            path.display()
+        );
+    }
+
+    /// Search a file that isn't valid UTF-8 as the matcher's
+    /// [`Matcher::non_utf8_strategy`] says, or `None` if it's skipped
+    ///
+    /// By default such a file is skipped with a warning rather than failing the
+    /// search.
+    fn search_non_utf8(&self, path: &Path) -> Result<Option<Vec<MatchInfo>>, FilesystemError> {
+        match self.matcher.non_utf8_strategy() {
+            NonUtf8Strategy::Skip => {
+                self.skip_invalid_utf8(path);
+                Ok(None)
+            }
+            NonUtf8Strategy::Lossy => {
+                let bytes = self.fs.read_bytes(path)?;
+                Ok(Some(
+                    self.matcher
+                        .search_in_content(&String::from_utf8_lossy(&bytes)),
+                ))
+            }
+            NonUtf8Strategy::Binary => Ok(Some(
+                self.matcher.search_in_bytes(&self.fs.read_bytes(path)?),
+            )),
         }
     }
 
@@ -45,8 +435,15 @@ where
     /// Returns Ok with matches if successful, or Err with a `SearchError` if the file
     /// couldn't be searched.
     fn search_file(&self, path: &Path) -> Result<Vec<MatchResult>, SearchError> {
-        use crate::filesystem::FilesystemError;
+        let result = self.search_file_uncounted(path);
+        if result.is_err() {
+            self.record(|stats| stats.errored += 1);
+        }
+        result
+    }
 
+    /// [`Searcher::search_file`], counting skipped and searched files but not errors
+    fn search_file_uncounted(&self, path: &Path) -> Result<Vec<MatchResult>, SearchError> {
         // Check if file exists
         if !self.fs.exists(path) {
             return Err(SearchError::FileReadError {
@@ -75,13 +472,29 @@ where
         {
             None => {
                 // Read file contents
-                let content = self.fs.read_to_string(path).map_err(|source| {
-                    tracing::warn!("Failed to read {}: {}", path.display(), source);
-                    source
-                })?;
+                match self.fs.read_to_string(path) {
+                    // Search for matches
+                    Ok(content) => self.matcher.search_in_content(&content),
+                    Err(FilesystemError::InvalidUtf8 { .. }) => {
+                        let Some(matches) = self.search_non_utf8(path)? else {
+                            return Ok(Vec::new());
+                        };
+                        matches
+                    }
+                    Err(source) => {
+                        tracing::warn!("Failed to read {}: {}", path.display(), source);
+                        return Err(source.into());
+                    }
+                }
+            }
 
-                // Search for matches
-                self.matcher.search_in_content(&content)
+            Some(Err(MatcherError::SearchError { source }))
+                if source.kind() == std::io::ErrorKind::InvalidData =>
+            {
+                let Some(matches) = self.search_non_utf8(path)? else {
+                    return Ok(Vec::new());
+                };
+                matches
             }
 
             Some(matches) => matches.map_err(|source| {
@@ -90,15 +503,77 @@ where
             })?,
         };
 
+        // The matcher stops quietly at a NUL byte, so check whether a file without
+        // matches was binary to count it as skipped rather than searched
+        if match_infos.is_empty() && self.collect_stats && self.fs.is_binary(path).unwrap_or(false)
+        {
+            self.record(|stats| stats.binary_skipped += 1);
+            return Ok(Vec::new());
+        }
+        self.record(|stats| stats.searched += 1);
+
         // Convert to MatchResult
-        let matches: Vec<MatchResult> = match_infos
+        let mut matches: Vec<MatchResult> = match_infos
             .into_iter()
             .map(|info: MatchInfo| MatchResult::from_match_info(info, path.to_path_buf()))
             .collect();
 
+        // One lookup per file, shared by all of its matches
+        if !matches.is_empty() {
+            let metadata = self.fs.metadata(path).ok().map(Arc::new);
+            for m in &mut matches {
+                m.file_metadata.clone_from(&metadata);
+            }
+        }
+
+        if self.passthru && !matches.is_empty() {
+            self.attach_passthru(path, &mut matches);
+        } else if self.block_context && !matches.is_empty() {
+            self.attach_block_context(path, &mut matches);
+        }
+
+        if let Some(header) = &self.function_header
+            && !matches.is_empty()
+        {
+            self.attach_function_headers(header, path, &mut matches);
+        }
+
+        if let Some(transformer) = &self.transformer {
+            matches = matches
+                .into_iter()
+                .filter_map(|m| transformer(m).transpose())
+                .collect::<Result<_, _>>()?;
+        }
+
         Ok(matches)
     }
 
+    /// Search a single file, bypassing the walker
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read or searched.
+    #[allow(dead_code)]
+    pub fn search_file_public(&self, path: &Path) -> Result<Vec<MatchResult>, SearchError> {
+        self.search_file(path)
+    }
+
+    /// Search exactly `paths`, bypassing the walker
+    ///
+    /// Useful when the file list is already known, e.g. re-searching the files
+    /// of a previous result. Every file is searched in order, and all matches are
+    /// collected into one result. `with_newest` doesn't apply here.
+    ///
+    /// # Errors
+    /// Returns the first error encountered.
+    #[allow(dead_code)]
+    pub fn search_files(&self, paths: &[PathBuf]) -> Result<SearchResult, SearchError> {
+        let mut result = SearchResult::new();
+        for path in paths {
+            result.matches.extend(self.search_file(path)?);
+        }
+        Ok(result)
+    }
+
     /// Search all files and return results
     ///
     /// This is the main entry point for searching. It walks all files,
@@ -107,14 +582,214 @@ where
     /// Returns `Ok(SearchResult)` with all matches if successful, or `Err(SearchError)`
     /// if any errors occurred. If multiple files had errors, returns `SearchError::Multiple`.
     pub fn search_all(&self) -> impl Iterator<Item = Result<SearchResult, SearchError>> + '_ {
-        self.walker
-            .files()
+        self.search_all_with_paths()
+            .map(|(_, result)| result.map(|matches| SearchResult { matches }))
+    }
+
+    /// Search all files, keeping going past files that fail
+    ///
+    /// Unlike [`Searcher::search_all`], which leaves it to the caller whether an
+    /// error ends the search, this always searches every file: the matches from
+    /// the files that could be searched are returned together with every error
+    /// encountered along the way, in walk order.
+    #[allow(dead_code)]
+    pub fn search_all_collecting_errors(&self) -> (SearchResult, Vec<SearchError>) {
+        let mut result = SearchResult::new();
+        let mut errors = Vec::new();
+        for (_, found) in self.search_all_with_paths() {
+            match found {
+                Ok(matches) => result.matches.extend(matches),
+                Err(err) => errors.push(err),
+            }
+        }
+        (result, errors)
+    }
+
+    /// Like [`Searcher::search_all`], but pairs each result with the file it came
+    /// from, so a per-file error can be reported against its path.
+    ///
+    /// Files without matches are skipped.
+    pub fn search_all_with_paths(
+        &self,
+    ) -> impl Iterator<Item = (PathBuf, Result<Vec<MatchResult>, SearchError>)> + '_ {
+        self.walked_files()
             .filter_map(move |path| match self.search_file(&path) {
-                Err(err) => Some(Err(err)),
                 Ok(matches) if matches.is_empty() => None,
-                Ok(matches) => Some(Ok(SearchResult { matches })),
+                result => Some((path, result)),
             })
     }
+
+    /// Like [`Searcher::search_all_with_paths`], but collected, searching the
+    /// walked files on a pool of `threads` worker threads (`0` for one per
+    /// logical CPU; `1` searches on the calling thread, without a pool)
+    ///
+    /// The walk runs on the calling thread and hands each file to the pool as
+    /// it's found. Results come back in walk order whichever thread searched
+    /// each file, so the thread count never changes the output. With
+    /// `fail_fast`, the walk stops at the first file that fails and the results
+    /// end with an error; files walked after it may not have been searched.
+    pub fn search_all_parallel(&self, threads: usize, fail_fast: bool) -> Vec<FileResult> {
+        if threads == 1 {
+            let mut results = Vec::new();
+            for (path, result) in self.search_all_with_paths() {
+                let failed = result.is_err();
+                results.push((path, result));
+                if fail_fast && failed {
+                    break;
+                }
+            }
+            return results;
+        }
+        let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool,
+            Err(err) => {
+                tracing::warn!("Searching on the current thread: {}", err);
+                return self.search_all_parallel(1, fail_fast);
+            }
+        };
+
+        let found: Mutex<Vec<(usize, FileResult)>> = Mutex::default();
+        let failed = AtomicBool::new(false);
+        pool.in_place_scope(|scope| {
+            for (idx, path) in self.walked_files().enumerate() {
+                if failed.load(Ordering::Relaxed) {
+                    break;
+                }
+                let (found, failed) = (&found, &failed);
+                scope.spawn(move |_| {
+                    if failed.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let result = match self.search_file(&path) {
+                        Ok(matches) if matches.is_empty() => return,
+                        result => result,
+                    };
+                    if fail_fast && result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    found
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .push((idx, (path, result)));
+                });
+            }
+        });
+
+        let mut found = found
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        found.sort_unstable_by_key(|(idx, _)| *idx);
+        let mut results = Vec::new();
+        for (_, (path, result)) in found {
+            let failed = result.is_err();
+            results.push((path, result));
+            if fail_fast && failed {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Yield each file whose name matches `name` or whose contents match the
+    /// pattern, once
+    ///
+    /// A file whose name matches isn't read at all. With `search_contents` off,
+    /// only names are checked; with `name` unset, only contents are.
+    pub fn matching_files<'a>(
+        &'a self,
+        name: Option<&'a M>,
+        search_contents: bool,
+    ) -> impl Iterator<Item = Result<PathBuf, SearchError>> + 'a {
+        let mut seen = std::collections::HashSet::new();
+        self.walked_files().filter_map(move |path| {
+            if seen.contains(&path) {
+                return None;
+            }
+            let name_matches = name.is_some_and(|name| {
+                path.file_name()
+                    .is_some_and(|file_name| name.is_match(&file_name.to_string_lossy()))
+            });
+            let found = if name_matches {
+                Ok(true)
+            } else if search_contents {
+                self.search_file(&path).map(|matches| !matches.is_empty())
+            } else {
+                Ok(false)
+            };
+            match found {
+                Ok(false) => None,
+                Ok(true) => {
+                    seen.insert(path.clone());
+                    Some(Ok(path))
+                }
+                Err(err) => Some(Err(err)),
+            }
+        })
+    }
+
+    /// The files to search: the walker's, narrowed by `with_newest` and
+    /// `with_max_total_bytes`
+    fn walked_files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        let mut files: Box<dyn Iterator<Item = PathBuf> + '_> = match self.newest {
+            None => self.walker.files(),
+            Some(n) => Box::new(self.newest_files(n).into_iter()),
+        };
+        if self.dedup_files {
+            let mut seen = std::collections::HashSet::new();
+            files = Box::new(files.filter(move |path| match self.fs.canonicalize(path) {
+                Ok(canonical) => seen.insert(canonical),
+                Err(_) => true,
+            }));
+        }
+        if let Some(budget) = self.max_total_bytes {
+            files = Box::new(self.within_budget(files, budget));
+        }
+        files
+    }
+}
+
+/// Find the innermost brace-delimited block around `lines[idx]`
+///
+/// Returns the 0-based lines holding its opening `{` and matching `}`. A `{`
+/// left open on the match line itself counts, so a match on a function's
+/// signature yields that function's body.
+fn enclosing_block(lines: &[&str], idx: usize) -> Option<(usize, usize)> {
+    // Scan backward from the end of the match line for an unclosed `{`
+    let mut depth = 0usize;
+    let mut open = None;
+    'backward: for line_idx in (0..=idx.min(lines.len().checked_sub(1)?)).rev() {
+        for (col, ch) in lines[line_idx].char_indices().rev() {
+            match ch {
+                '}' => depth += 1,
+                '{' if depth == 0 => {
+                    open = Some((line_idx, col));
+                    break 'backward;
+                }
+                '{' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    let (start, col) = open?;
+
+    // Then forward from that `{` to the `}` that balances it
+    let mut depth = 0usize;
+    for (line_idx, line) in lines.iter().enumerate().skip(start) {
+        let from = if line_idx == start { col } else { 0 };
+        for ch in line[from..].chars() {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((start, line_idx));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
 }
 
 #[cfg(test)]
@@ -124,7 +799,7 @@ mod tests {
     use crate::matcher::regex::GrepMatcher;
     use crate::matcher::stub::StubMatcher;
     use crate::walker::simple::SimpleWalker;
-    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
 
     /// Test Searcher with all test doubles (solitary unit test)
     #[test]
@@ -141,9 +816,12 @@ mod tests {
             line_num: 2,
             byte_offset: 7,
             line_match: None,
+            column_start: 0,
+            column_end: 0,
             line_content: "TARGET line\n".to_string(),
             previous_lines: String::new(),
             next_lines: String::new(),
+            spans: Vec::new(),
         });
 
         // Setup SimpleWalker
@@ -232,6 +910,65 @@ mod tests {
         // Binary file is silently skipped by GrepMatcher (no error, no matches)
     }
 
+    #[test]
+    fn test_searcher_stats() {
+        let fs = MemoryFS::new();
+        let files: Vec<_> = ["/hit.txt", "/miss.txt", "/bin", "/latin1.txt", "/gone.txt"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        fs.add_file(&files[0], "match\n").unwrap();
+        fs.add_file(&files[1], "nothing\n").unwrap();
+        fs.add_file(&files[2], "match\0data").unwrap();
+        fs.add_file_bytes(&files[3], b"caf\xe9 match\n").unwrap();
+
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let searcher =
+            Searcher::new(fs.clone(), matcher, SimpleWalker::new(files)).with_stats(true);
+        let _ = searcher.search_all().count();
+        assert_eq!(
+            searcher.stats(),
+            SearchStats {
+                searched: 2,
+                binary_skipped: 1,
+                invalid_utf8_skipped: 1,
+                size_skipped: 0,
+                errored: 1,
+            }
+        );
+
+        // The file that spends the budget is searched, the rest are counted
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let walker = SimpleWalker::new(vec![
+            PathBuf::from("/hit.txt"),
+            PathBuf::from("/miss.txt"),
+            PathBuf::from("/hit.txt"),
+        ]);
+        let searcher = Searcher::new(fs.clone(), matcher, walker)
+            .with_max_total_bytes(1)
+            .with_stats(true);
+        let _ = searcher.search_all().count();
+        let stats = searcher.stats();
+        assert_eq!((stats.searched, stats.size_skipped), (1, 2));
+
+        // Without stats the walk stops at the budget
+        struct Counting(Arc<std::sync::atomic::AtomicUsize>);
+        impl Walker for Counting {
+            fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+                Box::new(std::iter::repeat_with(|| {
+                    self.0.fetch_add(1, Ordering::SeqCst);
+                    PathBuf::from("/hit.txt")
+                }))
+            }
+        }
+        let walked = Arc::default();
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let searcher =
+            Searcher::new(fs, matcher, Counting(Arc::clone(&walked))).with_max_total_bytes(1);
+        assert_eq!(searcher.search_all().count(), 1);
+        assert_eq!(walked.load(Ordering::SeqCst), 2);
+    }
+
     /// Test Searcher handles nonexistent files
     #[test]
     fn test_searcher_handles_nonexistent_files() {
@@ -255,6 +992,370 @@ mod tests {
         }
     }
 
+    /// Test Searcher reports a file that exists but can't be read
+    #[test]
+    fn test_searcher_reports_read_failure() {
+        let fs = MemoryFS::new();
+        let broken = PathBuf::from("/test/broken.txt");
+        let ok = PathBuf::from("/test/ok.txt");
+        fs.add_file(&broken, "match").unwrap();
+        fs.add_file(&ok, "match").unwrap();
+        fs.fail_read(&broken);
+
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let walker = SimpleWalker::new(vec![broken, ok.clone()]);
+
+        let searcher = Searcher::new(fs, matcher, walker);
+        let results: Vec<_> = searcher.search_all().collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            results[0],
+            Err(SearchError::FileReadError {
+                source: crate::filesystem::FilesystemError::ReadError { .. }
+            })
+        ));
+        assert_eq!(results[1].as_ref().unwrap().matches[0].file_path, ok);
+    }
+
+    /// Test one missing file doesn't lose the other files' matches
+    #[test]
+    fn test_searcher_collecting_errors() {
+        let fs = MemoryFS::new();
+        let first = PathBuf::from("/test/first.txt");
+        let missing = PathBuf::from("/test/missing.txt");
+        let last = PathBuf::from("/test/last.txt");
+        fs.add_file(&first, "match one\n").unwrap();
+        fs.add_file(&last, "no\nmatch two\n").unwrap();
+
+        let matcher = GrepMatcher::compile("match").unwrap().with_context(0);
+        let walker = SimpleWalker::new(vec![first.clone(), missing.clone(), last.clone()]);
+
+        let searcher = Searcher::new(fs, matcher, walker);
+        let (result, errors) = searcher.search_all_collecting_errors();
+
+        let found: Vec<_> = result
+            .matches
+            .iter()
+            .map(|m| (m.file_path.clone(), m.line_number))
+            .collect();
+        assert_eq!(found, vec![(first, 1), (last, 2)]);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            SearchError::FileReadError {
+                source: crate::filesystem::FilesystemError::FileNotFound { path }
+            } if *path == missing
+        ));
+    }
+
+    /// Test every match in a file shares one metadata, and a file without
+    /// metadata still yields matches
+    #[test]
+    fn test_searcher_attaches_shared_file_metadata() {
+        let fs = MemoryFS::new();
+        let with = PathBuf::from("/test/with.txt");
+        let without = PathBuf::from("/test/without.txt");
+        fs.add_file(&with, "match\nno\nmatch\n").unwrap();
+        fs.add_file(&without, "match\n").unwrap();
+        fs.fail_metadata(&without);
+
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let walker = SimpleWalker::new(vec![with.clone(), without.clone()]);
+        let searcher = Searcher::new(fs, matcher, walker);
+
+        let found = searcher.search_file_public(&with).unwrap();
+        assert_eq!(found.len(), 2);
+        let first = found[0].file_metadata.as_ref().unwrap();
+        let second = found[1].file_metadata.as_ref().unwrap();
+        assert!(Arc::ptr_eq(first, second));
+        assert_eq!(first.len, 15);
+
+        let found = searcher.search_file_public(&without).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].file_metadata.is_none());
+    }
+
+    /// Test overlapping roots report each file once with dedup on
+    #[test]
+    fn test_searcher_dedup_files() {
+        let fs = MemoryFS::canonical();
+        fs.add_file(Path::new("/src/lib/a.rs"), "TARGET\n").unwrap();
+        fs.add_file(Path::new("/src/b.rs"), "TARGET\n").unwrap();
+
+        // Roots `/src` and `/src/lib`, one also reached through `..`
+        let walked = vec![
+            PathBuf::from("/src/b.rs"),
+            PathBuf::from("/src/lib/a.rs"),
+            PathBuf::from("/src/lib/a.rs"),
+            PathBuf::from("/src/lib/../b.rs"),
+        ];
+        let matcher = || GrepMatcher::compile("TARGET").unwrap();
+        let searcher = Searcher::new(fs.clone(), matcher(), walked.clone());
+        let found = |searcher: &Searcher<MemoryFS, GrepMatcher, Vec<PathBuf>>| {
+            searcher
+                .search_all()
+                .flat_map(|result| result.unwrap().matches)
+                .map(|m| m.file_path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found(&searcher).len(), 4);
+
+        let searcher = Searcher::new(fs, matcher(), walked).with_dedup_files(true);
+        assert_eq!(
+            found(&searcher),
+            vec![PathBuf::from("/src/b.rs"), PathBuf::from("/src/lib/a.rs")]
+        );
+    }
+
+    #[test]
+    fn test_searcher_parallel_matches_regardless_of_threads() {
+        let fs = MemoryFS::new();
+        let mut walked = Vec::new();
+        for i in 0..32 {
+            let path = PathBuf::from(format!("/src/file{i}.rs"));
+            let content = (0..i % 5)
+                .map(|n| format!("x\nTARGET {n}\n"))
+                .collect::<String>();
+            fs.add_file(&path, &content).unwrap();
+            walked.push(path);
+        }
+        walked.insert(16, PathBuf::from("/src/missing.rs"));
+
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(1);
+        let searcher = Searcher::new(fs, matcher, walked);
+        let found = |threads, fail_fast| {
+            searcher
+                .search_all_parallel(threads, fail_fast)
+                .into_iter()
+                .map(|(path, result)| (path, result.map_err(|err| err.to_string())))
+                .collect::<Vec<_>>()
+        };
+
+        let sequential: Vec<_> = searcher
+            .search_all_with_paths()
+            .map(|(path, result)| (path, result.map_err(|err| err.to_string())))
+            .collect();
+        assert_eq!(found(1, false), sequential);
+        assert_eq!(found(4, false), sequential);
+
+        // Failing fast ends the results at the missing file
+        let failed = sequential.iter().position(|(_, r)| r.is_err()).unwrap();
+        assert_eq!(found(1, true), sequential[..=failed]);
+        let fast = found(4, true);
+        assert_eq!(fast.last(), sequential.get(failed));
+        assert!(
+            fast.iter()
+                .all(|found| sequential[..=failed].contains(found))
+        );
+    }
+
+    /// Test a transformer can drop matches by file and rewrite the rest
+    #[test]
+    fn test_searcher_match_transformer() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let fs = MemoryFS::new();
+        let keep = PathBuf::from("/src/keep.rs");
+        let generated = PathBuf::from("/src/generated.rs");
+        fs.add_file(&keep, "TARGET\nTARGET\n").unwrap();
+        fs.add_file(&generated, "TARGET\n").unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+        let walker = SimpleWalker::new(vec![keep.clone(), generated]);
+        let searcher = Searcher::new(fs, matcher, walker).with_match_transformer(move |mut m| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if m.file_path.ends_with("generated.rs") {
+                return None;
+            }
+            m.line_content = m.line_content.to_lowercase();
+            Some(m)
+        });
+
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        // Called for all three matches; the generated file's match is gone
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].matches.len(), 2);
+        assert!(results[0].matches.iter().all(|m| m.file_path == keep));
+        assert!(
+            results[0]
+                .matches
+                .iter()
+                .all(|m| m.line_content == "target\n")
+        );
+    }
+
+    #[test]
+    fn test_searcher_with_map_drops_even_lines() {
+        let fs = MemoryFS::new();
+        let file = PathBuf::from("/src/lib.rs");
+        fs.add_file(&file, "TARGET\nTARGET\nTARGET\nTARGET\nTARGET\n")
+            .unwrap();
+
+        let mut seen = 0;
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+        let searcher = Searcher::new(fs, matcher, vec![file])
+            .with_match_transformer(|m| (m.line_number != 5).then_some(m))
+            .with_map(move |mut m| {
+                seen += 1;
+                m.line_content = format!("{seen}: {}", m.line_content);
+                (m.line_number % 2 == 1).then_some(m)
+            });
+
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let kept: Vec<_> = results[0]
+            .matches
+            .iter()
+            .map(|m| (m.line_number, m.line_content.as_str()))
+            .collect();
+        // Line 5 never reaches the map, which counts the four it does see
+        assert_eq!(kept, vec![(1, "1: TARGET\n"), (3, "3: TARGET\n")]);
+    }
+
+    /// Test `search_all_with_paths` ties each error to its file and keeps going
+    #[test]
+    fn test_searcher_search_all_with_paths() {
+        let fs = MemoryFS::new();
+        let broken = PathBuf::from("/test/broken.txt");
+        let empty = PathBuf::from("/test/empty.txt");
+        let ok = PathBuf::from("/test/ok.txt");
+        fs.add_file(&broken, "match").unwrap();
+        fs.add_file(&empty, "nothing here").unwrap();
+        fs.add_file(&ok, "match").unwrap();
+        fs.fail_read(&broken);
+
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let walker = SimpleWalker::new(vec![broken.clone(), empty, ok.clone()]);
+
+        let searcher = Searcher::new(fs, matcher, walker);
+        let results: Vec<_> = searcher.search_all_with_paths().collect();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, broken);
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].0, ok);
+        assert_eq!(results[1].1.as_ref().unwrap().len(), 1);
+    }
+
+    /// Test `search_files` ignores the walker and searches the given paths
+    #[test]
+    fn test_searcher_search_files() {
+        let fs = MemoryFS::new();
+        let a = PathBuf::from("/a.txt");
+        let b = PathBuf::from("/b.txt");
+        let c = PathBuf::from("/c.txt");
+        fs.add_file(&a, "match a\n").unwrap();
+        fs.add_file(&b, "nothing\n").unwrap();
+        fs.add_file(&c, "match c\n").unwrap();
+
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let walker = SimpleWalker::new(vec![a.clone()]);
+        let searcher = Searcher::new(fs, matcher, walker);
+
+        let result = searcher.search_files(&[b.clone(), c.clone()]).unwrap();
+        let files: Vec<_> = result.matches.iter().map(|m| &m.file_path).collect();
+        assert_eq!(files, vec![&c]);
+
+        assert_eq!(searcher.search_file_public(&a).unwrap().len(), 1);
+        assert!(searcher.search_file_public(&b).unwrap().is_empty());
+        assert!(
+            searcher
+                .search_files(&[PathBuf::from("/missing.txt")])
+                .is_err()
+        );
+    }
+
+    /// Test Searcher skips a file that isn't valid UTF-8 instead of failing
+    #[test]
+    fn test_searcher_skips_invalid_utf8_file() {
+        let fs = MemoryFS::new();
+        let latin1 = PathBuf::from("/test/latin1.txt");
+        let utf8 = PathBuf::from("/test/utf8.txt");
+        fs.add_file_bytes(&latin1, b"caf\xe9 match\n").unwrap();
+        fs.add_file(&utf8, "café match\n").unwrap();
+
+        let matcher = GrepMatcher::compile("match").unwrap();
+        let walker = SimpleWalker::new(vec![latin1.clone(), utf8.clone()]);
+
+        let searcher = Searcher::new(fs.clone(), matcher, walker);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let files: Vec<_> = results
+            .iter()
+            .flat_map(|r| &r.matches)
+            .map(|m| &m.file_path)
+            .collect();
+        assert_eq!(files, vec![&utf8]);
+
+        // With lossy decoding the invalid file is searched too
+        let matcher = GrepMatcher::compile("match")
+            .unwrap()
+            .with_non_utf8_strategy(NonUtf8Strategy::Lossy);
+        let walker = SimpleWalker::new(vec![latin1.clone(), utf8]);
+        let searcher = Searcher::new(fs, matcher, walker);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].matches[0].file_path, latin1);
+        assert_eq!(results[0].matches[0].line_content, "caf\u{FFFD} match\n");
+    }
+
+    /// Test each `NonUtf8Strategy` the matcher carries
+    #[test]
+    fn test_searcher_non_utf8_strategies() {
+        let fs = MemoryFS::new();
+        let file = PathBuf::from("/test/mixed.bin");
+        fs.add_file_bytes(&file, b"head\nfoo\xff\xfebar\ntail\n")
+            .unwrap();
+
+        let search = |pattern: &str, strategy| {
+            let matcher = GrepMatcher::compile(pattern)
+                .unwrap()
+                .with_non_utf8_strategy(strategy);
+            let searcher = Searcher::new(fs.clone(), matcher, vec![file.clone()]);
+            let (result, errors) = searcher.search_all_collecting_errors();
+            assert!(errors.is_empty());
+            result
+                .matches
+                .into_iter()
+                .map(|m| m.line_content)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(search("bar", NonUtf8Strategy::Skip).is_empty());
+
+        // Each invalid byte becomes one U+FFFD, which `.` matches
+        assert_eq!(
+            search("foo..bar", NonUtf8Strategy::Lossy),
+            vec!["foo\u{FFFD}\u{FFFD}bar\n"]
+        );
+        assert_eq!(
+            search("foo\u{FFFD}+bar", NonUtf8Strategy::Lossy),
+            vec!["foo\u{FFFD}\u{FFFD}bar\n"]
+        );
+
+        assert_eq!(
+            search("bar", NonUtf8Strategy::Binary),
+            vec!["foo\\xFF\\xFEbar\n"]
+        );
+    }
+
     /// Test Searcher with no matches
     #[test]
     fn test_searcher_no_matches() {
@@ -274,6 +1375,40 @@ mod tests {
         assert_eq!(results.len(), 0);
     }
 
+    #[test]
+    fn test_matching_files_by_name_or_contents() {
+        let fs = MemoryFS::new();
+        let files = ["/src/parser.rs", "/src/lexer.rs", "/src/ast.rs", "/README"];
+        fs.add_file(Path::new(files[0]), "fn parse() {}\n").unwrap();
+        fs.add_file(Path::new(files[1]), "// feeds the parser\n")
+            .unwrap();
+        fs.add_file(Path::new(files[2]), "struct Node;\n").unwrap();
+        fs.add_file(Path::new(files[3]), "see the parser\n")
+            .unwrap();
+        // Listing a file twice still reports it once
+        let walker: Vec<PathBuf> = files.iter().chain(&files[..1]).map(PathBuf::from).collect();
+
+        let searcher = Searcher::new(fs, GrepMatcher::compile("parser").unwrap(), walker);
+        let name = GrepMatcher::compile(r"^(parser|ast)\.rs$").unwrap();
+        let found = |name: Option<&GrepMatcher>, contents: bool| -> Vec<PathBuf> {
+            searcher
+                .matching_files(name, contents)
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        let paths = |paths: &[&str]| -> Vec<PathBuf> { paths.iter().map(PathBuf::from).collect() };
+
+        assert_eq!(
+            found(Some(&name), false),
+            paths(&["/src/parser.rs", "/src/ast.rs"])
+        );
+        assert_eq!(found(None, true), paths(&["/src/lexer.rs", "/README"]));
+        assert_eq!(
+            found(Some(&name), true),
+            paths(&["/src/parser.rs", "/src/lexer.rs", "/src/ast.rs", "/README"])
+        );
+    }
+
     /// Test Searcher with context extraction (Phase 2)
     #[test]
     fn test_searcher_with_context() {
@@ -314,4 +1449,247 @@ mod tests {
         assert_eq!(m.context_after[1].line_number, 5);
         assert_eq!(m.context_after[1].content, "line 5\n");
     }
+
+    #[test]
+    fn test_searcher_context_when_match_is_last_line_without_newline() {
+        let fs = MemoryFS::new();
+        let file = PathBuf::from("/test/file.txt");
+        fs.add_file(&file, "a\nb\nMATCH").unwrap();
+
+        let matcher = GrepMatcher::compile("MATCH").unwrap().with_context(2);
+        let walker = SimpleWalker::new(vec![file.clone()]);
+
+        let searcher = Searcher::new(fs, matcher, walker);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let m = &results[0].matches[0];
+        assert_eq!(m.line_number, 3);
+        assert_eq!(m.line_content, "MATCH");
+        let before: Vec<_> = m
+            .context_before
+            .iter()
+            .map(|ctx| (ctx.line_number, ctx.content.as_str()))
+            .collect();
+        assert_eq!(before, vec![(1, "a\n"), (2, "b\n")]);
+        assert!(m.context_after.is_empty());
+    }
+
+    /// Test `with_newest` only searches the most recently modified files
+    #[test]
+    fn test_searcher_with_newest() {
+        let fs = MemoryFS::new();
+        let old = PathBuf::from("/logs/old.log");
+        let mid = PathBuf::from("/logs/mid.log");
+        let new = PathBuf::from("/logs/new.log");
+        for (secs, path) in [(10, &old), (20, &mid), (30, &new)] {
+            fs.add_file(path, "ERROR something\n").unwrap();
+            fs.set_modified(path, SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        }
+
+        let matcher = GrepMatcher::compile("ERROR").unwrap();
+        let walker = SimpleWalker::new(vec![old, mid.clone(), new.clone()]);
+
+        let searcher = Searcher::new(fs, matcher, walker).with_newest(2);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let files: Vec<_> = results
+            .iter()
+            .flat_map(|r| &r.matches)
+            .map(|m| m.file_path.clone())
+            .collect();
+
+        assert_eq!(files, vec![new, mid]);
+    }
+
+    /// Test `with_max_total_bytes` stops the walk once the budget is spent
+    #[test]
+    fn test_searcher_max_total_bytes() {
+        let fs = MemoryFS::new();
+        let files: Vec<PathBuf> = (1..=4)
+            .map(|n| PathBuf::from(format!("/{n}.txt")))
+            .collect();
+        for file in &files {
+            // 7 bytes each
+            fs.add_file(file, "TARGET\n").unwrap();
+        }
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+
+        // 7 + 7 = 14 > 10: the second file crosses the budget, so the walk ends there
+        let searcher = Searcher::new(fs.clone(), matcher, SimpleWalker::new(files.clone()))
+            .with_max_total_bytes(10);
+        let searched: Vec<_> = searcher
+            .search_all_with_paths()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(searched, files[..2]);
+
+        // A budget of 0 still searches the first file
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+        let searcher =
+            Searcher::new(fs, matcher, SimpleWalker::new(files.clone())).with_max_total_bytes(0);
+        assert_eq!(searcher.search_all().count(), 1);
+    }
+
+    /// Test `with_show_function` finds the enclosing Rust `fn` outside the context
+    #[test]
+    fn test_searcher_show_function_rust_fn() {
+        let fs = MemoryFS::new();
+        let file = PathBuf::from("/src/lib.rs");
+        let content = "\
+pub fn first() {}
+
+pub fn compute(x: u32) -> u32 {
+    let a = x + 1;
+    let b = a * 2;
+    let c = b - 3;
+    TARGET(c)
+}
+";
+        fs.add_file(&file, content).unwrap();
+
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(1);
+        let header = GrepMatcher::compile(r"^\s*(pub\s+)?fn\s+\w+").unwrap();
+        let walker = SimpleWalker::new(vec![file]);
+
+        let searcher = Searcher::new(fs, matcher, walker).with_show_function(header);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let m = &results[0].matches[0];
+        assert_eq!(
+            m.function_header,
+            Some(ContextLine {
+                line_number: 3,
+                content: "pub fn compute(x: u32) -> u32 {\n".to_string(),
+            })
+        );
+    }
+
+    /// Test `with_show_function` doesn't repeat a header already in the context
+    #[test]
+    fn test_searcher_show_function_header_in_context() {
+        let fs = MemoryFS::new();
+        let file = PathBuf::from("/src/lib.rs");
+        fs.add_file(&file, "fn main() {\n    TARGET;\n}\n").unwrap();
+
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(2);
+        let header = GrepMatcher::compile(r"^\s*fn\s+\w+").unwrap();
+        let walker = SimpleWalker::new(vec![file]);
+
+        let searcher = Searcher::new(fs, matcher, walker).with_show_function(header);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(results[0].matches[0].function_header, None);
+    }
+
+    /// Test `with_block_context` swaps the fixed context for the enclosing block
+    #[test]
+    fn test_searcher_block_context() {
+        let fs = MemoryFS::new();
+        let file = PathBuf::from("/src/lib.rs");
+        let content = "\
+fn first() {}
+
+fn compute(x: u32) -> u32 {
+    let a = x + 1;
+    if a > 2 {
+        return 0;
+    }
+    TARGET(a)
+}
+
+fn last() {}
+";
+        fs.add_file(&file, content).unwrap();
+
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(1);
+        let walker = SimpleWalker::new(vec![file]);
+
+        let searcher = Searcher::new(fs, matcher, walker).with_block_context(true);
+        let results: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let m = &results[0].matches[0];
+        let before: Vec<_> = m.context_before.iter().map(|c| c.line_number).collect();
+        let after: Vec<_> = m.context_after.iter().map(|c| c.line_number).collect();
+        assert_eq!(before, vec![3, 4, 5, 6, 7]);
+        assert_eq!(after, vec![9]);
+    }
+
+    #[test]
+    fn test_searcher_passthru() {
+        let fs = MemoryFS::new();
+        let one = PathBuf::from("/one.txt");
+        let two = PathBuf::from("/two.txt");
+        fs.add_file(&one, "a\nb\nTARGET\nd\ne\n").unwrap();
+        fs.add_file(&two, "TARGET\nb\nTARGET\nd\n").unwrap();
+
+        let matcher = GrepMatcher::compile("TARGET").unwrap().with_context(0);
+        let walker = SimpleWalker::new(vec![one.clone(), two]);
+        let searcher = Searcher::new(fs, matcher, walker).with_passthru(true);
+        let matches: Vec<_> = searcher
+            .search_all()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+            .into_iter()
+            .flat_map(|result| result.matches)
+            .collect();
+
+        let mut format = crate::format::Format::from_matches(&matches);
+        format.normalize();
+        let chunks: Vec<_> = format
+            .iter()
+            .map(|c| (c.start_line, c.num_lines, c.content.as_str()))
+            .collect();
+        assert_eq!(
+            chunks,
+            vec![
+                (1, 5, "a\nb\nTARGET\nd\ne\n"),
+                (1, 4, "TARGET\nb\nTARGET\nd\n"),
+            ]
+        );
+        assert_eq!(format.iter().next().unwrap().path, one);
+    }
+
+    #[test]
+    fn test_enclosing_block() {
+        let lines: Vec<&str> = "\
+fn main() {
+    let s = S { a: 1 };
+    if ok {
+        go();
+    } else {
+        stop();
+    }
+}
+top_level();
+fn broken() {
+"
+        .split_inclusive('\n')
+        .collect();
+
+        // Inline `{ ... }` pairs on the way out are skipped
+        assert_eq!(enclosing_block(&lines, 1), Some((0, 7)));
+        // A match on the signature takes the block it opens
+        assert_eq!(enclosing_block(&lines, 0), Some((0, 7)));
+        assert_eq!(enclosing_block(&lines, 3), Some((2, 4)));
+        // `} else {` opens a new block
+        assert_eq!(enclosing_block(&lines, 5), Some((4, 6)));
+        // Outside any block, or never closed
+        assert_eq!(enclosing_block(&lines, 8), None);
+        assert_eq!(enclosing_block(&lines, 9), None);
+    }
 }
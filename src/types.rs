@@ -3,16 +3,22 @@
 //! These types represent the pure data structures used throughout bulked.
 //! They have no dependencies on filesystem, network, or other I/O.
 
+use std::collections::HashMap;
+use std::fmt;
 use std::path::PathBuf;
+use std::sync::Arc;
 use thiserror::Error;
 
-use crate::filesystem::FilesystemError;
+use crate::filesystem::{FileMetadata, FilesystemError};
 use crate::matcher::{MatchInfo, MatcherError};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IngestInput {
     pub file_path: PathBuf,
+    /// 1-indexed line, or 0 when the location is given by `byte_offset`
     pub line_number: usize,
+    /// Byte offset into the file, for tools that report those instead of lines
+    pub byte_offset: Option<u64>,
     // TODO: add support for context messages
     // pub message: String,
 }
@@ -24,30 +30,45 @@ pub struct MatchResult {
     pub file_path: PathBuf,
     /// Line number (1-indexed) where the match occurred
     pub line_number: usize,
-    /// Content of the line containing the match
+    /// Content of the line containing the match (for a multiline match, every
+    /// line it spans, see [`MatchResult::line_count`])
     pub line_content: String,
+    /// How many file lines the match spans, counted before `search --replace`
+    /// rewrites `line_content`, so a replaced chunk still covers the original lines
+    pub match_lines: usize,
 
     /// Line match range (if any)
     /// the range is relative to the start of the line.
     pub line_match: Option<std::ops::Range<usize>>,
 
-    /// Byte offset of the match within the file
+    /// Byte offset within the file of the start of the matched line (0-based),
+    /// not of the match within it, like `grep -b`
     pub byte_offset: usize,
     /// Context lines before the match (added in Phase 2)
     pub context_before: Vec<ContextLine>,
     /// Context lines after the match (added in Phase 2)
     pub context_after: Vec<ContextLine>,
+    /// Nearest enclosing function header above the context window (`--show-function`)
+    pub function_header: Option<ContextLine>,
+    /// Size and modification time of the file, shared by every match in it
+    pub file_metadata: Option<Arc<FileMetadata>>,
 }
 
 impl MatchResult {
     #[must_use]
     pub fn from_match_info(match_info: MatchInfo, path: PathBuf) -> Self {
+        // A multiline match spans several lines, and its context follows the last
+        let match_lines = match_info.line_content.split_inclusive('\n').count().max(1);
         Self {
             file_path: path,
             line_number: match_info.line_num,
             line_match: match_info.line_match,
             line_content: match_info.line_content,
             byte_offset: match_info.byte_offset,
+            // Only the file's last line can lack a trailing newline, and that is
+            // either the match itself or the last after-context line. Counting
+            // both sides outward from the match keeps the numbering right either
+            // way, since a missing newline never merges two lines.
             context_before: {
                 let lines: Vec<&str> = match_info.previous_lines.split_inclusive('\n').collect();
                 let count = lines.len();
@@ -55,7 +76,7 @@ impl MatchResult {
                     .into_iter()
                     .enumerate()
                     .map(|(idx, line)| ContextLine {
-                        line_number: match_info.line_num - count + idx,
+                        line_number: match_info.line_num.saturating_sub(count - idx),
                         content: line.to_string(),
                     })
                     .collect()
@@ -66,13 +87,124 @@ impl MatchResult {
                     .split_inclusive('\n')
                     .enumerate()
                     .map(|(idx, line)| ContextLine {
-                        line_number: match_info.line_num + idx + 1,
+                        line_number: match_info.line_num + match_lines + idx,
                         content: line.to_string(),
                     })
                     .collect()
             },
+            function_header: None,
+            match_lines,
+            file_metadata: None,
         }
     }
+
+    /// Turn every `\r\n` in the line and its context into `\n`, for displays
+    /// that show `--crlf` matches as plain lines rather than editable chunks
+    pub fn strip_cr(&mut self) {
+        let contexts = self
+            .context_before
+            .iter_mut()
+            .chain(&mut self.context_after);
+        for text in std::iter::once(&mut self.line_content).chain(contexts.map(|c| &mut c.content))
+        {
+            if text.contains("\r\n") {
+                *text = text.replace("\r\n", "\n");
+            }
+        }
+    }
+
+    /// How many lines the match spans: 1, or more for a multiline match
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.match_lines
+    }
+
+    /// Whether `other` is in the same file, on this line or a neighboring one
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn is_adjacent_to(&self, other: &MatchResult) -> bool {
+        self.file_path == other.file_path && self.line_number.abs_diff(other.line_number) <= 1
+    }
+
+    /// 1-indexed column where the match starts, if the match span is known
+    ///
+    /// Columns count characters, like [`crate::matcher::MatchInfo::column_start`],
+    /// so they agree on non-ASCII lines. With `tab_width`, each leading tab on the
+    /// line counts as that many columns instead, which lines the column up with
+    /// how an editor shows tab-indented code. Tabs after the indentation still
+    /// count as one.
+    #[must_use]
+    pub fn column(&self, tab_width: Option<usize>) -> Option<usize> {
+        let start = self.line_match.as_ref()?.start;
+        let prefix = self.line_content.get(..start)?;
+        let tabs = match tab_width {
+            Some(width) => {
+                prefix.bytes().take_while(|&b| b == b'\t').count() * width.saturating_sub(1)
+            }
+            None => 0,
+        };
+        Some(prefix.chars().count() + tabs + 1)
+    }
+
+    /// The match's span as an LSP-style range, if the match span is known
+    ///
+    /// `line_number` is 1-indexed and `line_match` is a byte range into
+    /// `line_content`; the range is zero-indexed on both axes, with `character`
+    /// counted in UTF-16 code units as LSP requires (so `é` is 1 unit but `🦀` is 2).
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn to_range(&self) -> Option<TextRange> {
+        let range = self.line_match.as_ref()?;
+        let line = self.line_number.checked_sub(1)?;
+        let utf16_len = |end: usize| Some(self.line_content.get(..end)?.encode_utf16().count());
+
+        Some(TextRange {
+            start: TextPosition {
+                line,
+                character: utf16_len(range.start)?,
+            },
+            end: TextPosition {
+                line,
+                character: utf16_len(range.end)?,
+            },
+        })
+    }
+}
+
+/// A zero-indexed position in a file, LSP style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextPosition {
+    /// Line (0-indexed)
+    pub line: usize,
+    /// Column in UTF-16 code units (0-indexed)
+    pub character: usize,
+}
+
+/// A half-open range between two [`TextPosition`]s, like an LSP `Range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: TextPosition,
+    pub end: TextPosition,
+}
+
+/// Renders `path:line: content`, followed by the context lines indented below it.
+///
+/// This is a convenience for human debugging (e.g. `println!("{m}")`), not a
+/// stable format: use [`crate::format::Format`] for output other tools consume.
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.file_path.display(),
+            self.line_number,
+            self.line_content.trim_end_matches(['\r', '\n'])
+        )?;
+        for ctx in self.context_before.iter().chain(&self.context_after) {
+            write!(f, "\n  {ctx}")?;
+        }
+        Ok(())
+    }
 }
 
 /// A line of context around a match
@@ -84,6 +216,18 @@ pub struct ContextLine {
     pub content: String,
 }
 
+/// Renders `line_number: content` without the trailing newline (debugging aid).
+impl fmt::Display for ContextLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.line_number,
+            self.content.trim_end_matches(['\r', '\n'])
+        )
+    }
+}
+
 /// Errors that can occur during searching
 #[derive(Debug, Error)]
 pub enum SearchError {
@@ -100,6 +244,56 @@ pub enum SearchError {
         #[from]
         source: MatcherError,
     },
+
+    /// Several files failed, see [`SearchError::from_errors`]
+    #[error(transparent)]
+    Multiple(MultipleErrors),
+}
+
+impl SearchError {
+    /// Combine the errors of a search into one: `None` if there were none, the
+    /// error itself if there was one, and [`SearchError::Multiple`] otherwise
+    #[must_use]
+    pub fn from_errors(mut errors: Vec<SearchError>) -> Option<Self> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(Self::Multiple(MultipleErrors(errors))),
+        }
+    }
+
+    /// The individual errors: those of a [`SearchError::Multiple`], or just this one
+    #[must_use]
+    pub fn errors(&self) -> &[SearchError] {
+        match self {
+            Self::Multiple(MultipleErrors(errors)) => errors,
+            _ => std::slice::from_ref(self),
+        }
+    }
+}
+
+/// The errors of every file that failed, listed one after another; the first
+/// is the source
+#[derive(Debug)]
+pub struct MultipleErrors(pub Vec<SearchError>);
+
+impl fmt::Display for MultipleErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} files failed: ", self.0.len())?;
+        for (idx, err) in self.0.iter().enumerate() {
+            if idx != 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MultipleErrors {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.first().map(|err| err as _)
+    }
 }
 
 /// Result of a search operation
@@ -118,6 +312,104 @@ impl SearchResult {
         }
     }
 
+    /// Group runs of adjacent matches (see [`MatchResult::is_adjacent_to`]), in order
+    ///
+    /// A run continues while each match is adjacent to the one before it, so
+    /// matches on lines 5, 6 and 7 form one group and an isolated match forms its own.
+    #[allow(dead_code)]
+    #[must_use]
+    pub fn cluster_adjacent_matches(&self) -> Vec<Vec<&MatchResult>> {
+        let mut clusters: Vec<Vec<&MatchResult>> = Vec::new();
+        for m in &self.matches {
+            match clusters.last_mut() {
+                Some(cluster) if cluster.last().is_some_and(|prev| prev.is_adjacent_to(m)) => {
+                    cluster.push(m);
+                }
+                _ => clusters.push(vec![m]),
+            }
+        }
+        clusters
+    }
+
+    /// Write one CSV row per match, after a header row
+    ///
+    /// The columns are `file_path`, `line_number`, `byte_offset`,
+    /// `line_content` (without its line terminator), `context_before_count` and
+    /// `context_after_count`. Fields are quoted as RFC 4180 requires.
+    ///
+    /// # Errors
+    /// Returns an error if a row can't be written.
+    pub fn to_csv(&self) -> Result<String, csv::Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record([
+            "file_path",
+            "line_number",
+            "byte_offset",
+            "line_content",
+            "context_before_count",
+            "context_after_count",
+        ])?;
+        for m in &self.matches {
+            writer.write_record([
+                m.file_path.to_string_lossy().as_ref(),
+                &m.line_number.to_string(),
+                &m.byte_offset.to_string(),
+                m.line_content.trim_end_matches(['\n', '\r']),
+                &m.context_before.len().to_string(),
+                &m.context_after.len().to_string(),
+            ])?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|err| csv::Error::from(err.into_error()))?;
+        // Every field written was a `str`, so the output is UTF-8
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Consume the result, yielding its matches sorted by `(file_path, line_number)`
+    ///
+    /// The sort is stable, so several matches on one line keep their order.
+    /// Matches are moved out, not cloned.
+    #[allow(dead_code)]
+    pub fn into_sorted_iter(mut self) -> impl Iterator<Item = MatchResult> {
+        self.sort_by_file_and_line();
+        self.matches.into_iter()
+    }
+
+    /// Sort the matches by `(file_path, line_number)`, stably
+    pub fn sort_by_file_and_line(&mut self) {
+        self.matches
+            .sort_by(|a, b| (&a.file_path, a.line_number).cmp(&(&b.file_path, b.line_number)));
+    }
+
+    /// Sort the matches by `(line_number, file_path)`, stably, interleaving
+    /// the files
+    pub fn sort_by_line(&mut self) {
+        self.matches
+            .sort_by(|a, b| (a.line_number, &a.file_path).cmp(&(b.line_number, &b.file_path)));
+    }
+
+    /// Consume the result, grouping its matches by file
+    ///
+    /// Files come in the order of their first match, and each file's matches
+    /// keep their order.
+    #[must_use]
+    pub fn by_file(self) -> Vec<(PathBuf, Vec<MatchResult>)> {
+        let mut files: Vec<(PathBuf, Vec<MatchResult>)> = Vec::new();
+        // Each file's index in `files`
+        let mut index: HashMap<PathBuf, usize> = HashMap::new();
+        for m in self.matches {
+            match index.get(&m.file_path) {
+                Some(&idx) => files[idx].1.push(m),
+                None => {
+                    index.insert(m.file_path.clone(), files.len());
+                    files.push((m.file_path.clone(), vec![m]));
+                }
+            }
+        }
+        files
+    }
+
     /// Add a match to the result
     #[cfg(test)]
     pub fn add_match(&mut self, match_result: MatchResult) {
@@ -131,6 +423,46 @@ impl Default for SearchResult {
     }
 }
 
+/// Yields the matches in the order they were added
+impl IntoIterator for SearchResult {
+    type Item = MatchResult;
+    type IntoIter = std::vec::IntoIter<MatchResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.matches.into_iter()
+    }
+}
+
+/// How many files a search looked at, and why any of them weren't searched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Files that were read and matched against
+    pub searched: usize,
+    /// Files skipped because they look binary
+    pub binary_skipped: usize,
+    /// Files skipped because they aren't valid UTF-8
+    pub invalid_utf8_skipped: usize,
+    /// Files left unsearched once the byte budget was spent
+    pub size_skipped: usize,
+    /// Files that couldn't be read or searched
+    pub errored: usize,
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} searched, {} skipped as binary, {} skipped as invalid UTF-8, \
+             {} skipped by size, {} errored",
+            self.searched,
+            self.binary_skipped,
+            self.invalid_utf8_skipped,
+            self.size_skipped,
+            self.errored
+        )
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::similar_names)]
 mod tests {
@@ -153,9 +485,320 @@ mod tests {
             byte_offset: 100,
             context_before: vec![],
             context_after: vec![],
+            function_header: None,
+            match_lines: 1,
+            file_metadata: None,
         };
         result.add_match(match_result.clone());
         assert_eq!(result.matches.len(), 1);
         assert_eq!(result.matches[0], match_result);
     }
+
+    #[test]
+    fn test_into_sorted_iter_ignores_insert_order() {
+        let at = |path: &str, line_number| MatchResult {
+            file_path: PathBuf::from(path),
+            line_number,
+            ..match_on("x\n", None)
+        };
+        let sorted = vec![at("a.rs", 2), at("a.rs", 10), at("b.rs", 1)];
+
+        let mut forward = SearchResult::new();
+        let mut backward = SearchResult::new();
+        for m in &sorted {
+            forward.add_match(m.clone());
+        }
+        for m in sorted.iter().rev() {
+            backward.add_match(m.clone());
+        }
+
+        // Plain iteration keeps insert order
+        let reversed: Vec<_> = backward.clone().into_iter().collect();
+        assert_eq!(reversed, sorted.iter().rev().cloned().collect::<Vec<_>>());
+
+        assert_eq!(forward.into_sorted_iter().collect::<Vec<_>>(), sorted);
+        assert_eq!(backward.into_sorted_iter().collect::<Vec<_>>(), sorted);
+    }
+
+    fn match_on(line_content: &str, line_match: Option<std::ops::Range<usize>>) -> MatchResult {
+        MatchResult {
+            file_path: PathBuf::from("src/lib.rs"),
+            line_number: 10,
+            line_content: line_content.to_string(),
+            line_match,
+            byte_offset: 0,
+            context_before: vec![],
+            context_after: vec![],
+            function_header: None,
+            match_lines: 1,
+            file_metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_to_csv_quotes_and_parses_back() {
+        let at = |path: &str, line_number: usize, line_content: &str| MatchResult {
+            file_path: PathBuf::from(path),
+            line_number,
+            line_content: line_content.to_string(),
+            line_match: None,
+            byte_offset: 10 * line_number,
+            context_before: vec![],
+            context_after: vec![],
+            function_header: None,
+            match_lines: 1,
+            file_metadata: None,
+        };
+        let mut result = SearchResult::new();
+        result.add_match(at("src/a.rs", 2, "call(a, b);\n"));
+        result.add_match(at("src/b.rs", 7, "say \"hi\"\n"));
+
+        let csv = result.to_csv().unwrap();
+        assert!(csv.contains("\"call(a, b);\""), "{csv}");
+
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec![
+                "file_path",
+                "line_number",
+                "byte_offset",
+                "line_content",
+                "context_before_count",
+                "context_after_count",
+            ]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0],
+            vec!["src/a.rs", "2", "20", "call(a, b);", "0", "0"]
+        );
+        assert_eq!(&rows[1][3], "say \"hi\"");
+    }
+
+    #[test]
+    fn test_cluster_adjacent_matches() {
+        let at = |path: &str, line| MatchResult {
+            file_path: PathBuf::from(path),
+            line_number: line,
+            ..match_on("TARGET\n", None)
+        };
+        let mut result = SearchResult::new();
+        for m in [
+            at("a.rs", 1),
+            at("a.rs", 5),
+            at("a.rs", 6),
+            at("a.rs", 7),
+            at("a.rs", 9),
+            at("b.rs", 10),
+        ] {
+            result.add_match(m);
+        }
+
+        let lines: Vec<Vec<usize>> = result
+            .cluster_adjacent_matches()
+            .iter()
+            .map(|cluster| cluster.iter().map(|m| m.line_number).collect())
+            .collect();
+        assert_eq!(lines, vec![vec![1], vec![5, 6, 7], vec![9], vec![10]]);
+
+        // Same line number, different file: not adjacent
+        assert!(!at("a.rs", 10).is_adjacent_to(&at("b.rs", 10)));
+        assert!(at("a.rs", 6).is_adjacent_to(&at("a.rs", 5)));
+    }
+
+    #[test]
+    fn test_match_result_to_range() {
+        let at = |line, character| TextPosition { line, character };
+
+        // ASCII: bytes and UTF-16 units agree; the line becomes 0-indexed
+        let range = match_on("let x = TARGET;\n", Some(8..14))
+            .to_range()
+            .unwrap();
+        assert_eq!(
+            range,
+            TextRange {
+                start: at(9, 8),
+                end: at(9, 14)
+            }
+        );
+
+        // `é` is 2 bytes but 1 unit, `🦀` is 4 bytes but 2 units
+        let range = match_on("é🦀 TARGET\n", Some(7..13)).to_range().unwrap();
+        assert_eq!(
+            range,
+            TextRange {
+                start: at(9, 4),
+                end: at(9, 10)
+            }
+        );
+
+        assert_eq!(match_on("TARGET\n", None).to_range(), None);
+    }
+
+    #[test]
+    fn test_from_match_info_numbers_context_around_unterminated_lines() {
+        let info = |line_num: usize, line_content: &str, previous: &str, next: &str| MatchInfo {
+            line_num,
+            byte_offset: 0,
+            line_content: line_content.to_string(),
+            line_match: None,
+            column_start: 0,
+            column_end: 0,
+            previous_lines: previous.to_string(),
+            next_lines: next.to_string(),
+            spans: Vec::new(),
+        };
+        let numbers = |lines: &[ContextLine]| -> Vec<usize> {
+            lines.iter().map(|ctx| ctx.line_number).collect()
+        };
+
+        // `a\nb\nMATCH`: the match is the unterminated last line
+        let m = MatchResult::from_match_info(info(3, "MATCH", "a\nb\n", ""), "f".into());
+        assert_eq!(numbers(&m.context_before), vec![1, 2]);
+        assert!(m.context_after.is_empty());
+
+        // `a\nMATCH\nc\nd`: the last after-context line is unterminated
+        let m = MatchResult::from_match_info(info(2, "MATCH\n", "a\n", "c\nd"), "f".into());
+        assert_eq!(numbers(&m.context_before), vec![1]);
+        assert_eq!(numbers(&m.context_after), vec![3, 4]);
+        assert_eq!(m.context_after[1].content, "d");
+    }
+
+    #[test]
+    fn test_multiline_match_numbers_context_after_its_last_line() {
+        let info = MatchInfo {
+            line_num: 2,
+            byte_offset: 2,
+            line_content: "foo\nbar\n".to_string(),
+            line_match: Some(0..7),
+            column_start: 1,
+            column_end: 4,
+            previous_lines: "a\n".to_string(),
+            next_lines: "c\n".to_string(),
+            spans: Vec::new(),
+        };
+        let m = MatchResult::from_match_info(info, "f".into());
+        assert_eq!(m.line_count(), 2);
+        assert_eq!(m.context_before[0].line_number, 1);
+        assert_eq!(m.context_after[0].line_number, 4);
+
+        let format = crate::format::Format::from_matches_grouped(std::slice::from_ref(&m));
+        assert_eq!(format.0[0].num_lines, 4);
+        assert_eq!(format.0[0].content, "a\nfoo\nbar\nc\n");
+        let format = crate::format::Format::from_matches(&[m]);
+        assert_eq!(format.0[0].num_lines, 4);
+    }
+
+    #[test]
+    fn test_context_line_display() {
+        let ctx = ContextLine {
+            line_number: 7,
+            content: "let x = 1;\n".to_string(),
+        };
+        assert_eq!(ctx.to_string(), "7: let x = 1;");
+    }
+
+    #[test]
+    fn test_match_result_display() {
+        let match_result = MatchResult {
+            file_path: PathBuf::from("src/lib.rs"),
+            line_number: 2,
+            line_content: "TARGET line\n".to_string(),
+            line_match: None,
+            byte_offset: 7,
+            context_before: vec![ContextLine {
+                line_number: 1,
+                content: "before\n".to_string(),
+            }],
+            context_after: vec![ContextLine {
+                line_number: 3,
+                content: "after".to_string(),
+            }],
+            function_header: None,
+            match_lines: 1,
+            file_metadata: None,
+        };
+
+        assert_eq!(
+            match_result.to_string(),
+            "src/lib.rs:2: TARGET line\n  1: before\n  3: after"
+        );
+    }
+
+    #[test]
+    fn test_column_expands_leading_tabs() {
+        let mut m = match_on("\t\tlet x = \tTARGET;\n", Some(11..17));
+
+        assert_eq!(m.column(None), Some(12));
+        // Only the two indentation tabs widen; the tab before TARGET stays 1
+        assert_eq!(m.column(Some(4)), Some(18));
+        assert_eq!(m.column(Some(1)), Some(12));
+
+        m.line_match = None;
+        assert_eq!(m.column(Some(4)), None);
+    }
+
+    #[test]
+    fn test_by_file_groups_in_first_seen_order() {
+        let at = |path: &str, line_number| MatchResult {
+            file_path: PathBuf::from(path),
+            line_number,
+            ..match_on("TARGET\n", None)
+        };
+        let result = SearchResult {
+            matches: vec![at("b.rs", 1), at("a.rs", 1), at("b.rs", 2), at("a.rs", 3)],
+        };
+
+        let files: Vec<(PathBuf, Vec<usize>)> = result
+            .by_file()
+            .into_iter()
+            .map(|(path, matches)| (path, matches.iter().map(|m| m.line_number).collect()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                (PathBuf::from("b.rs"), vec![1, 2]),
+                (PathBuf::from("a.rs"), vec![1, 3])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_counts_characters_not_bytes() {
+        // 'é' is two bytes but one column, as in `MatchInfo::column_start`
+        let m = match_on("\théllo TARGET\n", Some(8..14));
+
+        assert_eq!(m.column(None), Some(8));
+        assert_eq!(m.column(Some(4)), Some(11));
+    }
+
+    #[test]
+    fn test_multiple_search_errors_name_each_path() {
+        use std::error::Error as _;
+
+        let not_found = |path: &str| SearchError::FileReadError {
+            source: FilesystemError::FileNotFound {
+                path: PathBuf::from(path),
+            },
+        };
+        assert!(SearchError::from_errors(Vec::new()).is_none());
+        assert!(matches!(
+            SearchError::from_errors(vec![not_found("/a.rs")]),
+            Some(SearchError::FileReadError { .. })
+        ));
+
+        let err = SearchError::from_errors(vec![not_found("/a.rs"), not_found("/b.rs")]).unwrap();
+        assert_eq!(err.errors().len(), 2);
+        assert_eq!(
+            err.to_string(),
+            "2 files failed: Failed to read file: File not found: /a.rs; \
+             Failed to read file: File not found: /b.rs"
+        );
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            "Failed to read file: File not found: /a.rs"
+        );
+    }
 }
@@ -0,0 +1,93 @@
+//! Walker adaptors
+//!
+//! This module provides generic wrappers that change which files any `Walker`
+//! yields, built with [`Walker::first_n`] and [`Walker::skip_n`].
+
+use super::Walker;
+use std::path::PathBuf;
+
+/// Walker that stops after the first `n` files of the inner walker
+///
+/// Built with [`Walker::first_n`].
+pub struct TakeWalker<W> {
+    inner: W,
+    n: usize,
+}
+
+impl<W> TakeWalker<W> {
+    pub(super) fn new(inner: W, n: usize) -> Self {
+        Self { inner, n }
+    }
+}
+
+impl<W: Walker> Walker for TakeWalker<W> {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        Box::new(self.inner.files().take(self.n))
+    }
+}
+
+/// Walker that skips the first `n` files of the inner walker
+///
+/// Built with [`Walker::skip_n`].
+pub struct SkipWalker<W> {
+    inner: W,
+    n: usize,
+}
+
+impl<W> SkipWalker<W> {
+    pub(super) fn new(inner: W, n: usize) -> Self {
+        Self { inner, n }
+    }
+}
+
+impl<W: Walker> Walker for SkipWalker<W> {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        Box::new(self.inner.files().skip(self.n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walker::simple::SimpleWalker;
+
+    fn ten_files() -> SimpleWalker {
+        SimpleWalker::new(
+            (1..=10)
+                .map(|i| PathBuf::from(format!("/f{i}.txt")))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_first_n_limits_files() {
+        let files: Vec<PathBuf> = ten_files().first_n(3).files().collect();
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("/f1.txt"),
+                PathBuf::from("/f2.txt"),
+                PathBuf::from("/f3.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_n_then_first_n_pages() {
+        let files: Vec<PathBuf> = ten_files().skip_n(3).first_n(3).files().collect();
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("/f4.txt"),
+                PathBuf::from("/f5.txt"),
+                PathBuf::from("/f6.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_n_past_end_yields_everything() {
+        assert_eq!(ten_files().first_n(50).files().count(), 10);
+        assert_eq!(ten_files().skip_n(50).files().count(), 0);
+    }
+}
@@ -5,18 +5,83 @@
 //! implementation based on the same infrastructure used by ripgrep and Helix.
 
 use super::Walker;
-use ignore::WalkBuilder;
-use std::{collections::HashSet, path::PathBuf, sync::Mutex};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{DirEntry, Match, WalkBuilder, WalkState};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    sync::{Arc, Mutex, PoisonError, mpsc},
+    time::SystemTime,
+};
+
+/// Which version control system's ignore rules the walker follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum VcsType {
+    /// `.gitignore` files, the global gitignore and `.git/info/exclude`
+    #[default]
+    Git,
+    /// `.hgignore` files. They're read with gitignore syntax, so only glob
+    /// patterns work (Mercurial's default `syntax: regexp` patterns don't)
+    Mercurial,
+    /// No VCS ignore files at all (`.ignore` files still apply)
+    None,
+}
+
+impl VcsType {
+    /// Whether git's ignore files are read
+    fn uses_git_ignore(self) -> bool {
+        self == Self::Git
+    }
+
+    /// The per-directory ignore file read through the ignore crate's custom
+    /// ignore file mechanism, if any
+    fn custom_ignore_filename(self) -> Option<&'static str> {
+        match self {
+            Self::Mercurial => Some(".hgignore"),
+            Self::Git | Self::None => None,
+        }
+    }
+
+    /// The repository metadata directory, which is never searched while the
+    /// VCS's rules are followed
+    fn metadata_dir(self) -> Option<&'static str> {
+        match self {
+            Self::Git => Some(".git"),
+            Self::Mercurial => Some(".hg"),
+            Self::None => None,
+        }
+    }
+}
 
 /// Production walker using ignore crate
 ///
 /// This walker respects .gitignore files and other ignore patterns.
 /// It's used in production to efficiently traverse large directory trees.
+///
+/// Each call to [`Walker::files`] is a fresh walk, so one walker can be built
+/// once and reused (e.g. by an embedder re-searching in a watch loop). Override
+/// globs are compiled once, when they're set, and are fixed for the walker's
+/// lifetime. A walker from [`IgnoreWalker::new`] re-parses every ignore file on
+/// each walk; one from [`IgnoreWalker::from_config`] keeps them compiled across
+/// walks, see [`IgnoreConfig`].
 pub struct IgnoreWalker {
     roots: Vec<PathBuf>,
     respect_gitignore: bool,
     include_hidden: bool,
     include_bk: bool,
+    vcs: VcsType,
+    threads: Option<usize>,
+    sorted: bool,
+    /// Compiled `!` patterns, applied to the normal walk (one per root)
+    exclude_overrides: Option<Vec<Override>>,
+    /// Compiled whitelist of every pattern, driving the force-include walk (one
+    /// per root)
+    include_overrides: Option<Vec<Override>>,
+    /// Ignore files compiled by earlier walks, if built from an [`IgnoreConfig`]
+    ignore_cache: Option<Arc<IgnoreCache>>,
 }
 
 impl IgnoreWalker {
@@ -39,80 +104,987 @@ impl IgnoreWalker {
             respect_gitignore,
             include_hidden: hidden,
             include_bk,
+            vcs: VcsType::Git,
+            threads: None,
+            sorted: false,
+            exclude_overrides: None,
+            include_overrides: None,
+            ignore_cache: None,
         }
     }
-}
 
-impl Walker for IgnoreWalker {
-    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
-        let Some((root, rem)) = self.roots.split_first() else {
-            return Box::new(std::iter::empty());
+    /// Create a walker with `config`'s settings that reads ignore files through
+    /// its cache, so walks after the first only re-parse the ones that changed
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn from_config(config: &IgnoreConfig) -> Self {
+        Self {
+            vcs: config.vcs,
+            ignore_cache: Some(Arc::clone(&config.cache)),
+            ..Self::new(
+                config.roots.clone(),
+                config.respect_gitignore,
+                config.hidden,
+                config.include_bk,
+            )
+        }
+    }
+
+    /// Force-include or force-exclude paths regardless of ignore rules
+    ///
+    /// Patterns use gitignore glob syntax, relative to each root:
+    ///
+    /// * `vendor/util.rs` force-includes matching files even when a `.gitignore`
+    ///   (or the hidden-file rule) would skip them. Every other file is still
+    ///   walked normally.
+    /// * `!secrets.rs` force-excludes matching files even when no ignore file
+    ///   mentions them.
+    ///
+    /// Force-including is done with a second walk that disables ignore files and
+    /// keeps only the whitelisted paths, so it costs a full traversal of the tree
+    /// (including normally ignored directories like `target/`).
+    ///
+    /// # Errors
+    /// Returns an error if any pattern is not a valid glob.
+    pub fn with_override_patterns(mut self, patterns: &[&str]) -> Result<Self, ignore::Error> {
+        // Compile once up front: invalid globs are reported here rather than
+        // silently dropped during the walk, and every walk reuses the matchers.
+        let excludes: Vec<&str> = patterns
+            .iter()
+            .copied()
+            .filter(|pattern| pattern.starts_with('!'))
+            .collect();
+        let per_root = |patterns: &[&str]| {
+            self.roots
+                .iter()
+                .map(|root| override_matcher(root, patterns.iter().copied()))
+                .collect::<Result<Vec<_>, _>>()
         };
 
-        let mut walker = WalkBuilder::new(root);
-        for path in rem {
-            walker.add(path);
-        }
+        let exclude_overrides = if excludes.is_empty() {
+            None
+        } else {
+            Some(per_root(&excludes)?)
+        };
+        let include_overrides = if patterns.iter().all(|pattern| pattern.starts_with('!')) {
+            None
+        } else {
+            Some(per_root(patterns)?)
+        };
+        self.exclude_overrides = exclude_overrides;
+        self.include_overrides = include_overrides;
+        Ok(self)
+    }
 
-        let walker = walker
-            .git_ignore(self.respect_gitignore)
-            .git_global(self.respect_gitignore)
-            .git_exclude(self.respect_gitignore)
-            .hidden(!self.include_hidden);
-
-        let visited = Mutex::new(HashSet::new());
-
-        let walker = if self.respect_gitignore {
-            walker.filter_entry(move |entry| {
-                // Always skip .git directories
-                if entry.file_name() == ".git" {
-                    return false;
-                }
+    /// Walk the tree with `threads` worker threads (`0` lets the ignore crate pick
+    /// a count based on the available CPUs).
+    ///
+    /// The parallel walk runs on a background thread and feeds paths through a
+    /// channel, so `files()` still streams: the first paths are yielded while the
+    /// rest of the tree is being walked. Paths arrive in a non-deterministic order.
+    ///
+    /// The speedup comes from overlapping directory reads across cores, so it scales
+    /// with the CPU count and with I/O latency (cold caches, network mounts); on a
+    /// single core it is on par with the sequential walk.
+    ///
+    /// Ignored when [`IgnoreWalker::with_sorted`] is enabled.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_parallel(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
 
-                let Ok(mut visited) = visited.lock() else {
-                    return true;
-                };
-                // Skip visited files
-                if visited.contains(entry.path()) {
-                    return false;
-                }
+    /// Yield paths in a deterministic, path-sorted order (default: false).
+    ///
+    /// Sorting needs to see each directory's entries together, so it forces the
+    /// single-threaded walk even if [`IgnoreWalker::with_parallel`] was set.
+    /// Several roots are walked in path order too.
+    #[must_use]
+    pub fn with_sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
 
-                visited.insert(entry.path().to_path_buf());
-                true
-            })
+    /// Follow the ignore rules of `vcs` (default: [`VcsType::Git`])
+    ///
+    /// Only matters while ignore files are respected: with `respect_gitignore`
+    /// off, no VCS ignore files are read whatever the type.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_vcs(mut self, vcs: VcsType) -> Self {
+        self.vcs = vcs;
+        self
+    }
+
+    /// Build one `WalkBuilder` per root, configured with this walker's ignore
+    /// rules, in walk order
+    ///
+    /// Each root gets its own builder so override globs are rooted at it. The
+    /// builders share the set of visited paths, so overlapping roots are still
+    /// walked once.
+    ///
+    /// With `force_include`, the builders are for the force-include pass: ignore
+    /// files are disabled and the override whitelist decides which files are
+    /// yielded. Otherwise only the force-exclude (`!`) patterns are applied.
+    fn builders(&self, force_include: bool) -> Vec<WalkBuilder> {
+        let mut roots: Vec<usize> = (0..self.roots.len()).collect();
+        if self.sorted {
+            roots.sort_by_key(|&idx| &self.roots[idx]);
+        }
+
+        // The cache stands in for the ignore crate's own ignore file handling
+        let cache = self.ignore_cache.clone().filter(|_| !force_include);
+        let respect_ignore_files = self.respect_gitignore && !force_include && cache.is_none();
+        let respect_git = respect_ignore_files && self.vcs.uses_git_ignore();
+        let ignore_files = !force_include && cache.is_none();
+        let cached_walk = cache.map(|cache| Arc::new(CachedWalk::new(cache)));
+
+        let overrides = if force_include {
+            &self.include_overrides
         } else {
-            walker.filter_entry(move |entry| {
-                let Ok(mut visited) = visited.lock() else {
-                    return true;
-                };
-                // Skip visited files
-                if visited.contains(entry.path()) {
-                    return false;
+            &self.exclude_overrides
+        };
+        let visited = Arc::new(Mutex::new(HashSet::new()));
+        let skip_dir = self.vcs.metadata_dir().filter(|_| self.respect_gitignore);
+
+        roots
+            .into_iter()
+            .map(|idx| {
+                let mut walker = WalkBuilder::new(&self.roots[idx]);
+                walker
+                    .git_ignore(respect_git)
+                    .git_global(respect_git)
+                    .git_exclude(respect_git)
+                    .ignore(ignore_files)
+                    // Forced files bypass the hidden-file rule as well as ignore files
+                    .hidden(!self.include_hidden && !force_include);
+                if respect_ignore_files && let Some(name) = self.vcs.custom_ignore_filename() {
+                    walker.add_custom_ignore_filename(name);
+                }
+                if let Some(overrides) = overrides {
+                    walker.overrides(overrides[idx].clone());
                 }
+                if self.sorted {
+                    walker.sort_by_file_path(|a, b| a.cmp(b));
+                }
+
+                let visited = Arc::clone(&visited);
+                let cached_walk = cached_walk.clone();
+                walker.filter_entry(move |entry| {
+                    // Always skip the VCS metadata directory (.git, .hg)
+                    if skip_dir.is_some_and(|dir| entry.file_name() == dir) {
+                        return false;
+                    }
+                    if cached_walk.as_ref().is_some_and(|walk| walk.skips(entry)) {
+                        return false;
+                    }
 
-                visited.insert(entry.path().to_path_buf());
-                true
+                    let Ok(mut visited) = visited.lock() else {
+                        return true;
+                    };
+                    // Skip visited files
+                    if visited.contains(entry.path()) {
+                        return false;
+                    }
+
+                    visited.insert(entry.path().to_path_buf());
+                    true
+                });
+                walker
             })
-        };
+            .collect()
+    }
 
-        let walker = walker.build();
+    /// Whether a walked entry is a file that should be searched.
+    fn is_searchable(entry: &DirEntry, include_bk: bool) -> bool {
+        entry.file_type().is_some_and(|ft| ft.is_file())
+            && is_searchable_file(entry.path(), entry.depth() == 0, include_bk)
+    }
 
-        // Skip bulked's own output format so search never matches the files it
-        // (or a previous run) produced, unless the caller opts in with --include-bk.
+    /// Walk `builders` one after another, each in parallel or sequentially
+    /// depending on configuration.
+    fn walk(&self, builders: Vec<WalkBuilder>) -> Box<dyn Iterator<Item = PathBuf>> {
+        let (threads, sorted) = (self.threads, self.sorted);
         let include_bk = self.include_bk;
-
         Box::new(
-            walker
-                .filter_map(std::result::Result::ok)
-                .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
-                .map(|entry| entry.path().to_path_buf())
-                .filter(move |path| include_bk || path.extension().is_none_or(|ext| ext != "bk")),
+            builders
+                .into_iter()
+                .flat_map(move |builder| Self::walk_one(builder, threads, sorted, include_bk)),
         )
     }
+
+    /// Walk one root's `builder`
+    fn walk_one(
+        builder: WalkBuilder,
+        threads: Option<usize>,
+        sorted: bool,
+        include_bk: bool,
+    ) -> Box<dyn Iterator<Item = PathBuf>> {
+        match threads {
+            Some(threads) if !sorted => Self::files_parallel(builder, threads, include_bk),
+            _ => Box::new(
+                builder
+                    .build()
+                    .filter_map(std::result::Result::ok)
+                    .filter(move |entry| Self::is_searchable(entry, include_bk))
+                    .map(DirEntry::into_path),
+            ),
+        }
+    }
+
+    /// Run a parallel walk on a background thread, streaming paths through a channel.
+    fn files_parallel(
+        mut builder: WalkBuilder,
+        threads: usize,
+        include_bk: bool,
+    ) -> Box<dyn Iterator<Item = PathBuf>> {
+        let (tx, rx) = mpsc::channel();
+        let walker = builder.threads(threads).build_parallel();
+
+        std::thread::spawn(move || {
+            walker.run(|| {
+                let tx = tx.clone();
+                Box::new(move |entry| {
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+                    if !Self::is_searchable(&entry, include_bk) {
+                        return WalkState::Continue;
+                    }
+                    // The receiver was dropped: nobody wants more paths.
+                    if tx.send(entry.into_path()).is_err() {
+                        return WalkState::Quit;
+                    }
+                    WalkState::Continue
+                })
+            });
+        });
+
+        Box::new(rx.into_iter())
+    }
+}
+
+/// Whether the file at `path` should be searched.
+///
+/// Skips bulked's own output format so search never matches the files it
+/// (or a previous run) produced, unless the caller opts in with --include-bk.
+/// A file passed directly as a root (`is_root`) is always searched: the ignore
+/// crate yields it regardless of ignore and hidden rules, and so do we.
+fn is_searchable_file(path: &Path, is_root: bool, include_bk: bool) -> bool {
+    is_root || include_bk || path.extension().is_none_or(|ext| ext != "bk")
 }
 
-// Note: We don't add #[cfg(test)] tests for IgnoreWalker here because
-// testing it would require creating real directories and .gitignore files,
-// which violates our hermetic testing principle. IgnoreWalker is a thin
-// wrapper over the ignore crate, which is well-tested. We test the Walker
-// trait contract with SimpleWalker.
+/// Walker settings plus the ignore files compiled under them, shared by every
+/// walker built with [`IgnoreWalker::from_config`]
+///
+/// Each ignore file (`.gitignore`, `.ignore`, `.git/info/exclude` and the VCS's
+/// custom file) is parsed once and kept until it changes. A walk stats the
+/// ignore files of each directory it visits (and of the directories above the
+/// roots), and re-parses only those whose modification time or size differs
+/// from the last walk, including files that appeared or were deleted. The
+/// global gitignore is read once, when the config is built: build a new config
+/// to pick up changes to it.
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct IgnoreConfig {
+    roots: Vec<PathBuf>,
+    respect_gitignore: bool,
+    hidden: bool,
+    include_bk: bool,
+    vcs: VcsType,
+    cache: Arc<IgnoreCache>,
+}
+
+#[allow(dead_code)]
+impl IgnoreConfig {
+    /// Create a config with an empty cache. The arguments are as for
+    /// [`IgnoreWalker::new`].
+    #[must_use]
+    pub fn new(
+        roots: Vec<PathBuf>,
+        respect_gitignore: bool,
+        hidden: bool,
+        include_bk: bool,
+    ) -> Self {
+        let vcs = VcsType::Git;
+        Self {
+            roots,
+            respect_gitignore,
+            hidden,
+            include_bk,
+            vcs,
+            cache: Arc::new(IgnoreCache::new(respect_gitignore, vcs)),
+        }
+    }
+
+    /// Follow the ignore rules of `vcs`, see [`IgnoreWalker::with_vcs`]. This
+    /// starts a new cache, since other ignore files are read.
+    #[must_use]
+    pub fn with_vcs(mut self, vcs: VcsType) -> Self {
+        self.vcs = vcs;
+        self.cache = Arc::new(IgnoreCache::new(self.respect_gitignore, vcs));
+        self
+    }
+}
+
+/// The ignore files of one directory, in the ignore crate's precedence order:
+/// the VCS's custom file, `.ignore`, `.gitignore`, then `.git/info/exclude`
+#[derive(Clone)]
+struct DirIgnores {
+    /// Whether the directory has a `.git`, making it a repository root
+    has_git: bool,
+    matchers: [Arc<Gitignore>; 4],
+}
+
+impl DirIgnores {
+    fn empty() -> Self {
+        let empty = Arc::new(Gitignore::empty());
+        Self {
+            has_git: false,
+            matchers: std::array::from_fn(|_| Arc::clone(&empty)),
+        }
+    }
+}
+
+/// A directory's compiled ignore files and what they looked like when parsed
+struct CachedDir {
+    /// The walk that last checked the files, so each walk stats them only once
+    walk: u64,
+    /// Modification time and size of each file, `None` if it's missing
+    stamps: [Option<(SystemTime, u64)>; 4],
+    ignores: DirIgnores,
+}
+
+/// Ignore files compiled by earlier walks, keyed by absolute directory path
+struct IgnoreCache {
+    /// File names read in each directory, by [`DirIgnores`] slot
+    file_names: [Option<&'static str>; 4],
+    global: Option<Gitignore>,
+    dirs: Mutex<HashMap<PathBuf, CachedDir>>,
+    walks: AtomicU64,
+}
+
+impl IgnoreCache {
+    fn new(respect_gitignore: bool, vcs: VcsType) -> Self {
+        let git = respect_gitignore && vcs.uses_git_ignore();
+        let custom = vcs.custom_ignore_filename().filter(|_| respect_gitignore);
+        Self {
+            file_names: [
+                custom,
+                Some(".ignore"),
+                git.then_some(".gitignore"),
+                git.then_some(".git/info/exclude"),
+            ],
+            global: git.then(|| Gitignore::global().0),
+            dirs: Mutex::new(HashMap::new()),
+            walks: AtomicU64::new(0),
+        }
+    }
+
+    /// Start a walk, returning its number
+    fn start_walk(&self) -> u64 {
+        self.walks.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// The ignore stack of `dir` and every directory above it
+    fn stack(&self, dir: &Path, walk: u64) -> Arc<DirStack> {
+        Arc::new(DirStack {
+            ignores: self.dir(dir, walk),
+            parent: dir.parent().map(|parent| self.stack(parent, walk)),
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// The ignore files of `dir`, re-parsing any that changed since the last walk
+    fn dir(&self, dir: &Path, walk: u64) -> DirIgnores {
+        let mut dirs = self.dirs.lock().unwrap_or_else(PoisonError::into_inner);
+        let cached = dirs.entry(dir.to_path_buf()).or_insert_with(|| CachedDir {
+            walk: 0,
+            stamps: [None; 4],
+            ignores: DirIgnores::empty(),
+        });
+        if cached.walk != walk {
+            cached.walk = walk;
+            cached.ignores.has_git = dir.join(".git").exists();
+            for (slot, name) in self.file_names.iter().enumerate() {
+                let Some(name) = name else { continue };
+                let file = dir.join(name);
+                let stamp = std::fs::metadata(&file)
+                    .ok()
+                    .and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+                if stamp != cached.stamps[slot] {
+                    cached.stamps[slot] = stamp;
+                    cached.ignores.matchers[slot] =
+                        Arc::new(compile_ignore_file(dir, &file, stamp));
+                }
+            }
+        }
+        cached.ignores.clone()
+    }
+}
+
+/// Compile the ignore file at `file` (with `stamp` from its metadata, `None` if
+/// it's missing), rooted at `dir`
+///
+/// Like the ignore crate, a file that can't be fully parsed still applies its
+/// valid lines; the error is logged.
+fn compile_ignore_file(dir: &Path, file: &Path, stamp: Option<(SystemTime, u64)>) -> Gitignore {
+    if stamp.is_none() {
+        return Gitignore::empty();
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(err) = builder.add(file) {
+        tracing::warn!("Failed to read {}: {}", file.display(), err);
+    }
+    builder.build().unwrap_or_else(|err| {
+        tracing::warn!("Failed to read {}: {}", file.display(), err);
+        Gitignore::empty()
+    })
+}
+
+/// The ignore files that apply inside one directory: its own, then those of the
+/// directories above it
+struct DirStack {
+    /// Absolute path of the directory
+    dir: PathBuf,
+    ignores: DirIgnores,
+    parent: Option<Arc<DirStack>>,
+}
+
+impl DirStack {
+    /// The ignore files of this directory and the ones above it, nearest first
+    fn dirs(&self) -> impl Iterator<Item = &DirIgnores> + Clone {
+        std::iter::successors(Some(self), |stack| stack.parent.as_deref())
+            .map(|stack| &stack.ignores)
+    }
+}
+
+/// One walk through an [`IgnoreCache`]
+///
+/// Each directory the walk enters gets its ignore stack once, from its
+/// parent's; entries are then checked against their parent's stack, so the
+/// ignore files are looked up per directory rather than per entry.
+struct CachedWalk {
+    cache: Arc<IgnoreCache>,
+    walk: u64,
+    /// Stacks of the directories entered so far, keyed by their walked path
+    stacks: Mutex<HashMap<PathBuf, Arc<DirStack>>>,
+}
+
+impl CachedWalk {
+    fn new(cache: Arc<IgnoreCache>) -> Self {
+        let walk = cache.start_walk();
+        Self {
+            cache,
+            walk,
+            stacks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the ignore files skip `entry`. Roots are walked whatever the
+    /// ignore files say.
+    fn skips(&self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+        let Some(parent) = entry.path().parent().and_then(|dir| self.stack(dir)) else {
+            return false;
+        };
+        let path = parent.dir.join(entry.file_name());
+        let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+        if is_ignored(parent.dirs(), self.cache.global.as_ref(), &path, is_dir) {
+            return true;
+        }
+        if is_dir {
+            let stack = Arc::new(DirStack {
+                ignores: self.cache.dir(&path, self.walk),
+                dir: path,
+                parent: Some(parent),
+            });
+            self.stacks
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(entry.path().to_path_buf(), stack);
+        }
+        false
+    }
+
+    /// The stack of the walked directory `dir`: entered directories get theirs
+    /// when they're kept, and roots (which the ignore crate doesn't filter) on
+    /// their first entry
+    fn stack(&self, dir: &Path) -> Option<Arc<DirStack>> {
+        let mut stacks = self.stacks.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(stack) = stacks.get(dir) {
+            return Some(Arc::clone(stack));
+        }
+        let stack = self.cache.stack(&std::path::absolute(dir).ok()?, self.walk);
+        stacks.insert(dir.to_path_buf(), Arc::clone(&stack));
+        Some(stack)
+    }
+}
+
+/// Whether `path` is ignored, given the ignore files of the directories above
+/// it (`dirs`, nearest first) and the global gitignore
+///
+/// Follows the ignore crate's rules: for each kind of file the nearest match
+/// wins, kinds are tried in [`DirIgnores`] order, and git's files only apply
+/// inside a repository, up to its root.
+fn is_ignored<'a>(
+    dirs: impl Iterator<Item = &'a DirIgnores> + Clone,
+    global: Option<&Gitignore>,
+    path: &Path,
+    is_dir: bool,
+) -> bool {
+    let any_git = dirs.clone().any(|dir| dir.has_git);
+    let mut found: [Match<()>; 4] = std::array::from_fn(|_| Match::None);
+    let mut saw_git = false;
+    for dir in dirs {
+        for (slot, matcher) in dir.matchers.iter().enumerate() {
+            let git_file = slot >= 2;
+            if found[slot].is_none() && !(git_file && (!any_git || saw_git)) {
+                found[slot] = matcher.matched(path, is_dir).map(|_| ());
+            }
+        }
+        saw_git = saw_git || dir.has_git;
+    }
+    let global = match global {
+        Some(global) if any_git => global.matched(path, is_dir).map(|_| ()),
+        _ => Match::None,
+    };
+    found
+        .into_iter()
+        .fold(Match::None, Match::or)
+        .or(global)
+        .is_ignore()
+}
+
+/// The force-exclude override glob for `--exclude-dir NAME`: every directory
+/// named `name`, at any depth, and everything under it
+pub fn exclude_dir_glob(name: &str) -> String {
+    format!("!**/{name}/**")
+}
+
+/// Compile override globs rooted at `root`.
+fn override_matcher<'a>(
+    root: &Path,
+    patterns: impl IntoIterator<Item = &'a str>,
+) -> Result<Override, ignore::Error> {
+    let mut builder = OverrideBuilder::new(root);
+    for pattern in patterns {
+        builder.add(pattern)?;
+    }
+    builder.build()
+}
+
+impl Walker for IgnoreWalker {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        let files = self.walk(self.builders(false));
+        if self.include_overrides.is_none() {
+            return files;
+        }
+
+        merge_walks(files, self.walk(self.builders(true)))
+    }
+}
+
+/// The files of the normal walk followed by those only the force-include walk
+/// found: a force-included file that isn't ignored shows up in both walks.
+fn merge_walks<'a>(
+    files: Box<dyn Iterator<Item = PathBuf> + 'a>,
+    forced: Box<dyn Iterator<Item = PathBuf> + 'a>,
+) -> Box<dyn Iterator<Item = PathBuf> + 'a> {
+    let mut seen = HashSet::new();
+    Box::new(
+        files
+            .chain(forced)
+            .filter(move |path| seen.insert(path.clone())),
+    )
+}
+
+// Note: The uncached walk is left to the ignore crate, which is well-tested; we
+// test the override glob semantics below against in-memory ignore rules, and
+// the one uncached walk that is tested reads this crate's own `src/`, which
+// exists and is never written. The cached walk reimplements the ignore crate's
+// precedence, so it is tested twice over: the rules with hand-built ignore
+// files, and whole walks of scratch repositories in a temporary directory,
+// checked against the uncached walk of the same tree.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ignore::gitignore::GitignoreBuilder;
+
+    #[test]
+    fn test_override_force_includes_gitignored_file() {
+        let root = Path::new("/project");
+        let mut gitignore = GitignoreBuilder::new(root);
+        gitignore.add_line(None, "vendor/").unwrap();
+        let gitignore = gitignore.build().unwrap();
+
+        // The gitignore excludes the vendor directory...
+        assert!(gitignore.matched(root.join("vendor"), true).is_ignore());
+
+        // ...but the override whitelists the file inside it.
+        let overrides = override_matcher(root, ["vendor/util.rs"]).unwrap();
+        assert!(
+            overrides
+                .matched(root.join("vendor/util.rs"), false)
+                .is_whitelist()
+        );
+        // Whitelisting only picks files for the force-include pass; other files
+        // don't match it and are walked by the normal pass instead.
+        assert!(
+            overrides
+                .matched(root.join("src/main.rs"), false)
+                .is_ignore()
+        );
+    }
+
+    #[test]
+    fn test_override_negated_pattern_force_excludes() {
+        let root = Path::new("/project");
+        let overrides = override_matcher(root, ["!secrets.rs"]).unwrap();
+
+        assert!(
+            overrides
+                .matched(root.join("src/secrets.rs"), false)
+                .is_ignore()
+        );
+        // With only negated patterns, other files are left to the normal rules.
+        assert!(overrides.matched(root.join("src/main.rs"), false).is_none());
+    }
+
+    #[test]
+    fn test_exclude_dir_glob_excludes_files_under_the_directory() {
+        let root = Path::new("/project");
+        let overrides =
+            override_matcher(root, [exclude_dir_glob("node_modules").as_str()]).unwrap();
+
+        for excluded in ["node_modules/a.js", "web/node_modules/lib/b.js"] {
+            assert!(overrides.matched(root.join(excluded), false).is_ignore());
+        }
+        for kept in ["src/main.rs", "src/node_modules.rs"] {
+            assert!(overrides.matched(root.join(kept), false).is_none());
+        }
+    }
+
+    #[test]
+    fn test_exclude_dir_skips_files_under_the_directory_during_a_walk() {
+        let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+        let under_walker = |files: Vec<PathBuf>| {
+            files
+                .iter()
+                .filter(|path| {
+                    path.strip_prefix(&src)
+                        .is_ok_and(|p| p.starts_with("walker"))
+                })
+                .count()
+        };
+
+        let walker = IgnoreWalker::new(vec![src.clone()], true, false, false);
+        assert!(under_walker(walker.files().collect()) > 0);
+
+        let glob = exclude_dir_glob("walker");
+        let walker = IgnoreWalker::new(vec![src.clone()], true, false, false)
+            .with_override_patterns(&[glob.as_str()])
+            .unwrap();
+        let files: Vec<PathBuf> = walker.files().collect();
+        assert!(files.iter().any(|path| path.ends_with("main.rs")));
+        assert_eq!(under_walker(files), 0);
+    }
+
+    #[test]
+    fn test_force_included_files_are_walked_once() {
+        let normal = vec![
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("vendor/util.rs"),
+        ];
+        let forced = vec![
+            PathBuf::from("vendor/util.rs"),
+            PathBuf::from(".hidden/config.rs"),
+        ];
+
+        let files: Vec<PathBuf> = merge_walks(normal.files(), forced.files()).collect();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("src/main.rs"),
+                PathBuf::from("vendor/util.rs"),
+                PathBuf::from(".hidden/config.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_root_is_always_searched() {
+        let bk = Path::new("./edits.bk");
+
+        // Found while walking a directory: skipped unless --include-bk
+        assert!(!is_searchable_file(bk, false, false));
+        assert!(is_searchable_file(bk, false, true));
+        // Passed directly (`bulked search PATTERN ./edits.bk`): never dropped
+        assert!(is_searchable_file(bk, true, false));
+        assert!(is_searchable_file(Path::new("./single.txt"), true, false));
+    }
+
+    #[test]
+    fn test_with_override_patterns_compiles_once() {
+        let walker = IgnoreWalker::new(vec![PathBuf::from(".")], true, false, false);
+        let walker = walker.with_override_patterns(&["!*.gen.rs"]).unwrap();
+        // Only force-excludes: no force-include walk is needed
+        assert!(walker.exclude_overrides.is_some());
+        assert!(walker.include_overrides.is_none());
+
+        let walker = walker
+            .with_override_patterns(&["vendor/util.rs", "!*.gen.rs"])
+            .unwrap();
+        assert!(walker.exclude_overrides.is_some());
+        assert!(walker.include_overrides.is_some());
+    }
+
+    /// A directory whose ignore file in `slot` (see [`DirIgnores`]) has `lines`
+    fn dir_ignores(dir: &str, has_git: bool, slot: usize, lines: &[&str]) -> DirIgnores {
+        let mut builder = GitignoreBuilder::new(dir);
+        for line in lines {
+            builder.add_line(None, line).unwrap();
+        }
+        let mut ignores = DirIgnores::empty();
+        ignores.has_git = has_git;
+        ignores.matchers[slot] = Arc::new(builder.build().unwrap());
+        ignores
+    }
+
+    #[test]
+    fn test_cached_ignore_files_follow_the_ignore_crate_precedence() {
+        const GITIGNORE: usize = 2;
+        let path = Path::new("/repo/src/gen.rs");
+        let ignored = |dirs: &[DirIgnores]| is_ignored(dirs.iter(), None, path, false);
+
+        // A nested .gitignore's whitelist beats the repository root's ignore
+        let root = dir_ignores("/repo", true, GITIGNORE, &["*.rs"]);
+        let nested = dir_ignores("/repo/src", false, GITIGNORE, &["!gen.rs"]);
+        assert!(ignored(&[DirIgnores::empty(), root.clone()]));
+        assert!(!ignored(&[nested, root.clone()]));
+
+        // .ignore outranks .gitignore
+        let dot_ignore = dir_ignores("/repo/src", false, 1, &["!gen.rs"]);
+        assert!(!ignored(&[dot_ignore, root]));
+
+        // A .gitignore only applies inside a repository, up to its root
+        let outside = dir_ignores("/repo", false, GITIGNORE, &["*.rs"]);
+        assert!(!ignored(&[DirIgnores::empty(), outside]));
+        let above_root = dir_ignores("/", false, GITIGNORE, &["*.rs"]);
+        let root = dir_ignores("/repo", true, GITIGNORE, &[]);
+        assert!(!ignored(&[DirIgnores::empty(), root, above_root]));
+    }
+
+    #[test]
+    fn test_cached_ignore_files_rank_custom_file_first_and_exclude_last() {
+        const CUSTOM: usize = 0;
+        const EXCLUDE: usize = 3;
+        let path = Path::new("/repo/gen.rs");
+        let ignored = |dirs: &[DirIgnores], global: Option<&Gitignore>| {
+            is_ignored(dirs.iter(), global, path, false)
+        };
+        let with = |ignores: &[(usize, &[&str])]| {
+            let mut dir = DirIgnores::empty();
+            dir.has_git = true;
+            for (slot, lines) in ignores {
+                dir.matchers[*slot] =
+                    dir_ignores("/repo", true, *slot, lines).matchers[*slot].clone();
+            }
+            dir
+        };
+
+        // The VCS's custom file outranks .ignore, and .git/info/exclude ranks
+        // below .gitignore
+        assert!(!ignored(
+            &[with(&[(CUSTOM, &["!gen.rs"]), (1, &["*.rs"])])],
+            None
+        ));
+        assert!(ignored(
+            &[with(&[(CUSTOM, &["*.rs"]), (1, &["!gen.rs"])])],
+            None
+        ));
+        assert!(!ignored(
+            &[with(&[(2, &["!gen.rs"]), (EXCLUDE, &["*.rs"])])],
+            None
+        ));
+        assert!(ignored(&[with(&[(EXCLUDE, &["*.rs"])])], None));
+
+        // The global gitignore ranks last, and only applies inside a repository
+        let mut global = GitignoreBuilder::new("/");
+        global.add_line(None, "gen.rs").unwrap();
+        let global = global.build().unwrap();
+        assert!(ignored(&[with(&[])], Some(&global)));
+        assert!(!ignored(&[with(&[(2, &["!gen.rs"])])], Some(&global)));
+        let mut outside = with(&[]);
+        outside.has_git = false;
+        assert!(!ignored(&[outside], Some(&global)));
+
+        // Directory patterns only match directories
+        let dirs = [with(&[(2, &["gen.rs/"])])];
+        assert!(!ignored(&dirs, None));
+        assert!(is_ignored(dirs.iter(), None, path, true));
+    }
+
+    /// A scratch repository with `files` (path, contents) under a `.git`
+    fn scratch_repo(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    /// The files a walker yields, relative to `root` and sorted
+    fn walked(walker: &IgnoreWalker, root: &Path) -> Vec<String> {
+        let mut files: Vec<String> = walker
+            .files()
+            .map(|path| path.strip_prefix(root).unwrap().display().to_string())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_cached_walk_matches_the_ignore_crate() {
+        let repo = scratch_repo(&[
+            (".gitignore", "*.log\ntarget/\n/build.rs\n"),
+            (".ignore", "!keep.log\n"),
+            (".git/info/exclude", "*.tmp\n"),
+            ("app.log", ""),
+            ("keep.log", ""),
+            ("build.rs", ""),
+            ("notes.tmp", ""),
+            ("target/out.rs", ""),
+            ("src/build.rs", ""),
+            ("src/main.rs", ""),
+            ("src/.gitignore", "gen/\n!app.log\n"),
+            ("src/app.log", ""),
+            ("src/gen/a.rs", ""),
+            ("src/nested/.gitignore", "*.rs\n"),
+            ("src/nested/b.rs", ""),
+            ("src/nested/c.txt", ""),
+        ]);
+        let root = repo.path().to_path_buf();
+        let uncached = IgnoreWalker::new(vec![root.clone()], true, false, false);
+        let config = IgnoreConfig::new(vec![root.clone()], true, false, false);
+        let cached = IgnoreWalker::from_config(&config);
+
+        let expected = walked(&uncached, &root);
+        assert_eq!(
+            expected,
+            [
+                "keep.log",
+                "src/app.log",
+                "src/build.rs",
+                "src/main.rs",
+                "src/nested/c.txt"
+            ]
+        );
+        assert_eq!(walked(&cached, &root), expected);
+        // Walking a subdirectory still applies the ignore files above it
+        let src = root.join("src");
+        let cached =
+            IgnoreWalker::from_config(&IgnoreConfig::new(vec![src.clone()], true, false, false));
+        assert_eq!(
+            walked(&cached, &src),
+            walked(
+                &IgnoreWalker::new(vec![src.clone()], true, false, false),
+                &src
+            )
+        );
+    }
+
+    #[test]
+    fn test_override_globs_are_rooted_at_each_root() {
+        let repo = scratch_repo(&[
+            (".gitignore", "vendor/\n"),
+            ("sub/dir/a.rs", ""),
+            ("sub/dir/b.rs", ""),
+            ("sub/dir/vendor/util.rs", ""),
+            ("sub/dir/vendor/other.rs", ""),
+            ("lib/vendor/util.rs", ""),
+            ("lib/b.rs", ""),
+        ]);
+        let root = repo.path().to_path_buf();
+        let roots = vec![root.join("sub/dir"), root.join("lib")];
+        let walker = IgnoreWalker::new(roots, true, false, false)
+            .with_override_patterns(&["vendor/util.rs", "!b.rs"])
+            .unwrap();
+
+        assert_eq!(
+            walked(&walker, &root),
+            [
+                "lib/vendor/util.rs",
+                "sub/dir/a.rs",
+                "sub/dir/vendor/util.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cached_walk_sees_ignore_file_edits_between_walks() {
+        let repo = scratch_repo(&[
+            (".gitignore", "a.rs\n"),
+            ("a.rs", ""),
+            ("b.rs", ""),
+            ("src/c.rs", ""),
+        ]);
+        let root = repo.path().to_path_buf();
+        let config = IgnoreConfig::new(vec![root.clone()], true, false, false);
+        let walker = IgnoreWalker::from_config(&config);
+        assert_eq!(walked(&walker, &root), ["b.rs", "src/c.rs"]);
+
+        // An edited file is re-parsed (the size changes, so a coarse mtime
+        // can't hide the edit)
+        std::fs::write(root.join(".gitignore"), "b.rs\nsrc/\n").unwrap();
+        assert_eq!(walked(&walker, &root), ["a.rs"]);
+
+        // So is one that appears, and one that's deleted
+        std::fs::write(root.join(".ignore"), "!src/\n").unwrap();
+        assert_eq!(walked(&walker, &root), ["a.rs", "src/c.rs"]);
+        std::fs::remove_file(root.join(".gitignore")).unwrap();
+        assert_eq!(walked(&walker, &root), ["a.rs", "b.rs", "src/c.rs"]);
+
+        // Walkers built from the same config share the cache
+        let other = IgnoreWalker::from_config(&config);
+        std::fs::write(root.join(".ignore"), "a.rs\n").unwrap();
+        assert_eq!(walked(&other, &root), ["b.rs", "src/c.rs"]);
+    }
+
+    #[test]
+    fn test_vcs_type_selects_ignore_files() {
+        // Git reads the gitignore files and skips .git
+        assert!(VcsType::Git.uses_git_ignore());
+        assert_eq!(VcsType::Git.custom_ignore_filename(), None);
+        assert_eq!(VcsType::Git.metadata_dir(), Some(".git"));
+
+        // Mercurial reads .hgignore instead of any gitignore
+        assert!(!VcsType::Mercurial.uses_git_ignore());
+        assert_eq!(
+            VcsType::Mercurial.custom_ignore_filename(),
+            Some(".hgignore")
+        );
+        assert_eq!(VcsType::Mercurial.metadata_dir(), Some(".hg"));
+
+        // None reads no VCS ignore files, so .gitignore rules don't apply
+        assert!(!VcsType::None.uses_git_ignore());
+        assert_eq!(VcsType::None.custom_ignore_filename(), None);
+        assert_eq!(VcsType::None.metadata_dir(), None);
+
+        let walker = IgnoreWalker::new(vec![PathBuf::from(".")], true, false, false);
+        assert_eq!(walker.vcs, VcsType::Git);
+        assert_eq!(walker.with_vcs(VcsType::None).vcs, VcsType::None);
+    }
+
+    #[test]
+    fn test_with_override_patterns_rejects_invalid_glob() {
+        let walker = IgnoreWalker::new(vec![PathBuf::from(".")], true, false, false);
+        assert!(walker.with_override_patterns(&["a[b"]).is_err());
+    }
+}
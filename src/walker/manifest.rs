@@ -0,0 +1,283 @@
+//! Workspace-aware walker
+//!
+//! This module provides `ManifestWalker`, which restricts a search to the member
+//! directories a manifest lists and walks each of them with an `IgnoreWalker`.
+//! A manifest is either a `Cargo.toml` (read with the `manifest` feature) or a
+//! plain list file with one member directory or glob per line.
+
+use super::Walker;
+use super::ignore_walker::IgnoreWalker;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors that can occur while resolving a manifest's members
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("Failed to read manifest {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[cfg(feature = "manifest")]
+    #[error("Failed to parse manifest {}: {source}", path.display())]
+    Toml {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[cfg(not(feature = "manifest"))]
+    #[error(
+        "Can't read {}: bulked was built without the `manifest` feature, use a list file instead",
+        path.display()
+    )]
+    TomlUnsupported { path: PathBuf },
+
+    #[error("Invalid member pattern {pattern}: {source}")]
+    Pattern {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+
+    #[error("Manifest {} lists no members", path.display())]
+    NoMembers { path: PathBuf },
+}
+
+/// The member patterns a manifest lists, relative to the manifest's directory
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Members {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Members {
+    /// Parse a list file: one member directory or glob per line, skipping blank
+    /// lines and `#` comments; a leading `!` excludes
+    #[must_use]
+    pub fn from_list(content: &str) -> Self {
+        let mut members = Self::default();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.strip_prefix('!') {
+                Some(excluded) => members.exclude.push(excluded.to_string()),
+                None => members.include.push(line.to_string()),
+            }
+        }
+        members
+    }
+
+    /// Read `[workspace] members` and `exclude` from a `Cargo.toml`
+    ///
+    /// A manifest without a `[workspace]` is a single package, whose member is
+    /// the manifest's own directory. A root package inside a workspace isn't
+    /// added, since its directory would contain every other member.
+    ///
+    /// # Errors
+    /// Returns an error if `content` isn't valid TOML.
+    #[cfg(feature = "manifest")]
+    pub fn from_cargo_toml(content: &str) -> Result<Self, toml::de::Error> {
+        #[derive(serde::Deserialize)]
+        struct CargoManifest {
+            workspace: Option<Workspace>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Workspace {
+            #[serde(default)]
+            members: Vec<String>,
+            #[serde(default)]
+            exclude: Vec<String>,
+        }
+
+        let manifest: CargoManifest = toml::from_str(content)?;
+        Ok(match manifest.workspace {
+            Some(workspace) => Self {
+                include: workspace.members,
+                exclude: workspace.exclude,
+            },
+            None => Self {
+                include: vec![".".to_string()],
+                exclude: Vec::new(),
+            },
+        })
+    }
+
+    /// Expand the member globs against the directories under `base`
+    ///
+    /// Only existing directories are kept; the result is sorted and deduplicated.
+    fn resolve(&self, base: &Path) -> Result<Vec<PathBuf>, ManifestError> {
+        let expand = |patterns: &[String]| -> Result<Vec<PathBuf>, ManifestError> {
+            let mut dirs = Vec::new();
+            for pattern in patterns {
+                let full = base.join(pattern);
+                let paths = glob::glob(&full.to_string_lossy()).map_err(|source| {
+                    ManifestError::Pattern {
+                        pattern: pattern.clone(),
+                        source,
+                    }
+                })?;
+                let before = dirs.len();
+                dirs.extend(paths.filter_map(Result::ok).filter(|path| path.is_dir()));
+                if dirs.len() == before {
+                    tracing::warn!("Workspace member {} matches no directory", pattern);
+                }
+            }
+            Ok(dirs)
+        };
+
+        let excluded = expand(&self.exclude)?;
+        let mut dirs = expand(&self.include)?;
+        dirs.retain(|dir| !excluded.contains(dir));
+        dirs.sort();
+        dirs.dedup();
+        Ok(dirs)
+    }
+}
+
+/// Walks the member directories of a workspace manifest
+///
+/// Members are resolved once, when the walker is built; each walk then covers
+/// every member with the usual ignore rules, as one [`IgnoreWalker`] with a
+/// root per member.
+pub struct ManifestWalker {
+    members: Vec<PathBuf>,
+    inner: IgnoreWalker,
+}
+
+impl ManifestWalker {
+    /// Read `manifest` and build a walker over its members
+    ///
+    /// A `.toml` manifest is read as a `Cargo.toml`; anything else as a list
+    /// file (see [`Members::from_list`]). Member patterns are relative to the
+    /// manifest's directory. The other arguments are as for [`IgnoreWalker::new`].
+    ///
+    /// # Errors
+    /// Returns an error if the manifest can't be read or parsed, a member glob is
+    /// invalid, or no member directory exists.
+    pub fn new(
+        manifest: &Path,
+        respect_gitignore: bool,
+        hidden: bool,
+        include_bk: bool,
+    ) -> Result<Self, ManifestError> {
+        let content = std::fs::read_to_string(manifest).map_err(|source| ManifestError::Read {
+            path: manifest.to_path_buf(),
+            source,
+        })?;
+        let members = if manifest.extension().is_some_and(|ext| ext == "toml") {
+            parse_cargo_toml(manifest, &content)?
+        } else {
+            Members::from_list(&content)
+        };
+
+        let base = manifest.parent().unwrap_or_else(|| Path::new("."));
+        let base = if base.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            base
+        };
+        let members = members.resolve(base)?;
+        if members.is_empty() {
+            return Err(ManifestError::NoMembers {
+                path: manifest.to_path_buf(),
+            });
+        }
+
+        let inner = IgnoreWalker::new(members.clone(), respect_gitignore, hidden, include_bk);
+        Ok(Self { members, inner })
+    }
+
+    /// See [`IgnoreWalker::with_override_patterns`]
+    ///
+    /// # Errors
+    /// Returns an error if a pattern is not a valid glob.
+    pub fn with_override_patterns(mut self, patterns: &[&str]) -> Result<Self, ignore::Error> {
+        self.inner = self.inner.with_override_patterns(patterns)?;
+        Ok(self)
+    }
+
+    /// See [`IgnoreWalker::with_sorted`]
+    #[must_use]
+    pub fn with_sorted(mut self, sorted: bool) -> Self {
+        self.inner = self.inner.with_sorted(sorted);
+        self
+    }
+
+    /// The member directories being walked
+    #[allow(dead_code)]
+    pub fn members(&self) -> &[PathBuf] {
+        &self.members
+    }
+}
+
+impl Walker for ManifestWalker {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        self.inner.files()
+    }
+}
+
+#[cfg(feature = "manifest")]
+fn parse_cargo_toml(manifest: &Path, content: &str) -> Result<Members, ManifestError> {
+    Members::from_cargo_toml(content).map_err(|source| ManifestError::Toml {
+        path: manifest.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(not(feature = "manifest"))]
+fn parse_cargo_toml(manifest: &Path, _content: &str) -> Result<Members, ManifestError> {
+    Err(ManifestError::TomlUnsupported {
+        path: manifest.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_from_list() {
+        let members = Members::from_list("# services\ncrates/*\n\n  tools/cli  \n!crates/old\n");
+
+        assert_eq!(
+            members,
+            Members {
+                include: vec!["crates/*".to_string(), "tools/cli".to_string()],
+                exclude: vec!["crates/old".to_string()],
+            }
+        );
+    }
+
+    #[cfg(feature = "manifest")]
+    #[test]
+    fn test_members_from_cargo_toml() {
+        let workspace = r#"
+[workspace]
+members = ["crates/*", "xtask"]
+exclude = ["crates/legacy"]
+
+[workspace.dependencies]
+serde = "1"
+"#;
+        assert_eq!(
+            Members::from_cargo_toml(workspace).unwrap(),
+            Members {
+                include: vec!["crates/*".to_string(), "xtask".to_string()],
+                exclude: vec!["crates/legacy".to_string()],
+            }
+        );
+
+        let package = "[package]\nname = \"solo\"\nversion = \"0.1.0\"\n";
+        assert_eq!(
+            Members::from_cargo_toml(package).unwrap().include,
+            vec![".".to_string()]
+        );
+
+        assert!(Members::from_cargo_toml("[workspace\n").is_err());
+    }
+}
@@ -4,9 +4,12 @@
 //! directory walking. This allows testing search logic with controlled
 //! file lists without depending on actual filesystem traversal.
 
+pub mod adaptors;
 pub mod ignore_walker;
+pub mod manifest;
 pub mod simple;
 
+use adaptors::{SkipWalker, TakeWalker};
 use std::path::PathBuf;
 
 /// Abstract directory walker interface
@@ -20,6 +23,43 @@ pub trait Walker: Send + Sync {
     /// Returns paths to files that should be searched. Directories are
     /// not included, only files.
     fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_>;
+
+    /// Stop after the first `n` files
+    #[allow(dead_code)]
+    fn first_n(self, n: usize) -> TakeWalker<Self>
+    where
+        Self: Sized,
+    {
+        TakeWalker::new(self, n)
+    }
+
+    /// Skip the first `n` files; combine with [`Walker::first_n`] to page through a walk
+    #[allow(dead_code)]
+    fn skip_n(self, n: usize) -> SkipWalker<Self>
+    where
+        Self: Sized,
+    {
+        SkipWalker::new(self, n)
+    }
+}
+
+impl Walker for Box<dyn Walker> {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        (**self).files()
+    }
+}
+
+/// A fixed file list walks as exactly those paths, in order
+impl Walker for Vec<PathBuf> {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        Box::new(self.iter().cloned())
+    }
+}
+
+impl Walker for &[PathBuf] {
+    fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
+        Box::new(self.iter().cloned())
+    }
 }
 
 #[cfg(test)]
@@ -50,4 +90,37 @@ mod tests {
         assert_eq!(files[1], PathBuf::from("/test/file2.txt"));
         assert_eq!(files[2], PathBuf::from("/test/subdir/file3.txt"));
     }
+
+    #[test]
+    fn test_vec_and_slice_walk_their_paths() {
+        let paths = vec![PathBuf::from("/a.txt"), PathBuf::from("/b.txt")];
+
+        let from_slice: Vec<PathBuf> = paths.as_slice().files().collect();
+        assert_eq!(from_slice, paths);
+        let from_vec: Vec<PathBuf> = paths.files().collect();
+        assert_eq!(from_vec, paths);
+    }
+
+    #[test]
+    fn test_searcher_takes_a_vec_as_walker() {
+        use crate::filesystem::memory::MemoryFS;
+        use crate::matcher::Matcher;
+        use crate::matcher::regex::GrepMatcher;
+        use crate::searcher::Searcher;
+
+        let fs = MemoryFS::new();
+        let (a, b) = (PathBuf::from("/a.txt"), PathBuf::from("/b.txt"));
+        fs.add_file(&a, "TARGET\n").unwrap();
+        fs.add_file(&b, "nothing\nTARGET\n").unwrap();
+        let matcher = GrepMatcher::compile("TARGET").unwrap();
+
+        let searcher = Searcher::new(fs, matcher, vec![a.clone(), b.clone()]);
+        let found: Vec<(PathBuf, usize)> = searcher
+            .search_all()
+            .flat_map(|result| result.unwrap().matches)
+            .map(|m| (m.file_path, m.line_number))
+            .collect();
+
+        assert_eq!(found, vec![(a, 1), (b, 2)]);
+    }
 }
@@ -1,16 +1,17 @@
-//! Simple walker implementation for testing
+//! Simple walker implementation for fixed file lists
 //!
-//! This module provides `SimpleWalker`, a test double that returns a predefined
-//! list of files. This allows testing search logic without filesystem traversal.
+//! This module provides `SimpleWalker`, which returns a predefined list of
+//! files. Tests use it to exercise search logic without filesystem traversal,
+//! and `search --files-from` uses it to search exactly the listed files.
 
 use super::Walker;
 use std::path::PathBuf;
 
-/// Simple walker for testing
+/// Simple walker over a fixed file list
 ///
 /// This walker returns a predefined list of file paths.
 /// It's used in tests to control exactly which files are "walked".
-#[allow(dead_code)]
+/// A plain `Vec<PathBuf>` walks the same way; this named type delegates to it.
 pub(crate) struct SimpleWalker {
     files: Vec<PathBuf>,
 }
@@ -31,7 +32,7 @@ impl SimpleWalker {
 
 impl Walker for SimpleWalker {
     fn files(&self) -> Box<dyn Iterator<Item = PathBuf> + '_> {
-        Box::new(self.files.clone().into_iter())
+        self.files.files()
     }
 }
 